@@ -1,37 +1,73 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 
+use binance::model::*;
 use binance::websockets::*;
+use serde::Deserialize;
 
 use core::time::Duration;
 
-fn criterion_benchmark(c: &mut Criterion) {
-    let mut group = c.benchmark_group("websockets-decoder");
+// Decoder used before events were dispatched by type, kept as a baseline
+#[allow(dead_code, clippy::large_enum_variant)]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum UntaggedEvents {
+    DayTickerEventAll(Vec<DayTickerEvent>),
+    WindowTickerEventAll(Vec<WindowTickerEvent>),
+    BalanceUpdateEvent(BalanceUpdateEvent),
+    DayTickerEvent(DayTickerEvent),
+    WindowTickerEvent(WindowTickerEvent),
+    BookTickerEvent(BookTickerEvent),
+    AccountUpdateEvent(AccountUpdateEvent),
+    OrderTradeEvent(OrderTradeEvent),
+    AggrTradesEvent(AggrTradesEvent),
+    TradeEvent(TradeEvent),
+    KlineEvent(KlineEvent),
+    OrderBook(OrderBook),
+    DepthOrderBookEvent(DepthOrderBookEvent),
+}
 
-    let all_symbols_json = reqwest::blocking::get("https://api.binance.com/api/v3/ticker/price")
-        .unwrap()
-        .text()
-        .unwrap();
+fn untagged_decode(msg: &str) -> Option<UntaggedEvents> {
+    let value: serde_json::Value = serde_json::from_str(msg).unwrap();
+    if let Some(data) = value.get("data") {
+        return untagged_decode(&data.to_string());
+    }
+    serde_json::from_value(value).ok()
+}
 
-    let btc_symbol_json =
-        reqwest::blocking::get("https://api.binance.com/api/v3/ticker/price?symbol=BTCUSDT")
-            .unwrap()
-            .text()
-            .unwrap();
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("websockets-decoder");
 
-    let mut web_socket_subscribed: WebSockets<'_> =
-        WebSockets::new(|_event: WebsocketEvent| Ok(()));
-    web_socket_subscribed.connect("!ticker@arr").unwrap();
+    let fixtures = [
+        (
+            "all symbols",
+            include_str!("../tests/mocks/websockets/all_tickers.json"),
+        ),
+        (
+            "partial depth",
+            include_str!("../tests/mocks/websockets/partial_depth.json"),
+        ),
+        (
+            "book ticker",
+            include_str!("../tests/mocks/websockets/book_ticker.json"),
+        ),
+        (
+            "combined trade",
+            include_str!("../tests/mocks/websockets/combined_trade.json"),
+        ),
+    ];
 
     let mut web_socket: WebSockets<'_> = WebSockets::new(|_event: WebsocketEvent| Ok(()));
 
     group.sample_size(200);
-    group.measurement_time(Duration::new(35, 0));
-    group.bench_function("handle_msg all symbols", |b| {
-        b.iter(|| web_socket_subscribed.test_handle_msg(&all_symbols_json));
-    });
-    group.bench_function("handle_msg BTCUSDT symbol", |b| {
-        b.iter(|| web_socket.test_handle_msg(&btc_symbol_json));
-    });
+    group.measurement_time(Duration::new(10, 0));
+    for (name, fixture) in fixtures.iter() {
+        group.bench_function(format!("handle_msg {}", name), |b| {
+            b.iter(|| web_socket.test_handle_msg(fixture).unwrap());
+        });
+        group.bench_function(format!("untagged {}", name), |b| {
+            b.iter(|| untagged_decode(fixture).unwrap());
+        });
+    }
     group.finish();
 }
 
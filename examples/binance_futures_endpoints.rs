@@ -66,6 +66,11 @@ fn market_data() {
         Err(e) => println!("Error: {}", e),
     }
 
+    match market.get_mark_price_klines("btcusdt", "5m", 10, None, None) {
+        Ok(answer) => println!("First mark price kline: {:?}", answer[0]),
+        Err(e) => println!("Error: {}", e),
+    }
+
     match market.get_24h_price_stats("btcusdt") {
         Ok(answer) => println!("24hr price stats: {:?}", answer),
         Err(e) => println!("Error: {}", e),
@@ -91,6 +96,21 @@ fn market_data() {
         Err(e) => println!("Error: {}", e),
     }
 
+    match market.get_premium_index("btcusdt") {
+        Ok(answer) => println!("Premium index: {:?}", answer),
+        Err(e) => println!("Error: {}", e),
+    }
+
+    match market.get_funding_rate_history("btcusdt", None, None, 10) {
+        Ok(answer) => println!("Latest fundings: {:?}", answer),
+        Err(e) => println!("Error: {}", e),
+    }
+
+    match market.get_funding_info() {
+        Ok(answer) => println!("Funding info: {:?}", answer),
+        Err(e) => println!("Error: {}", e),
+    }
+
     match market.get_all_liquidation_orders() {
         Ok(LiquidationOrders::AllLiquidationOrders(answer)) => {
             println!("First liquidation order: {:?}", answer[0]);
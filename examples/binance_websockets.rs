@@ -47,20 +47,17 @@ fn user_stream_websocket() {
 
         let mut web_socket: WebSockets<'_> = WebSockets::new(|event: WebsocketEvent| {
             match event {
-                WebsocketEvent::AccountUpdate(account_update) => {
-                    for balance in &account_update.data.balances {
+                WebsocketEvent::AccountPosition(account_position) => {
+                    for balance in &account_position.balances {
                         println!(
-                            "Asset: {}, wallet_balance: {}, cross_wallet_balance: {}, balance: {}",
-                            balance.asset,
-                            balance.wallet_balance,
-                            balance.cross_wallet_balance,
-                            balance.balance_change
+                            "Asset: {}, free: {}, locked: {}",
+                            balance.asset, balance.free, balance.locked
                         );
                     }
                 }
                 WebsocketEvent::OrderTrade(trade) => {
                     println!(
-                        "Symbol: {}, Side: {}, Price: {}, Execution Type: {}",
+                        "Symbol: {}, Side: {}, Price: {}, Execution Type: {:?}",
                         trade.symbol, trade.side, trade.price, trade.execution_type
                     );
                 }
@@ -17,7 +17,7 @@ pub struct Account {
     pub recv_window: u64,
 }
 
-struct OrderRequest {
+pub(crate) struct OrderRequest {
     pub symbol: String,
     pub qty: f64,
     pub price: f64,
@@ -755,7 +755,7 @@ impl Account {
             .get_signed(API::Spot(Spot::MyTrades), Some(request))
     }
 
-    fn build_order(&self, order: OrderRequest) -> BTreeMap<String, String> {
+    pub(crate) fn build_order(&self, order: OrderRequest) -> BTreeMap<String, String> {
         let mut order_parameters: BTreeMap<String, String> = BTreeMap::new();
 
         order_parameters.insert("symbol".into(), order.symbol);
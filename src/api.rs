@@ -5,11 +5,13 @@ use crate::futures::account::FuturesAccount;
 use crate::futures::general::FuturesGeneral;
 use crate::futures::market::FuturesMarket;
 use crate::futures::userstream::FuturesUserStream;
+use crate::futures::ws_api::FuturesWsApi;
 use crate::general::General;
 use crate::market::Market;
 use crate::portfolio_margin::account::PortfolioMarginAccount;
 use crate::savings::Savings;
 use crate::userstream::UserStream;
+use crate::ws_api::WsApi;
 
 use super::futures_cm::market::FuturesCMMarket;
 
@@ -34,8 +36,11 @@ pub enum Spot {
     HistoricalTrades,
     AggTrades,
     Klines,
+    UiKlines,
     AvgPrice,
     Ticker24hr,
+    Ticker,
+    TickerTradingDay,
     Price,
     BookTicker,
     Order,
@@ -56,6 +61,7 @@ pub enum Sapi {
     AssetDetail,
     DepositAddress,
     SpotFuturesTransfer,
+    UserDataStream,
 }
 
 pub enum Futures {
@@ -70,8 +76,10 @@ pub enum Futures {
     ContinuousKlines,
     IndexPriceKlines,
     MarkPriceKlines,
+    PremiumIndexKlines,
     PremiumIndex,
     FundingRate,
+    FundingInfo,
     Ticker24hr,
     TickerPrice,
     BookTicker,
@@ -102,6 +110,7 @@ pub enum Futures {
 
 pub enum FuturesCM {
     Klines,
+    UserDataStream,
 }
 
 pub enum PortfolioMargin {
@@ -121,6 +130,7 @@ pub enum PortfolioMargin {
     CancelAllOpenOrdersCM,
     CancelAllConditionalOpenOrdersUM,
     CancelAllConditionalOpenOrdersCM,
+    UserDataStream,
 }
 
 impl From<API> for String {
@@ -135,8 +145,11 @@ impl From<API> for String {
                 Spot::HistoricalTrades => "/api/v3/historicalTrades",
                 Spot::AggTrades => "/api/v3/aggTrades",
                 Spot::Klines => "/api/v3/klines",
+                Spot::UiKlines => "/api/v3/uiKlines",
                 Spot::AvgPrice => "/api/v3/avgPrice",
                 Spot::Ticker24hr => "/api/v3/ticker/24hr",
+                Spot::Ticker => "/api/v3/ticker",
+                Spot::TickerTradingDay => "/api/v3/ticker/tradingDay",
                 Spot::Price => "/api/v3/ticker/price",
                 Spot::BookTicker => "/api/v3/ticker/bookTicker",
                 Spot::Order => "/api/v3/order",
@@ -156,6 +169,7 @@ impl From<API> for String {
                 Sapi::AssetDetail => "/sapi/v1/asset/assetDetail",
                 Sapi::DepositAddress => "/sapi/v1/capital/deposit/address",
                 Sapi::SpotFuturesTransfer => "/sapi/v1/futures/transfer",
+                Sapi::UserDataStream => "/sapi/v1/userDataStream",
             },
             API::Futures(route) => match route {
                 Futures::Ping => "/fapi/v1/ping",
@@ -169,8 +183,10 @@ impl From<API> for String {
                 Futures::ContinuousKlines => "/fapi/v1/continuousKlines",
                 Futures::IndexPriceKlines => "/fapi/v1/indexPriceKlines",
                 Futures::MarkPriceKlines => "/fapi/v1/markPriceKlines",
+                Futures::PremiumIndexKlines => "/fapi/v1/premiumIndexKlines",
                 Futures::PremiumIndex => "/fapi/v1/premiumIndex",
                 Futures::FundingRate => "/fapi/v1/fundingRate",
+                Futures::FundingInfo => "/fapi/v1/fundingInfo",
                 Futures::Ticker24hr => "/fapi/v1/ticker/24hr",
                 Futures::TickerPrice => "/fapi/v1/ticker/price",
                 Futures::BookTicker => "/fapi/v1/ticker/bookTicker",
@@ -200,6 +216,7 @@ impl From<API> for String {
             },
             API::FuturesCM(route) => match route {
                 FuturesCM::Klines => "/dapi/v1/klines",
+                FuturesCM::UserDataStream => "/dapi/v1/listenKey",
             },
             API::PortfolioMargin(route) => match route {
                 PortfolioMargin::Account => "/papi/v1/account",
@@ -222,6 +239,7 @@ impl From<API> for String {
                 PortfolioMargin::CancelAllConditionalOpenOrdersCM => {
                     "/papi/v1/cm/conditional/allOpenOrders"
                 }
+                PortfolioMargin::UserDataStream => "/papi/v1/listenKey",
             },
         })
     }
@@ -424,3 +442,33 @@ impl Binance for FuturesCMMarket {
         }
     }
 }
+
+impl<'a> Binance for WsApi<'a> {
+    fn new(api_key: Option<String>, secret_key: Option<String>) -> Self {
+        Self::new_with_config(api_key, secret_key, &Config::default())
+    }
+
+    fn new_with_config(
+        api_key: Option<String>, secret_key: Option<String>, config: &Config,
+    ) -> Self {
+        WsApi::new_with_account(
+            Account::new_with_config(api_key, secret_key, config),
+            config.ws_api_endpoint.clone(),
+        )
+    }
+}
+
+impl<'a> Binance for FuturesWsApi<'a> {
+    fn new(api_key: Option<String>, secret_key: Option<String>) -> Self {
+        Self::new_with_config(api_key, secret_key, &Config::default())
+    }
+
+    fn new_with_config(
+        api_key: Option<String>, secret_key: Option<String>, config: &Config,
+    ) -> Self {
+        FuturesWsApi::new_with_account(
+            FuturesAccount::new_with_config(api_key, secret_key, config),
+            config.futures_ws_api_endpoint.clone(),
+        )
+    }
+}
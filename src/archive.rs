@@ -0,0 +1,286 @@
+use crate::errors::Result;
+use crate::futures::model::Trade;
+use crate::model::{AggTrade, KlineSummary};
+use error_chain::bail;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Lines, Read};
+use std::path::Path;
+
+// Readers for the public data archives of https://data.binance.vision
+//
+// Files can be read either as downloaded (.zip) or extracted (.csv), for spot,
+// USD-M and COIN-M futures. Futures files start with a header row, spot ones do
+// not, and spot timestamps are in microseconds since 2025-01-01: they are all
+// converted to milliseconds, like the REST API returns them.
+//
+// The `iter_*` functions parse the rows as they are read, the `read_*` ones
+// collect them. Zip archives are decompressed in memory.
+
+// Timestamps above this cannot be in milliseconds (it is in year 2286)
+const MICROSECONDS_THRESHOLD: u64 = 10_000_000_000_000;
+
+/// Reads a klines archive (`<symbol>-<interval>-<date>.zip`).
+pub fn read_klines<P>(path: P) -> Result<Vec<KlineSummary>>
+where
+    P: AsRef<Path>,
+{
+    iter_klines(path)?.collect()
+}
+
+pub fn iter_klines<P>(path: P) -> Result<ArchiveRows<KlineSummary>>
+where
+    P: AsRef<Path>,
+{
+    ArchiveRows::open(path.as_ref(), 11, |row, _| {
+        Ok(KlineSummary {
+            open_time: to_milliseconds(row[0])? as i64,
+            open: row[1].into(),
+            high: row[2].into(),
+            low: row[3].into(),
+            close: row[4].into(),
+            volume: row[5].into(),
+            close_time: to_milliseconds(row[6])? as i64,
+            quote_asset_volume: row[7].into(),
+            number_of_trades: row[8].parse()?,
+            taker_buy_base_asset_volume: row[9].into(),
+            taker_buy_quote_asset_volume: row[10].into(),
+        })
+    })
+}
+
+/// Reads an aggTrades archive (`<symbol>-aggTrades-<date>.zip`).
+/// Futures archives have no best match column, all their trades are best matches.
+pub fn read_agg_trades<P>(path: P) -> Result<Vec<AggTrade>>
+where
+    P: AsRef<Path>,
+{
+    iter_agg_trades(path)?.collect()
+}
+
+pub fn iter_agg_trades<P>(path: P) -> Result<ArchiveRows<AggTrade>>
+where
+    P: AsRef<Path>,
+{
+    ArchiveRows::open(path.as_ref(), 7, |row, _| {
+        Ok(AggTrade {
+            agg_id: row[0].parse()?,
+            price: row[1].parse()?,
+            qty: row[2].parse()?,
+            first_id: row[3].parse()?,
+            last_id: row[4].parse()?,
+            time: to_milliseconds(row[5])?,
+            maker: to_bool(row[6])?,
+            best_match: match row.get(7) {
+                Some(value) => to_bool(value)?,
+                None => true,
+            },
+        })
+    })
+}
+
+/// Reads a spot or USD-M futures trades archive (`<symbol>-trades-<date>.zip`), see
+/// `read_coin_trades` for COIN-M ones.
+pub fn read_trades<P>(path: P) -> Result<Vec<Trade>>
+where
+    P: AsRef<Path>,
+{
+    iter_trades(path)?.collect()
+}
+
+pub fn iter_trades<P>(path: P) -> Result<ArchiveRows<Trade>>
+where
+    P: AsRef<Path>,
+{
+    ArchiveRows::open(path.as_ref(), 6, |row, header| {
+        if is_coin_trades(header) {
+            bail!("COIN-M trades are read with read_coin_trades");
+        }
+        Ok(Trade {
+            id: row[0].parse()?,
+            price: row[1].parse()?,
+            qty: row[2].parse()?,
+            quote_qty: row[3].parse()?,
+            time: to_milliseconds(row[4])?,
+            is_buyer_maker: to_bool(row[5])?,
+        })
+    })
+}
+
+/// A trade of a COIN-M futures trades archive, whose quantity is a number of contracts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoinTrade {
+    pub id: u64,
+    pub price: f64,
+    pub qty: f64,
+    // Base asset quantity, instead of the quote one of the other markets
+    pub base_qty: f64,
+    pub time: u64,
+    pub is_buyer_maker: bool,
+}
+
+/// Reads a COIN-M futures trades archive (`<symbol>-trades-<date>.zip`).
+pub fn read_coin_trades<P>(path: P) -> Result<Vec<CoinTrade>>
+where
+    P: AsRef<Path>,
+{
+    iter_coin_trades(path)?.collect()
+}
+
+pub fn iter_coin_trades<P>(path: P) -> Result<ArchiveRows<CoinTrade>>
+where
+    P: AsRef<Path>,
+{
+    ArchiveRows::open(path.as_ref(), 6, |row, header| {
+        if !is_coin_trades(header) {
+            bail!("Spot and USD-M futures trades are read with read_trades");
+        }
+        Ok(CoinTrade {
+            id: row[0].parse()?,
+            price: row[1].parse()?,
+            qty: row[2].parse()?,
+            base_qty: row[3].parse()?,
+            time: to_milliseconds(row[4])?,
+            is_buyer_maker: to_bool(row[5])?,
+        })
+    })
+}
+
+// COIN-M archives have a base asset quantity column
+fn is_coin_trades(header: Option<&[String]>) -> bool {
+    // Not `is_some_and`, which is more recent than the supported Rust version
+    matches!(header.and_then(|header| header.get(3)), Some(column) if column == "base_qty")
+}
+
+/// Checks a downloaded archive against its `.CHECKSUM` file, expected next to it.
+pub fn verify_checksum<P>(path: P) -> Result<()>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let mut checksum_path = path.as_os_str().to_owned();
+    checksum_path.push(".CHECKSUM");
+
+    // Formatted as "<sha256>  <file name>"
+    let mut checksum = String::new();
+    File::open(&checksum_path)?.read_to_string(&mut checksum)?;
+    let expected = match checksum.split_whitespace().next() {
+        Some(expected) => expected.to_lowercase(),
+        None => bail!(format!("Empty checksum file for {}", path.display())),
+    };
+
+    let mut hasher = Sha256::new();
+    let mut file = File::open(path)?;
+    let mut buffer = [0; 8192];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    let actual = hex::encode(hasher.finalize());
+
+    if actual != expected {
+        bail!(format!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            path.display(),
+            expected,
+            actual
+        ));
+    }
+    Ok(())
+}
+
+type ParseRow<T> = fn(&[&str], Option<&[String]>) -> Result<T>;
+
+/// The rows of an archive, parsed as they are read.
+pub struct ArchiveRows<T> {
+    lines: Lines<Box<dyn BufRead>>,
+    columns: usize,
+    // Column names of the futures archives, spot ones have no header row
+    header: Option<Vec<String>>,
+    index: usize,
+    parse: ParseRow<T>,
+}
+
+impl<T> ArchiveRows<T> {
+    fn open(path: &Path, columns: usize, parse: ParseRow<T>) -> Result<ArchiveRows<T>> {
+        let file = File::open(path)?;
+        let is_zip =
+            matches!(path.extension(), Some(extension) if extension.eq_ignore_ascii_case("zip"));
+
+        let reader: Box<dyn BufRead> = if is_zip {
+            let mut archive = zip::ZipArchive::new(file)?;
+            let mut csv = Vec::new();
+            archive.by_index(0)?.read_to_end(&mut csv)?;
+            Box::new(Cursor::new(csv))
+        } else {
+            Box::new(BufReader::new(file))
+        };
+
+        Ok(ArchiveRows {
+            lines: reader.lines(),
+            columns,
+            header: None,
+            index: 0,
+            parse,
+        })
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<Option<T>> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+
+        let row: Vec<&str> = line.split(',').collect();
+        // Header row of the futures archives
+        if self.index == 1 && row[0].parse::<u64>().is_err() {
+            self.header = Some(row.iter().map(|column| column.to_string()).collect());
+            return Ok(None);
+        }
+        if row.len() < self.columns {
+            bail!(format!("Invalid archive row {}: {}", self.index, line));
+        }
+        (self.parse)(&row, self.header.as_deref()).map(Some)
+    }
+}
+
+impl<T> Iterator for ArchiveRows<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            self.index += 1;
+            match self.parse_line(&line) {
+                Ok(Some(row)) => return Some(Ok(row)),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+fn to_milliseconds(value: &str) -> Result<u64> {
+    let timestamp: u64 = value.parse()?;
+    if timestamp >= MICROSECONDS_THRESHOLD {
+        Ok(timestamp / 1_000)
+    } else {
+        Ok(timestamp)
+    }
+}
+
+fn to_bool(value: &str) -> Result<bool> {
+    if value.eq_ignore_ascii_case("true") {
+        Ok(true)
+    } else if value.eq_ignore_ascii_case("false") {
+        Ok(false)
+    } else {
+        bail!(format!("Invalid boolean {}", value))
+    }
+}
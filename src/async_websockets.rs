@@ -0,0 +1,225 @@
+use crate::config::Config;
+use crate::errors::Result;
+use crate::futures::websockets::{
+    self as futures_websockets, FuturesMarket, FuturesWebsocketAPI, FuturesWebsocketEvent,
+};
+use crate::streams::{join_stream_names, StreamMarket, ToStreamName};
+use crate::websockets::{self, WebsocketAPI, WebsocketEvent};
+use error_chain::bail;
+use futures_util::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tokio::net::TcpStream;
+use url::Url;
+
+type Parser<E> = fn(&str) -> Result<Option<E>>;
+
+/// Websocket events as an async `Stream`.
+///
+/// Pings are answered while the stream is polled, a close frame or a read error is
+/// returned once as an error and ends the stream. Dropping the stream closes the
+/// connection, so it can be used in `select!` like any other future source.
+pub struct EventStream<E> {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    parse: Parser<E>,
+    closed: bool,
+}
+
+pub type WebsocketStream = EventStream<WebsocketEvent>;
+pub type FuturesWebsocketStream = EventStream<FuturesWebsocketEvent>;
+
+impl WebsocketStream {
+    pub async fn connect<S>(subscription: &S) -> Result<WebsocketStream>
+    where
+        S: ToStreamName + ?Sized,
+    {
+        let subscription = subscription.to_stream_name(StreamMarket::Spot)?;
+        connect_wss(
+            &WebsocketAPI::Default.params(&subscription),
+            parse_spot_event,
+        )
+        .await
+    }
+
+    pub async fn connect_with_config<S>(
+        subscription: &S, config: &Config,
+    ) -> Result<WebsocketStream>
+    where
+        S: ToStreamName + ?Sized,
+    {
+        let subscription = subscription.to_stream_name(StreamMarket::Spot)?;
+        connect_wss(
+            &WebsocketAPI::Custom(config.ws_endpoint.clone()).params(&subscription),
+            parse_spot_event,
+        )
+        .await
+    }
+
+    pub async fn connect_multiple_streams<S>(endpoints: &[S]) -> Result<WebsocketStream>
+    where
+        S: ToStreamName,
+    {
+        let endpoints = join_stream_names(endpoints, StreamMarket::Spot)?;
+        connect_wss(
+            &WebsocketAPI::MultiStream.params(&endpoints),
+            parse_spot_event,
+        )
+        .await
+    }
+
+    pub async fn connect_multiple_streams_with_config<S>(
+        endpoints: &[S], config: &Config,
+    ) -> Result<WebsocketStream>
+    where
+        S: ToStreamName,
+    {
+        let endpoints = join_stream_names(endpoints, StreamMarket::Spot)?;
+        connect_wss(
+            &WebsocketAPI::CustomMultiStream(config.ws_endpoint.clone()).params(&endpoints),
+            parse_spot_event,
+        )
+        .await
+    }
+}
+
+impl FuturesWebsocketStream {
+    pub async fn connect<S>(
+        market: &FuturesMarket, subscription: &S,
+    ) -> Result<FuturesWebsocketStream>
+    where
+        S: ToStreamName + ?Sized,
+    {
+        let subscription = subscription.to_stream_name(market.into())?;
+        connect_wss(
+            &FuturesWebsocketAPI::Default.params(market, &subscription),
+            parse_futures_event,
+        )
+        .await
+    }
+
+    pub async fn connect_with_config<S>(
+        market: &FuturesMarket, subscription: &S, config: &Config,
+    ) -> Result<FuturesWebsocketStream>
+    where
+        S: ToStreamName + ?Sized,
+    {
+        let subscription = subscription.to_stream_name(market.into())?;
+        let url = market.ws_endpoint(config).to_string();
+        connect_wss(
+            &FuturesWebsocketAPI::Custom(url).params(market, &subscription),
+            parse_futures_event,
+        )
+        .await
+    }
+
+    pub async fn connect_multiple_streams<S>(
+        market: &FuturesMarket, endpoints: &[S],
+    ) -> Result<FuturesWebsocketStream>
+    where
+        S: ToStreamName,
+    {
+        let endpoints = join_stream_names(endpoints, market.into())?;
+        connect_wss(
+            &FuturesWebsocketAPI::MultiStream.params(market, &endpoints),
+            parse_futures_event,
+        )
+        .await
+    }
+
+    pub async fn connect_multiple_streams_with_config<S>(
+        market: &FuturesMarket, endpoints: &[S], config: &Config,
+    ) -> Result<FuturesWebsocketStream>
+    where
+        S: ToStreamName,
+    {
+        let endpoints = join_stream_names(endpoints, market.into())?;
+        let url = market.ws_endpoint(config).to_string();
+        connect_wss(
+            &FuturesWebsocketAPI::CustomMultiStream(url).params(market, &endpoints),
+            parse_futures_event,
+        )
+        .await
+    }
+}
+
+// Streams can't send requests, answers to requests are not events
+fn parse_spot_event(msg: &str) -> Result<Option<WebsocketEvent>> {
+    match websockets::parse_event(msg)? {
+        WebsocketEvent::Response(_) => Ok(None),
+        event => Ok(Some(event)),
+    }
+}
+
+fn parse_futures_event(msg: &str) -> Result<Option<FuturesWebsocketEvent>> {
+    match futures_websockets::parse_event(msg)? {
+        FuturesWebsocketEvent::Response(_) => Ok(None),
+        event => Ok(Some(event)),
+    }
+}
+
+impl<E> EventStream<E> {
+    pub async fn disconnect(&mut self) -> Result<()> {
+        self.socket.close(None).await?;
+        Ok(())
+    }
+}
+
+async fn connect_wss<E>(wss: &str, parse: Parser<E>) -> Result<EventStream<E>> {
+    let url = Url::parse(wss)?;
+    match connect_async(url.as_str()).await {
+        Ok((socket, _)) => Ok(EventStream {
+            socket,
+            parse,
+            closed: false,
+        }),
+        Err(e) => bail!(format!("Error during handshake {}", e)),
+    }
+}
+
+impl<E> Stream for EventStream<E> {
+    type Item = Result<E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if self.closed {
+                return Poll::Ready(None);
+            }
+
+            let message = match Pin::new(&mut self.socket).poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => {
+                    self.closed = true;
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(Some(Err(e))) => {
+                    self.closed = true;
+                    return Poll::Ready(Some(Err(e.into())));
+                }
+                Poll::Ready(Some(Ok(message))) => message,
+            };
+
+            match message {
+                Message::Text(msg) => match (self.parse)(&msg) {
+                    Ok(Some(event)) => return Poll::Ready(Some(Ok(event))),
+                    // Not an event
+                    Ok(None) => continue,
+                    Err(e) => {
+                        return Poll::Ready(Some(Err(format!(
+                            "Error on handling stream message: {}",
+                            e
+                        )
+                        .into())))
+                    }
+                },
+                Message::Close(e) => {
+                    self.closed = true;
+                    return Poll::Ready(Some(Err(format!("Disconnected {:?}", e).into())));
+                }
+                // Pongs are sent by tungstenite while reading
+                Message::Ping(_) | Message::Pong(_) | Message::Binary(_) | Message::Frame(_) => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use crate::errors::Result;
+use crate::futures::websockets::FuturesWebsocketEvent;
+use crate::model::{AggrTradesEvent, KlineSummary, TradeEvent};
+use crate::websockets::WebsocketEvent;
+use error_chain::bail;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// How trades are grouped into bars.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BarType {
+    /// Fixed time intervals in milliseconds, aligned on the epoch like Binance klines
+    Time(u64),
+    /// A bar every N trades
+    Tick(u64),
+    /// A bar every time the traded base asset volume reaches the threshold.
+    /// Trades are never split, so the threshold is a minimum: the last trade of
+    /// a bar can take it over the threshold.
+    Volume(f64),
+    /// A bar every time the traded quote asset volume reaches the threshold,
+    /// a minimum like for `Volume`
+    Dollar(f64),
+}
+
+impl BarType {
+    /// Time bars from an interval such as "10s", "2m", "4h" or "1d".
+    pub fn from_interval(interval: &str) -> Result<BarType> {
+        let unit = match interval.chars().last() {
+            Some('s') => 1_000,
+            Some('m') => 60_000,
+            Some('h') => 3_600_000,
+            Some('d') => 86_400_000,
+            Some('w') => 604_800_000,
+            _ => bail!(format!("Invalid interval {}", interval)),
+        };
+        let count: u64 = match interval[..interval.len() - 1].parse() {
+            Ok(count) if count > 0 => count,
+            _ => bail!(format!("Invalid interval {}", interval)),
+        };
+        // Intervals are added to millisecond timestamps, which are i64
+        match count.checked_mul(unit).filter(|&ms| ms <= i64::MAX as u64) {
+            Some(ms) => Ok(BarType::Time(ms)),
+            None => bail!(format!("Invalid interval {}", interval)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Bar {
+    open_time: i64,
+    close_time: i64,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+    quote_volume: f64,
+    number_of_trades: i64,
+    taker_buy_volume: f64,
+    taker_buy_quote_volume: f64,
+}
+
+impl Bar {
+    fn new(open_time: i64, close_time: i64, price: f64) -> Bar {
+        Bar {
+            open_time,
+            close_time,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: 0.0,
+            quote_volume: 0.0,
+            number_of_trades: 0,
+            taker_buy_volume: 0.0,
+            taker_buy_quote_volume: 0.0,
+        }
+    }
+
+    fn from_kline(kline: &KlineSummary) -> Result<Bar> {
+        Ok(Bar {
+            open_time: kline.open_time,
+            close_time: kline.close_time,
+            open: kline.open.parse()?,
+            high: kline.high.parse()?,
+            low: kline.low.parse()?,
+            close: kline.close.parse()?,
+            volume: kline.volume.parse()?,
+            quote_volume: kline.quote_asset_volume.parse()?,
+            number_of_trades: kline.number_of_trades,
+            taker_buy_volume: kline.taker_buy_base_asset_volume.parse()?,
+            taker_buy_quote_volume: kline.taker_buy_quote_asset_volume.parse()?,
+        })
+    }
+
+    fn add(&mut self, trade: &Trade) {
+        self.high = self.high.max(trade.price);
+        self.low = self.low.min(trade.price);
+        self.close = trade.price;
+        self.volume += trade.qty;
+        self.quote_volume += trade.price * trade.qty;
+        self.number_of_trades += trade.count;
+        if !trade.is_buyer_maker {
+            self.taker_buy_volume += trade.qty;
+            self.taker_buy_quote_volume += trade.price * trade.qty;
+        }
+    }
+
+    fn to_kline(&self) -> KlineSummary {
+        KlineSummary {
+            open_time: self.open_time,
+            open: self.open.to_string(),
+            high: self.high.to_string(),
+            low: self.low.to_string(),
+            close: self.close.to_string(),
+            volume: self.volume.to_string(),
+            close_time: self.close_time,
+            quote_asset_volume: self.quote_volume.to_string(),
+            number_of_trades: self.number_of_trades,
+            taker_buy_base_asset_volume: self.taker_buy_volume.to_string(),
+            taker_buy_quote_asset_volume: self.taker_buy_quote_volume.to_string(),
+        }
+    }
+}
+
+struct Trade {
+    price: f64,
+    qty: f64,
+    time: i64,
+    // Number of trades, aggregated trades count all the trades they are made of
+    count: i64,
+    is_buyer_maker: bool,
+}
+
+#[derive(Default)]
+struct SymbolBars {
+    // Open bars, by open time
+    open: BTreeMap<i64, Bar>,
+    // Closed time bars not passed to the handler yet, because it failed
+    closed: VecDeque<Bar>,
+    // Trades before this time belong to bars which were already closed
+    closed_until: i64,
+}
+
+type BarHandler<'a> = Box<dyn FnMut(&str, KlineSummary) -> Result<()> + 'a>;
+
+/// Builds bars from the trade streams (`<symbol>@trade` or `<symbol>@aggTrade`).
+///
+/// Closed bars are passed to the handler with the symbol they belong to. Time bars
+/// close when a trade past their close time (plus the allowed lateness) arrives, or
+/// when `close_expired` is called; the other bar types close as soon as their
+/// threshold is reached.
+pub struct CandleAggregator<'a> {
+    bar_type: BarType,
+    allowed_lateness: i64,
+    bars: HashMap<String, SymbolBars>,
+    late_trades: u64,
+    handler: BarHandler<'a>,
+}
+
+impl<'a> CandleAggregator<'a> {
+    /// Fails for time bars of 0 ms, or longer than the millisecond timestamps can hold.
+    pub fn new<Callback>(bar_type: BarType, handler: Callback) -> Result<CandleAggregator<'a>>
+    where
+        Callback: FnMut(&str, KlineSummary) -> Result<()> + 'a,
+    {
+        if let BarType::Time(interval) = bar_type {
+            if interval == 0 || interval > i64::MAX as u64 {
+                bail!(format!("Invalid time bar interval {}", interval));
+            }
+        }
+        Ok(CandleAggregator {
+            bar_type,
+            allowed_lateness: 0,
+            bars: HashMap::new(),
+            late_trades: 0,
+            handler: Box::new(handler),
+        })
+    }
+
+    /// Keeps time bars open for 'lateness' milliseconds after their close time,
+    /// so that trades delivered out of order still land in the right bar.
+    /// Trades older than every open bar are dropped and counted by `late_trades`.
+    pub fn set_allowed_lateness(&mut self, lateness: u64) {
+        self.allowed_lateness = lateness as i64;
+    }
+
+    // Number of trades dropped because their bar was already closed
+    pub fn late_trades(&self) -> u64 {
+        self.late_trades
+    }
+
+    /// Starts from klines fetched through the REST API. The last kline is
+    /// considered still open and further trades are added to it.
+    pub fn seed<S>(&mut self, symbol: S, klines: &[KlineSummary]) -> Result<()>
+    where
+        S: Into<String>,
+    {
+        if let Some(kline) = klines.last() {
+            let bar = Bar::from_kline(kline)?;
+            let bars = self.bars.entry(symbol.into()).or_default();
+            bars.open.clear();
+            bars.closed_until = bar.open_time;
+            bars.open.insert(bar.open_time, bar);
+        }
+        Ok(())
+    }
+
+    /// The bar currently being built for a symbol.
+    pub fn current<S>(&self, symbol: S) -> Option<KlineSummary>
+    where
+        S: AsRef<str>,
+    {
+        self.bars
+            .get(symbol.as_ref())
+            .and_then(|bars| bars.open.values().next_back())
+            .map(Bar::to_kline)
+    }
+
+    pub fn handle_event(&mut self, event: &WebsocketEvent) -> Result<()> {
+        match event {
+            WebsocketEvent::Trade(trade) => self.handle_trade(trade),
+            WebsocketEvent::AggrTrades(trade) => self.handle_aggr_trade(trade),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn handle_futures_event(&mut self, event: &FuturesWebsocketEvent) -> Result<()> {
+        match event {
+            FuturesWebsocketEvent::Trade(trade) => self.handle_trade(trade),
+            FuturesWebsocketEvent::AggrTrades(trade) => self.handle_aggr_trade(trade),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn handle_trade(&mut self, event: &TradeEvent) -> Result<()> {
+        let trade = Trade {
+            price: event.price.parse()?,
+            qty: event.qty.parse()?,
+            time: event.trade_order_time as i64,
+            count: 1,
+            is_buyer_maker: event.is_buyer_maker,
+        };
+        self.add_trade(&event.symbol, trade)
+    }
+
+    pub fn handle_aggr_trade(&mut self, event: &AggrTradesEvent) -> Result<()> {
+        let trade = Trade {
+            price: event.price.parse()?,
+            qty: event.qty.parse()?,
+            time: event.trade_order_time as i64,
+            count: (event.last_break_trade_id + 1).saturating_sub(event.first_break_trade_id)
+                as i64,
+            is_buyer_maker: event.is_buyer_maker,
+        };
+        self.add_trade(&event.symbol, trade)
+    }
+
+    /// Closes the time bars which can no longer receive trades at 'now'
+    /// (milliseconds since the epoch). Useful when a symbol stops trading.
+    pub fn close_expired(&mut self, now: u64) -> Result<()> {
+        let symbols: Vec<String> = self.bars.keys().cloned().collect();
+        for symbol in symbols {
+            self.close_time_bars(&symbol, now as i64)?;
+        }
+        Ok(())
+    }
+
+    fn add_trade(&mut self, symbol: &str, trade: Trade) -> Result<()> {
+        match self.bar_type {
+            BarType::Time(interval) => self.add_to_time_bar(symbol, trade, interval as i64),
+            _ => self.add_to_threshold_bar(symbol, trade),
+        }
+    }
+
+    fn add_to_time_bar(&mut self, symbol: &str, trade: Trade, interval: i64) -> Result<()> {
+        let open_time = trade.time - trade.time.rem_euclid(interval);
+        let bars = self.bars.entry(symbol.into()).or_default();
+
+        if open_time < bars.closed_until {
+            self.late_trades += 1;
+            return Ok(());
+        }
+
+        bars.open
+            .entry(open_time)
+            .or_insert_with(|| Bar::new(open_time, open_time + interval - 1, trade.price))
+            .add(&trade);
+
+        self.close_time_bars(symbol, trade.time)
+    }
+
+    fn close_time_bars(&mut self, symbol: &str, now: i64) -> Result<()> {
+        let bars = match self.bars.get_mut(symbol) {
+            Some(bars) => bars,
+            None => return Ok(()),
+        };
+
+        while let Some((&open_time, bar)) = bars.open.iter().next() {
+            if bar.close_time + self.allowed_lateness >= now {
+                break;
+            }
+            bars.closed_until = bar.close_time + 1;
+            bars.closed.extend(bars.open.remove(&open_time));
+        }
+
+        // A bar is dropped once the handler took it, the next call retries the others
+        while let Some(bar) = bars.closed.front() {
+            (self.handler)(symbol, bar.to_kline())?;
+            bars.closed.pop_front();
+        }
+        Ok(())
+    }
+
+    fn add_to_threshold_bar(&mut self, symbol: &str, trade: Trade) -> Result<()> {
+        let bars = self.bars.entry(symbol.into()).or_default();
+        let mut bar = match bars.open.keys().next_back().copied() {
+            Some(open_time) => bars.open.remove(&open_time).unwrap(),
+            None => Bar::new(trade.time, trade.time, trade.price),
+        };
+        bar.add(&trade);
+        bar.close_time = bar.close_time.max(trade.time);
+
+        let is_complete = match self.bar_type {
+            BarType::Tick(count) => bar.number_of_trades >= count as i64,
+            BarType::Volume(volume) => bar.volume >= volume,
+            BarType::Dollar(value) => bar.quote_volume >= value,
+            BarType::Time(_) => false,
+        };
+
+        if is_complete {
+            (self.handler)(symbol, bar.to_kline())
+        } else {
+            bars.open.insert(bar.open_time, bar);
+            Ok(())
+        }
+    }
+}
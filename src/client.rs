@@ -124,18 +124,25 @@ impl Client {
         self.handler(response)
     }
 
+    pub(crate) fn api_key(&self) -> &str {
+        &self.api_key
+    }
+
+    // HMAC SHA256 signature of a request
+    pub(crate) fn sign(&self, request: &str) -> String {
+        let mut signed_key = Hmac::<Sha256>::new_from_slice(self.secret_key.as_bytes()).unwrap();
+        signed_key.update(request.as_bytes());
+        hex_encode(signed_key.finalize().into_bytes())
+    }
+
     // Request must be signed
     fn sign_request(&self, endpoint: API, request: Option<String>) -> String {
         if let Some(request) = request {
-            let mut signed_key =
-                Hmac::<Sha256>::new_from_slice(self.secret_key.as_bytes()).unwrap();
-            signed_key.update(request.as_bytes());
-            let signature = hex_encode(signed_key.finalize().into_bytes());
+            let signature = self.sign(&request);
             let request_body: String = format!("{}&signature={}", request, signature);
             format!("{}{}?{}", self.host, String::from(endpoint), request_body)
         } else {
-            let signed_key = Hmac::<Sha256>::new_from_slice(self.secret_key.as_bytes()).unwrap();
-            let signature = hex_encode(signed_key.finalize().into_bytes());
+            let signature = self.sign("");
             let request_body: String = format!("&signature={}", signature);
             format!("{}{}?{}", self.host, String::from(endpoint), request_body)
         }
@@ -2,12 +2,19 @@
 pub struct Config {
     pub rest_api_endpoint: String,
     pub ws_endpoint: String,
+    pub ws_api_endpoint: String,
+    pub sbe_ws_endpoint: String,
 
     pub portfolio_margin_rest_api_endpoint: String,
+    pub portfolio_margin_ws_endpoint: String,
 
     pub futures_rest_api_endpoint: String,
     pub futures_cm_rest_api_endpoint: String,
     pub futures_ws_endpoint: String,
+    pub futures_cm_ws_endpoint: String,
+    pub futures_ws_api_endpoint: String,
+
+    pub options_ws_endpoint: String,
 
     pub recv_window: u64,
 }
@@ -17,12 +24,19 @@ impl Default for Config {
         Self {
             rest_api_endpoint: "https://api.binance.com".into(),
             ws_endpoint: "wss://stream.binance.com/ws".into(),
+            ws_api_endpoint: "wss://ws-api.binance.com/ws-api/v3".into(),
+            sbe_ws_endpoint: "wss://stream-sbe.binance.com:9443/ws".into(),
 
             portfolio_margin_rest_api_endpoint: "https://papi.binance.com".into(),
+            portfolio_margin_ws_endpoint: "wss://fstream.binance.com/pm/ws".into(),
 
             futures_rest_api_endpoint: "https://fapi.binance.com".into(),
             futures_cm_rest_api_endpoint: "https://dapi.binance.com".into(),
             futures_ws_endpoint: "wss://fstream.binance.com/ws".into(),
+            futures_cm_ws_endpoint: "wss://dstream.binance.com/ws".into(),
+            futures_ws_api_endpoint: "wss://ws-fapi.binance.com/ws-fapi/v1".into(),
+
+            options_ws_endpoint: "wss://vstream.binance.com/ws".into(),
 
             recv_window: 5000,
         }
@@ -34,10 +48,21 @@ impl Config {
         Self::default()
             .set_rest_api_endpoint("https://testnet.binance.vision")
             .set_ws_endpoint("wss://testnet.binance.vision/ws")
+            .set_ws_api_endpoint("wss://ws-api.testnet.binance.vision/ws-api/v3")
             .set_portfolio_margin_rest_api_endpoint("https://testnet.binance.vision")
             .set_futures_rest_api_endpoint("https://testnet.binancefuture.com")
             .set_futures_cm_rest_api_endpoint("https://testnet.binancefuture.com")
-            .set_futures_ws_endpoint("https://testnet.binancefuture.com/ws")
+            .set_futures_ws_endpoint("wss://fstream.binancefuture.com/ws")
+            .set_futures_cm_ws_endpoint("wss://dstream.binancefuture.com/ws")
+            .set_futures_ws_api_endpoint("wss://testnet.binancefuture.com/ws-fapi/v1")
+    }
+
+    /// Public market data only, the REST API and streams of `data-api.binance.vision` and
+    /// `data-stream.binance.vision`.
+    pub fn market_data_only() -> Self {
+        Self::default()
+            .set_rest_api_endpoint("https://data-api.binance.vision")
+            .set_ws_endpoint("wss://data-stream.binance.vision/ws")
     }
 
     pub fn set_rest_api_endpoint<T: Into<String>>(mut self, rest_api_endpoint: T) -> Self {
@@ -49,6 +74,16 @@ impl Config {
         self.ws_endpoint = ws_endpoint.into();
         self
     }
+    pub fn set_ws_api_endpoint<T: Into<String>>(mut self, ws_api_endpoint: T) -> Self {
+        self.ws_api_endpoint = ws_api_endpoint.into();
+        self
+    }
+
+    pub fn set_sbe_ws_endpoint<T: Into<String>>(mut self, sbe_ws_endpoint: T) -> Self {
+        self.sbe_ws_endpoint = sbe_ws_endpoint.into();
+        self
+    }
+
     pub fn set_portfolio_margin_rest_api_endpoint<T: Into<String>>(
         mut self, portfolio_margin_rest_api_endpoint: T,
     ) -> Self {
@@ -56,6 +91,13 @@ impl Config {
         self
     }
 
+    pub fn set_portfolio_margin_ws_endpoint<T: Into<String>>(
+        mut self, portfolio_margin_ws_endpoint: T,
+    ) -> Self {
+        self.portfolio_margin_ws_endpoint = portfolio_margin_ws_endpoint.into();
+        self
+    }
+
     pub fn set_futures_rest_api_endpoint<T: Into<String>>(
         mut self, futures_rest_api_endpoint: T,
     ) -> Self {
@@ -75,6 +117,25 @@ impl Config {
         self
     }
 
+    pub fn set_futures_cm_ws_endpoint<T: Into<String>>(
+        mut self, futures_cm_ws_endpoint: T,
+    ) -> Self {
+        self.futures_cm_ws_endpoint = futures_cm_ws_endpoint.into();
+        self
+    }
+
+    pub fn set_futures_ws_api_endpoint<T: Into<String>>(
+        mut self, futures_ws_api_endpoint: T,
+    ) -> Self {
+        self.futures_ws_api_endpoint = futures_ws_api_endpoint.into();
+        self
+    }
+
+    pub fn set_options_ws_endpoint<T: Into<String>>(mut self, options_ws_endpoint: T) -> Self {
+        self.options_ws_endpoint = options_ws_endpoint.into();
+        self
+    }
+
     pub fn set_recv_window(mut self, recv_window: u64) -> Self {
         self.recv_window = recv_window;
         self
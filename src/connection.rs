@@ -0,0 +1,590 @@
+use crate::errors::Result;
+use crate::util::current_millis;
+use crate::event_channel::{self, ChannelConfig, EventReceiver, ReaderHandle};
+use crate::heartbeat::{
+    is_timeout, send_failure, tcp_stream, Heartbeat, HeartbeatConfig, Monitor, ReadOutcome,
+};
+use crate::recorder::{self, Frame, ReplaySpeed, SessionReader, SessionRecorder};
+use crate::reconnect::{sleep_while_running, ConnectionEvent, ReconnectPolicy};
+use crate::subscription::{StreamRequest, StreamResponse, Subscriptions};
+use crate::websockets::{next_connection_id, EventEnvelope};
+use error_chain::bail;
+use url::Url;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use tungstenite::{connect, Message};
+use tungstenite::protocol::WebSocket;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::handshake::client::Response;
+use tungstenite::client::IntoClientRequest;
+use tungstenite::http::HeaderValue;
+
+// Longest a reader thread takes to see `ReaderHandle::stop` on a quiet connection
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+// Longest a rotation reads the previous connection for the frames sent before its close
+const ROTATE_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The events of a market, decoded from the frames of its connections.
+pub trait StreamEvent: Sized {
+    // Requests a connection accepts per second
+    const MESSAGES_PER_SECOND: usize;
+
+    /// Decodes a text frame, with the name of the stream it was received on.
+    fn parse(msg: &str) -> Result<(Option<String>, Self)>;
+
+    /// Decodes a binary frame, with the name of the stream it was received on. Binary
+    /// frames are ignored unless the market sends them.
+    fn parse_binary(_frame: &[u8]) -> Result<(Option<String>, Vec<Self>)> {
+        Ok((None, Vec::new()))
+    }
+
+    fn from_connection(event: ConnectionEvent) -> Self;
+
+    fn response_mut(&mut self) -> Option<&mut StreamResponse>;
+
+    fn event_time(&self) -> Option<u64>;
+}
+
+/// A stream connection of any market, `WebSockets` and `FuturesWebSockets` are this
+/// connection with the events of their market.
+pub struct WebSocketConnection<'a, E> {
+    pub socket: Option<(WebSocket<MaybeTlsStream<TcpStream>>, Response)>,
+    // Last connected url, used to reconnect
+    url: Option<String>,
+    subscriptions: Subscriptions,
+    // Given to every opened connection
+    connection_id: u64,
+    // Records the received frames when set
+    recorder: Option<SessionRecorder>,
+    // Read timeouts, idle detection, pings and latency stats
+    heartbeat: Heartbeat,
+    // Sent as X-MBX-APIKEY when connecting, required by the SBE streams
+    pub(crate) api_key: Option<String>,
+    handler: Box<dyn FnMut(EventEnvelope<E>) -> Result<()> + 'a>,
+}
+
+impl<'a, E: StreamEvent> WebSocketConnection<'a, E> {
+    pub fn new<Callback>(mut handler: Callback) -> WebSocketConnection<'a, E>
+    where
+        Callback: FnMut(E) -> Result<()> + 'a,
+    {
+        Self::new_with_envelope(move |envelope: EventEnvelope<E>| handler(envelope.event))
+    }
+
+    /// Like `new`, but the handler gets every event with the stream it was received on,
+    /// the local receive time and the id of the connection.
+    pub fn new_with_envelope<Callback>(handler: Callback) -> WebSocketConnection<'a, E>
+    where
+        Callback: FnMut(EventEnvelope<E>) -> Result<()> + 'a,
+    {
+        WebSocketConnection {
+            socket: None,
+            url: None,
+            subscriptions: Subscriptions::new(E::MESSAGES_PER_SECOND),
+            connection_id: 0,
+            recorder: None,
+            heartbeat: Heartbeat::new(HeartbeatConfig::default()),
+            api_key: None,
+            handler: Box::new(handler),
+        }
+    }
+
+    pub(crate) fn connect_wss(&mut self, wss: &str) -> Result<()> {
+        self.open(wss)?;
+        self.subscriptions.reset();
+        Ok(())
+    }
+
+    fn open(&mut self, wss: &str) -> Result<()> {
+        let mut request = Url::parse(wss)?.into_client_request()?;
+        if let Some(ref api_key) = self.api_key {
+            match HeaderValue::from_str(api_key) {
+                Ok(api_key) => request.headers_mut().insert("X-MBX-APIKEY", api_key),
+                Err(e) => bail!(format!("Invalid API key: {}", e)),
+            };
+        }
+        match connect(request) {
+            Ok(answer) => {
+                self.heartbeat.connected(answer.0.get_ref())?;
+                self.socket = Some(answer);
+                self.connection_id = next_connection_id();
+                self.url = Some(wss.to_string());
+                self.subscriptions.opened(wss);
+                Ok(())
+            }
+            Err(e) => bail!(format!("Error during handshake {}", e)),
+        }
+    }
+
+    /// Listens to more streams, returns the id of the request. Requests are sent at once,
+    /// or queued when the connection already sent as many messages as Binance accepts
+    /// per second. Queued requests are sent by the next reads, set a read timeout with
+    /// `set_heartbeat` for them to go out on a quiet connection. Requests which could not
+    /// be sent stay queued for the reconnected connection.
+    pub fn subscribe(&mut self, streams: &[String]) -> Result<u64> {
+        let request = self.subscriptions.subscribe(streams)?;
+        self.send_request(request)
+    }
+
+    pub fn unsubscribe(&mut self, streams: &[String]) -> Result<u64> {
+        let request = self.subscriptions.unsubscribe(streams);
+        self.send_request(request)
+    }
+
+    // The streams are returned in a `Response` event
+    pub fn list_subscriptions(&mut self) -> Result<u64> {
+        let request = self.subscriptions.request("LIST_SUBSCRIPTIONS", Vec::new());
+        self.send_request(request)
+    }
+
+    // Only the "combined" property exists, wrapping events with their stream name
+    pub fn set_property(&mut self, property: &str, value: bool) -> Result<u64> {
+        let request = self
+            .subscriptions
+            .request("SET_PROPERTY", vec![property.into(), value.into()]);
+        self.send_request(request)
+    }
+
+    // Streams of the connection, including the pending subscriptions
+    pub fn subscriptions(&self) -> Vec<String> {
+        self.subscriptions.streams()
+    }
+
+    fn send_request(&mut self, request: StreamRequest) -> Result<u64> {
+        if self.socket.is_none() {
+            bail!("Not connected");
+        }
+        let id = request.id;
+        self.subscriptions.enqueue(request);
+        if let Some(reason) = self.send_queued()? {
+            bail!(reason);
+        }
+        Ok(id)
+    }
+
+    // Sends the queued requests the rate limit allows, returns the reason the connection
+    // is lost if a send failed. Requests change the streams only once they were sent.
+    fn send_queued(&mut self) -> Result<Option<String>> {
+        let socket = match self.socket {
+            Some(ref mut socket) => socket,
+            None => return Ok(None),
+        };
+        while let Some(request) = self.subscriptions.next_due() {
+            let text = serde_json::to_string(&request)?;
+            if let Some(reason) = send_failure(socket.0.send(Message::Text(text))) {
+                self.subscriptions.requeue(request);
+                return Ok(Some(reason));
+            }
+            self.subscriptions.sent(request);
+        }
+        Ok(None)
+    }
+
+    // Subscribes a reopened connection to the streams of the previous one
+    fn restore_subscriptions(&mut self) -> Result<()> {
+        self.subscriptions.restore();
+        match self.send_queued()? {
+            Some(reason) => bail!(reason),
+            None => Ok(()),
+        }
+    }
+
+    pub fn disconnect(&mut self) -> Result<()> {
+        if let Some(ref mut socket) = self.socket {
+            socket.0.close(None)?;
+            return Ok(());
+        }
+        bail!("Not able to close the connection");
+    }
+
+    pub fn test_handle_msg(&mut self, msg: &str) -> Result<()> {
+        self.handle_msg(msg)
+    }
+
+    pub fn handle_msg(&mut self, msg: &str) -> Result<()> {
+        let received_at = current_millis();
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.record(received_at, self.connection_id, msg)?;
+        }
+        self.dispatch(msg, received_at)
+    }
+
+    /// Passes the events of a binary frame to the handler, only the SBE streams send them.
+    pub fn handle_binary(&mut self, frame: &[u8]) -> Result<()> {
+        let received_at = current_millis();
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.record_binary(received_at, self.connection_id, frame)?;
+        }
+        self.dispatch_binary(frame, received_at)
+    }
+
+    fn dispatch_binary(&mut self, frame: &[u8], received_at: u64) -> Result<()> {
+        let (stream, events) = E::parse_binary(frame)?;
+        for event in events {
+            self.measure(&stream, event.event_time(), received_at);
+            (self.handler)(EventEnvelope {
+                stream: stream.clone(),
+                received_at,
+                connection_id: self.connection_id,
+                event,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Records every frame received from now on, see `SessionRecorder`.
+    pub fn set_recorder(&mut self, recorder: SessionRecorder) {
+        self.recorder = Some(recorder);
+    }
+
+    pub fn take_recorder(&mut self) -> Option<SessionRecorder> {
+        self.recorder.take()
+    }
+
+    /// Sets the read timeout, idle timeout and client pings of the connections. A silent
+    /// connection is lost, `event_loop` fails and `event_loop_with_reconnect` reconnects.
+    pub fn set_heartbeat(&mut self, config: HeartbeatConfig) -> Result<()> {
+        self.heartbeat.set_config(config);
+        if let Some(ref socket) = self.socket {
+            self.heartbeat.connected(socket.0.get_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Ping round trips and latency of the streams, which can be read from the handler.
+    pub fn monitor(&self) -> Monitor {
+        self.heartbeat.monitor.clone()
+    }
+
+    // Stats of the stream of the event, or of the url if the stream is unknown
+    fn measure(&self, stream: &Option<String>, event_time: Option<u64>, received_at: u64) {
+        let event_time = match event_time {
+            Some(event_time) => event_time,
+            None => return,
+        };
+        let streams;
+        let stream = if let Some(stream) = stream {
+            stream.as_str()
+        } else {
+            streams = self.subscriptions.streams();
+            match (streams.len(), &self.url) {
+                (1, _) => streams[0].as_str(),
+                (_, Some(url)) => url.as_str(),
+                (_, None) => "",
+            }
+        };
+        self.heartbeat
+            .monitor
+            .event(stream, event_time, received_at);
+    }
+
+    /// Passes the frames of a session log to the handler as they were received, with
+    /// their recorded receive time and connection id. Returns how many were replayed.
+    pub fn replay<P: AsRef<Path>>(&mut self, path: P, speed: ReplaySpeed) -> Result<u64> {
+        recorder::replay(SessionReader::open(path)?, speed, |frame| {
+            self.connection_id = frame.connection_id;
+            match frame.frame {
+                Frame::Text(msg) => self.dispatch(&msg, frame.received_at),
+                Frame::Binary(bytes) => self.dispatch_binary(&bytes, frame.received_at),
+            }
+        })
+    }
+
+    fn dispatch(&mut self, msg: &str, received_at: u64) -> Result<()> {
+        let (stream, mut event) = E::parse(msg)?;
+        if let Some(response) = event.response_mut() {
+            self.subscriptions.handle_response(response);
+        }
+        self.measure(&stream, event.event_time(), received_at);
+        (self.handler)(EventEnvelope {
+            stream,
+            received_at,
+            connection_id: self.connection_id,
+            event,
+        })
+    }
+
+    // Passes an event which was not received on a stream to the handler
+    pub(crate) fn emit(&mut self, event: E) -> Result<()> {
+        (self.handler)(EventEnvelope {
+            stream: None,
+            received_at: current_millis(),
+            connection_id: self.connection_id,
+            event,
+        })
+    }
+
+    // Reads until 'running' is cleared, failing when the connection is lost
+    pub(crate) fn read_while_running(&mut self, running: &AtomicBool) -> Result<()> {
+        while running.load(Ordering::Relaxed) {
+            if let Some(reason) = self.read_message()? {
+                bail!(reason);
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `event_loop`, but reconnects to the same streams with a backoff when the
+    /// connection is lost, and replaces it before Binance closes it after 24 hours.
+    /// Connection changes are passed to the handler as `Connection` events.
+    /// Errors returned by the handler still stop the loop.
+    pub fn event_loop_with_reconnect(
+        &mut self, running: &AtomicBool, policy: &ReconnectPolicy,
+    ) -> Result<()> {
+        let mut connected_at = Instant::now();
+        while running.load(Ordering::Relaxed) {
+            let rotating = connected_at.elapsed() >= policy.rotate_after;
+            let lost = if rotating {
+                self.rotate()?
+            } else {
+                self.read_message()?
+            };
+
+            if let Some(reason) = lost {
+                self.reconnect(reason, running, policy)?;
+                connected_at = Instant::now();
+            } else if rotating {
+                connected_at = Instant::now();
+            }
+        }
+        Ok(())
+    }
+
+    // Reads and handles one message, returning why the connection was lost if it was
+    pub(crate) fn read_message(&mut self) -> Result<Option<String>> {
+        match self.poll_message()? {
+            ReadOutcome::Lost(reason) => Ok(Some(reason)),
+            ReadOutcome::Handled | ReadOutcome::Timeout => Ok(None),
+        }
+    }
+
+    // Like `read_message`, telling apart a handled message from a timeout
+    pub(crate) fn poll_message(&mut self) -> Result<ReadOutcome> {
+        let socket = match self.socket {
+            Some(ref mut socket) => socket,
+            None => return Ok(ReadOutcome::Lost("Not connected".into())),
+        };
+        let message = match socket.0.read() {
+            Ok(message) => message,
+            Err(ref e) if is_timeout(e) => {
+                return Ok(ReadOutcome::Timeout.unless_lost(self.check_heartbeat()?))
+            }
+            Err(e) => return Ok(ReadOutcome::Lost(e.to_string())),
+        };
+        self.heartbeat.received();
+
+        match message {
+            Message::Text(msg) => {
+                if let Err(e) = self.handle_msg(&msg) {
+                    bail!(format!("Error on handling stream message: {}", e));
+                }
+            }
+            Message::Binary(frame) => {
+                if let Err(e) = self.handle_binary(&frame) {
+                    bail!(format!("Error on handling stream message: {}", e));
+                }
+            }
+            Message::Ping(payload) => {
+                if let Some(reason) = send_failure(socket.0.send(Message::Pong(payload))) {
+                    return Ok(ReadOutcome::Lost(reason));
+                }
+            }
+            Message::Pong(payload) => self.heartbeat.pong(&payload),
+            Message::Frame(_) => (),
+            Message::Close(e) => return Ok(ReadOutcome::Lost(format!("Disconnected {:?}", e))),
+        }
+        Ok(ReadOutcome::Handled.unless_lost(self.check_heartbeat()?))
+    }
+
+    // Sends a ping if one is due and the queued requests, returns the reason the
+    // connection is lost if it is idle or a send failed
+    fn check_heartbeat(&mut self) -> Result<Option<String>> {
+        if let Some(reason) = self.heartbeat.idle() {
+            return Ok(Some(reason));
+        }
+        if let Some(reason) = self.send_queued()? {
+            return Ok(Some(reason));
+        }
+        if let Some(payload) = self.heartbeat.ping() {
+            if let Some(ref mut socket) = self.socket {
+                if let Some(reason) = send_failure(socket.0.send(Message::Ping(payload))) {
+                    return Ok(Some(reason));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // Caps the read timeout of the connections opened afterwards, so that the reader
+    // wakes up on a quiet connection
+    pub(crate) fn set_max_read_timeout(&mut self, max_read_timeout: Duration) {
+        self.heartbeat.set_max_read_timeout(max_read_timeout);
+    }
+
+    // Opens a new connection before closing the current one
+    pub(crate) fn rotate(&mut self) -> Result<Option<String>> {
+        let url = match self.url.clone() {
+            Some(url) => url,
+            None => bail!("Not able to rotate a connection which was never opened"),
+        };
+
+        let previous = self.socket.take();
+        let result = self.open(&url).and_then(|_| self.restore_subscriptions());
+        if let Some(mut previous) = previous {
+            self.drain(&mut previous.0)?;
+        }
+
+        match result {
+            Ok(()) => {
+                self.emit(E::from_connection(ConnectionEvent::Rotated { url }))?;
+                Ok(None)
+            }
+            Err(e) => Ok(Some(e.to_string())),
+        }
+    }
+
+    // Closes the previous connection of a rotation, handling the frames it still
+    // receives until the server acknowledges the close
+    fn drain(&mut self, previous: &mut WebSocket<MaybeTlsStream<TcpStream>>) -> Result<()> {
+        if previous.close(None).is_err() {
+            return Ok(());
+        }
+        let deadline = Instant::now() + ROTATE_DRAIN_TIMEOUT;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(());
+            }
+            if let Some(stream) = tcp_stream(previous.get_ref()) {
+                stream.set_read_timeout(Some(deadline - now))?;
+            }
+            let handled = match previous.read() {
+                Ok(Message::Text(msg)) => self.handle_msg(&msg),
+                Ok(Message::Binary(frame)) => self.handle_binary(&frame),
+                Ok(_) => Ok(()),
+                // Closed, or no close acknowledgement within the timeout
+                Err(_) => return Ok(()),
+            };
+            if let Err(e) = handled {
+                bail!(format!("Error on handling stream message: {}", e));
+            }
+        }
+    }
+
+    fn reconnect(
+        &mut self, reason: String, running: &AtomicBool, policy: &ReconnectPolicy,
+    ) -> Result<()> {
+        self.disconnected(reason)?;
+
+        let mut attempt = 0;
+        while running.load(Ordering::Relaxed) {
+            attempt += 1;
+            let delay = self.reconnecting(attempt, policy)?;
+            sleep_while_running(delay, running);
+
+            if running.load(Ordering::Relaxed) && self.reopen()? {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    // Drops the lost connection and tells the handler
+    pub(crate) fn disconnected(&mut self, reason: String) -> Result<()> {
+        if self.url.is_none() {
+            bail!("Not able to reconnect a connection which was never opened");
+        }
+        self.socket = None;
+        self.emit(E::from_connection(ConnectionEvent::Disconnected { reason }))
+    }
+
+    // Tells the handler about the attempt, returns the delay to wait before it
+    pub(crate) fn reconnecting(
+        &mut self, attempt: u32, policy: &ReconnectPolicy,
+    ) -> Result<Duration> {
+        if let Some(max_attempts) = policy.max_attempts {
+            if attempt > max_attempts {
+                bail!(format!(
+                    "Not able to reconnect after {} attempts",
+                    max_attempts
+                ));
+            }
+        }
+
+        let delay = policy.backoff(attempt);
+        self.emit(E::from_connection(ConnectionEvent::Reconnecting {
+            attempt,
+            delay: delay.as_millis() as u64,
+        }))?;
+        Ok(delay)
+    }
+
+    // Opens the last connection again with its subscriptions, false if it failed
+    pub(crate) fn reopen(&mut self) -> Result<bool> {
+        let url = match self.url.clone() {
+            Some(url) => url,
+            None => bail!("Not able to reconnect a connection which was never opened"),
+        };
+        if self
+            .open(&url)
+            .and_then(|_| self.restore_subscriptions())
+            .is_err()
+        {
+            return Ok(false);
+        }
+        self.emit(E::from_connection(ConnectionEvent::Connected { url }))?;
+        Ok(true)
+    }
+
+    // Reads return at once when no message is pending, on this and the next connections
+    pub(crate) fn set_nonblocking(&mut self) -> Result<()> {
+        let stream = self.socket.as_ref().map(|socket| socket.0.get_ref());
+        self.heartbeat.set_nonblocking(stream)
+    }
+}
+
+impl<E: StreamEvent + Send + 'static> WebSocketConnection<'static, E> {
+    /// Reads on a new thread the connection opened by 'connect', queueing the events
+    /// for the returned receiver so a slow consumer doesn't hold up the socket.
+    pub fn spawn_reader<F>(
+        config: ChannelConfig, connect: F,
+    ) -> (EventReceiver<EventEnvelope<E>>, ReaderHandle)
+    where
+        F: FnOnce(&mut WebSocketConnection<'static, E>) -> Result<()> + Send + 'static,
+    {
+        let (sender, receiver) = event_channel::channel(config.capacity, config.overflow_policy);
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+
+        let thread = thread::spawn(move || {
+            let mut web_socket =
+                WebSocketConnection::new_with_envelope(move |envelope: EventEnvelope<E>| {
+                    sender.send(envelope)
+                });
+            // Reads wake up to check if the handle stopped the reader
+            web_socket.set_max_read_timeout(STOP_CHECK_INTERVAL);
+            connect(&mut web_socket)?;
+            let result = match config.reconnect_policy {
+                Some(ref policy) => web_socket.event_loop_with_reconnect(&thread_running, policy),
+                None => web_socket.read_while_running(&thread_running),
+            };
+            let _ = web_socket.disconnect();
+            result
+        });
+
+        let stop = event_channel::closer(&receiver);
+        (
+            receiver,
+            ReaderHandle {
+                running,
+                stop,
+                thread,
+            },
+        )
+    }
+}
@@ -0,0 +1,112 @@
+use crate::errors::Result;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use std::borrow::Cow;
+
+// Fields telling what a stream message holds, everything else is skipped
+#[derive(Deserialize)]
+struct Peek<'a> {
+    #[serde(borrow, default)]
+    stream: Option<Cow<'a, str>>,
+    #[serde(borrow, default)]
+    data: Option<&'a RawValue>,
+    #[serde(rename = "e", borrow, default)]
+    event_type: Option<Cow<'a, str>>,
+    #[serde(default)]
+    id: Option<IgnoredAny>,
+    #[serde(rename = "lastUpdateId", default)]
+    last_update_id: Option<IgnoredAny>,
+    #[serde(rename = "u", default)]
+    update_id: Option<IgnoredAny>,
+}
+
+#[derive(Deserialize)]
+struct PeekType<'a> {
+    #[serde(rename = "e", borrow, default)]
+    event_type: Option<Cow<'a, str>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Kind<'a> {
+    // Event with the given 'e' field
+    Event(Cow<'a, str>),
+    // Array of events with the given 'e' field
+    Events(Cow<'a, str>),
+    // Answer to a request sent on the connection
+    Response,
+    // Partial depth, which has no event type
+    OrderBook,
+    // Spot book ticker, which has no event type
+    BookTicker,
+    Unknown,
+}
+
+/// A stream message, with the payload still to be decoded into the type of its kind.
+pub(crate) struct Message<'a> {
+    // Stream name of combined stream messages
+    pub stream: Option<Cow<'a, str>>,
+    pub payload: &'a str,
+    pub kind: Kind<'a>,
+}
+
+// Finds what a message holds without decoding it
+pub(crate) fn peek(msg: &str) -> Result<Message<'_>> {
+    if msg.trim_start().starts_with('[') {
+        let events: Vec<PeekType<'_>> = serde_json::from_str(msg)?;
+        let kind = match events.into_iter().next().and_then(|event| event.event_type) {
+            Some(event_type) => Kind::Events(event_type),
+            None => Kind::Unknown,
+        };
+        return Ok(Message {
+            stream: None,
+            payload: msg,
+            kind,
+        });
+    }
+
+    let peek: Peek<'_> = serde_json::from_str(msg)?;
+    if let Some(data) = peek.data {
+        let mut message = self::peek(data.get())?;
+        if let Some(ref stream) = peek.stream {
+            // Payloads without an event type are recognized by their stream name
+            match message.kind {
+                Kind::OrderBook | Kind::BookTicker | Kind::Unknown => {
+                    message.kind = stream_kind(stream).unwrap_or(message.kind);
+                }
+                _ => (),
+            }
+        }
+        message.stream = peek.stream;
+        return Ok(message);
+    }
+
+    let kind = if let Some(event_type) = peek.event_type {
+        Kind::Event(event_type)
+    } else if peek.id.is_some() {
+        Kind::Response
+    } else if peek.last_update_id.is_some() {
+        Kind::OrderBook
+    } else if peek.update_id.is_some() {
+        Kind::BookTicker
+    } else {
+        Kind::Unknown
+    };
+    Ok(Message {
+        stream: None,
+        payload: msg,
+        kind,
+    })
+}
+
+// Kind of the events of a stream without event type, e.g. "btcusdt@depth20@100ms"
+fn stream_kind(stream: &str) -> Option<Kind<'static>> {
+    let suffix = stream.split('@').nth(1)?;
+    if suffix.starts_with("depth") && suffix.len() > "depth".len() {
+        Some(Kind::OrderBook)
+    } else if suffix == "bookTicker" {
+        Some(Kind::BookTicker)
+    } else {
+        None
+    }
+}
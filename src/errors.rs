@@ -22,9 +22,11 @@ error_chain! {
         InvalidHeaderError(reqwest::header::InvalidHeaderValue);
         IoError(std::io::Error);
         ParseFloatError(std::num::ParseFloatError);
+        ParseIntError(std::num::ParseIntError);
         UrlParserError(url::ParseError);
         Json(serde_json::Error);
         Tungstenite(tungstenite::Error);
         TimestampError(std::time::SystemTimeError);
+        ZipError(zip::result::ZipError);
     }
 }
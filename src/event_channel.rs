@@ -0,0 +1,298 @@
+use crate::errors::Result;
+use crate::reconnect::ReconnectPolicy;
+use error_chain::bail;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// What the reader thread does with an event when the queue is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    // Waits for the consumers, the connection may be dropped if they are too slow
+    Block,
+    // Drops the oldest queued event, counted in `QueueMetrics::dropped`
+    DropOldest,
+    // Stops the reader, which disconnects and returns an error
+    Disconnect,
+}
+
+/// Options of `WebSockets::spawn_reader` and `FuturesWebSockets::spawn_reader`.
+#[derive(Clone, Debug)]
+pub struct ChannelConfig {
+    pub capacity: usize,
+    pub overflow_policy: OverflowPolicy,
+    // Reconnects with `event_loop_with_reconnect` when set
+    pub reconnect_policy: Option<ReconnectPolicy>,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        ChannelConfig {
+            capacity: 1024,
+            overflow_policy: OverflowPolicy::Block,
+            reconnect_policy: None,
+        }
+    }
+}
+
+impl ChannelConfig {
+    pub fn set_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    pub fn set_overflow_policy(mut self, overflow_policy: OverflowPolicy) -> Self {
+        self.overflow_policy = overflow_policy;
+        self
+    }
+
+    pub fn set_reconnect_policy(mut self, reconnect_policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = Some(reconnect_policy);
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueMetrics {
+    // Events waiting to be received
+    pub depth: usize,
+    pub max_depth: usize,
+    pub capacity: usize,
+    // Events queued by the reader
+    pub sent: u64,
+    // Events dropped by OverflowPolicy::DropOldest
+    pub dropped: u64,
+}
+
+struct State<E> {
+    events: VecDeque<E>,
+    metrics: QueueMetrics,
+    receivers: usize,
+    closed: bool,
+}
+
+struct Shared<E> {
+    state: Mutex<State<E>>,
+    changed: Condvar,
+}
+
+impl<E> Shared<E> {
+    fn lock(&self) -> MutexGuard<'_, State<E>> {
+        // A consumer panicking while holding the lock doesn't corrupt the queue
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn close(&self) {
+        self.lock().closed = true;
+        self.changed.notify_all();
+    }
+}
+
+pub(crate) fn channel<E>(
+    capacity: usize, overflow_policy: OverflowPolicy,
+) -> (EventSender<E>, EventReceiver<E>) {
+    // An empty queue could not hold any event
+    let capacity = capacity.max(1);
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            events: VecDeque::with_capacity(capacity),
+            metrics: QueueMetrics {
+                capacity,
+                ..QueueMetrics::default()
+            },
+            receivers: 1,
+            closed: false,
+        }),
+        changed: Condvar::new(),
+    });
+    (
+        EventSender {
+            shared: shared.clone(),
+            overflow_policy,
+        },
+        EventReceiver { shared },
+    )
+}
+
+/// Queues events for the receivers, the channel is closed when it is dropped.
+pub(crate) struct EventSender<E> {
+    shared: Arc<Shared<E>>,
+    overflow_policy: OverflowPolicy,
+}
+
+impl<E> EventSender<E> {
+    pub(crate) fn send(&self, event: E) -> Result<()> {
+        let mut state = self.shared.lock();
+        loop {
+            if state.closed {
+                // Stopped by the reader handle, the event loop ends with the next message
+                return Ok(());
+            }
+            if state.receivers == 0 {
+                bail!("All event receivers were dropped");
+            }
+            if state.events.len() < state.metrics.capacity {
+                break;
+            }
+
+            match self.overflow_policy {
+                OverflowPolicy::Block => {
+                    state = self
+                        .shared
+                        .changed
+                        .wait(state)
+                        .unwrap_or_else(|e| e.into_inner());
+                }
+                OverflowPolicy::DropOldest => {
+                    state.events.pop_front();
+                    state.metrics.dropped += 1;
+                }
+                OverflowPolicy::Disconnect => bail!(format!(
+                    "Event queue is full ({} events)",
+                    state.metrics.capacity
+                )),
+            }
+        }
+
+        state.events.push_back(event);
+        state.metrics.sent += 1;
+        state.metrics.depth = state.events.len();
+        state.metrics.max_depth = state.metrics.max_depth.max(state.metrics.depth);
+        drop(state);
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+}
+
+impl<E> Drop for EventSender<E> {
+    fn drop(&mut self) {
+        self.shared.close();
+    }
+}
+
+/// Receives the events queued by a reader thread.
+///
+/// Receivers can be cloned to share the events between several workers, every event
+/// is received once. The reader stops when all receivers are dropped.
+pub struct EventReceiver<E> {
+    shared: Arc<Shared<E>>,
+}
+
+impl<E> EventReceiver<E> {
+    /// Waits for the next event, None once the reader stopped and the queue is empty.
+    pub fn recv(&self) -> Option<E> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(event) = self.pop(&mut state) {
+                return Some(event);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self
+                .shared
+                .changed
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Like `recv`, but returns None if no event was received within the timeout.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<E> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(event) = self.pop(&mut state) {
+                return Some(event);
+            }
+            let now = Instant::now();
+            if state.closed || now >= deadline {
+                return None;
+            }
+            state = self
+                .shared
+                .changed
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    pub fn try_recv(&self) -> Option<E> {
+        let mut state = self.shared.lock();
+        self.pop(&mut state)
+    }
+
+    // True once the reader stopped, queued events can still be received
+    pub fn is_closed(&self) -> bool {
+        self.shared.lock().closed
+    }
+
+    pub fn metrics(&self) -> QueueMetrics {
+        self.shared.lock().metrics
+    }
+
+    fn pop(&self, state: &mut State<E>) -> Option<E> {
+        let event = state.events.pop_front()?;
+        state.metrics.depth = state.events.len();
+        self.shared.changed.notify_all();
+        Some(event)
+    }
+}
+
+impl<E> Clone for EventReceiver<E> {
+    fn clone(&self) -> Self {
+        self.shared.lock().receivers += 1;
+        EventReceiver {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<E> Drop for EventReceiver<E> {
+    fn drop(&mut self) {
+        self.shared.lock().receivers -= 1;
+        self.shared.changed.notify_all();
+    }
+}
+
+impl<E> Iterator for EventReceiver<E> {
+    type Item = E;
+
+    fn next(&mut self) -> Option<E> {
+        self.recv()
+    }
+}
+
+/// Controls a reader thread started by `spawn_reader`.
+pub struct ReaderHandle {
+    pub(crate) running: Arc<AtomicBool>,
+    pub(crate) stop: Box<dyn Fn() + Send>,
+    pub(crate) thread: JoinHandle<Result<()>>,
+}
+
+impl ReaderHandle {
+    /// Stops the reader and waits for it. Reads of the reader thread time out every
+    /// 100 ms to check if it should stop, so this returns soon on a quiet connection too.
+    pub fn stop(self) -> Result<()> {
+        self.running.store(false, Ordering::Relaxed);
+        (self.stop)();
+        self.join()
+    }
+
+    /// Waits for the reader to stop, returning the error which stopped it if any.
+    pub fn join(self) -> Result<()> {
+        match self.thread.join() {
+            Ok(result) => result,
+            Err(_) => bail!("The websocket reader thread panicked"),
+        }
+    }
+}
+
+// Closes the channel of a reader, used by `ReaderHandle::stop`
+pub(crate) fn closer<E: Send + 'static>(receiver: &EventReceiver<E>) -> Box<dyn Fn() + Send> {
+    let shared = receiver.shared.clone();
+    Box::new(move || shared.close())
+}
@@ -0,0 +1,254 @@
+use crate::errors::Result;
+use crate::futures::general::FuturesGeneral;
+use crate::futures::model as futures_model;
+use crate::general::General;
+use crate::model;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// Fields of a symbol used to index it.
+pub trait SymbolInfo: Clone {
+    fn symbol(&self) -> &str;
+    fn status(&self) -> &str;
+    fn base_asset(&self) -> &str;
+    fn quote_asset(&self) -> &str;
+}
+
+impl SymbolInfo for model::Symbol {
+    fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    fn status(&self) -> &str {
+        &self.status
+    }
+
+    fn base_asset(&self) -> &str {
+        &self.base_asset
+    }
+
+    fn quote_asset(&self) -> &str {
+        &self.quote_asset
+    }
+}
+
+impl SymbolInfo for futures_model::Symbol {
+    fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    fn status(&self) -> &str {
+        &self.status
+    }
+
+    fn base_asset(&self) -> &str {
+        &self.base_asset
+    }
+
+    fn quote_asset(&self) -> &str {
+        &self.quote_asset
+    }
+}
+
+/// Source of the exchange information kept by the cache.
+pub trait ExchangeInfoSource {
+    type Symbol: SymbolInfo;
+
+    fn symbols(&self) -> Result<Vec<Self::Symbol>>;
+}
+
+impl ExchangeInfoSource for General {
+    type Symbol = model::Symbol;
+
+    fn symbols(&self) -> Result<Vec<model::Symbol>> {
+        Ok(self.exchange_info()?.symbols)
+    }
+}
+
+impl ExchangeInfoSource for FuturesGeneral {
+    type Symbol = futures_model::Symbol;
+
+    fn symbols(&self) -> Result<Vec<futures_model::Symbol>> {
+        Ok(self.exchange_info()?.symbols)
+    }
+}
+
+/// Difference between two versions of the exchange information.
+#[derive(Clone, Debug)]
+pub enum SymbolChange<S> {
+    Listed(S),
+    Delisted(S),
+    StatusChanged { symbol: S, previous_status: String },
+}
+
+type ChangeHandler<'a, S> = Box<dyn FnMut(&SymbolChange<S>) -> Result<()> + 'a>;
+
+/// Exchange information downloaded once and refreshed every `refresh_interval`.
+///
+/// Lookups refresh the cache when it is older than the interval, and the
+/// handler set with `on_change` is called with the symbols which were
+/// listed, delisted or changed status since the previous download.
+/// Errors returned by the handler do not hold up the refresh.
+pub struct ExchangeInfoCache<'a, G>
+where
+    G: ExchangeInfoSource,
+{
+    source: G,
+    refresh_interval: Duration,
+    refreshed_at: Option<Instant>,
+    symbols: BTreeMap<String, G::Symbol>,
+    by_base_asset: HashMap<String, Vec<String>>,
+    by_quote_asset: HashMap<String, Vec<String>>,
+    handler: Option<ChangeHandler<'a, G::Symbol>>,
+}
+
+impl<'a, G> ExchangeInfoCache<'a, G>
+where
+    G: ExchangeInfoSource,
+{
+    pub fn new(source: G, refresh_interval: Duration) -> ExchangeInfoCache<'a, G> {
+        ExchangeInfoCache {
+            source,
+            refresh_interval,
+            refreshed_at: None,
+            symbols: BTreeMap::new(),
+            by_base_asset: HashMap::new(),
+            by_quote_asset: HashMap::new(),
+            handler: None,
+        }
+    }
+
+    pub fn on_change<Callback>(&mut self, handler: Callback)
+    where
+        Callback: FnMut(&SymbolChange<G::Symbol>) -> Result<()> + 'a,
+    {
+        self.handler = Some(Box::new(handler));
+    }
+
+    // Downloads the exchange information, even if the cache is still fresh
+    pub fn refresh(&mut self) -> Result<()> {
+        let symbols: BTreeMap<String, G::Symbol> = self
+            .source
+            .symbols()?
+            .into_iter()
+            .map(|symbol| (symbol.symbol().to_string(), symbol))
+            .collect();
+
+        // Nothing to compare with on the first download
+        let changes = match self.refreshed_at {
+            Some(_) => diff(&self.symbols, &symbols),
+            None => Vec::new(),
+        };
+
+        self.by_base_asset = index(&symbols, SymbolInfo::base_asset);
+        self.by_quote_asset = index(&symbols, SymbolInfo::quote_asset);
+        self.symbols = symbols;
+        self.refreshed_at = Some(Instant::now());
+
+        // The new symbols are kept and every change delivered even if the handler
+        // fails, the first of its errors is returned afterwards
+        let mut result = Ok(());
+        if let Some(handler) = self.handler.as_mut() {
+            for change in &changes {
+                if let Err(e) = handler(change) {
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    pub fn symbol<S>(&mut self, symbol: S) -> Result<Option<&G::Symbol>>
+    where
+        S: Into<String>,
+    {
+        self.refresh_if_stale()?;
+        Ok(self.symbols.get(&symbol.into().to_uppercase()))
+    }
+
+    pub fn symbols(&mut self) -> Result<Vec<&G::Symbol>> {
+        self.refresh_if_stale()?;
+        Ok(self.symbols.values().collect())
+    }
+
+    pub fn symbols_by_base_asset<S>(&mut self, asset: S) -> Result<Vec<&G::Symbol>>
+    where
+        S: Into<String>,
+    {
+        self.refresh_if_stale()?;
+        Ok(lookup(
+            &self.symbols,
+            &self.by_base_asset,
+            &asset.into().to_uppercase(),
+        ))
+    }
+
+    pub fn symbols_by_quote_asset<S>(&mut self, asset: S) -> Result<Vec<&G::Symbol>>
+    where
+        S: Into<String>,
+    {
+        self.refresh_if_stale()?;
+        Ok(lookup(
+            &self.symbols,
+            &self.by_quote_asset,
+            &asset.into().to_uppercase(),
+        ))
+    }
+
+    fn refresh_if_stale(&mut self) -> Result<()> {
+        match self.refreshed_at {
+            Some(refreshed_at) if refreshed_at.elapsed() < self.refresh_interval => Ok(()),
+            _ => self.refresh(),
+        }
+    }
+}
+
+fn diff<S>(previous: &BTreeMap<String, S>, current: &BTreeMap<String, S>) -> Vec<SymbolChange<S>>
+where
+    S: SymbolInfo,
+{
+    let mut changes = Vec::new();
+    for (name, symbol) in current {
+        match previous.get(name) {
+            None => changes.push(SymbolChange::Listed(symbol.clone())),
+            Some(old) if old.status() != symbol.status() => {
+                changes.push(SymbolChange::StatusChanged {
+                    symbol: symbol.clone(),
+                    previous_status: old.status().to_string(),
+                });
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, symbol) in previous {
+        if !current.contains_key(name) {
+            changes.push(SymbolChange::Delisted(symbol.clone()));
+        }
+    }
+    changes
+}
+
+fn index<S, F>(symbols: &BTreeMap<String, S>, asset: F) -> HashMap<String, Vec<String>>
+where
+    F: Fn(&S) -> &str,
+{
+    let mut index: HashMap<String, Vec<String>> = HashMap::new();
+    for (name, symbol) in symbols {
+        index
+            .entry(asset(symbol).to_string())
+            .or_default()
+            .push(name.clone());
+    }
+    index
+}
+
+fn lookup<'s, S>(
+    symbols: &'s BTreeMap<String, S>, index: &HashMap<String, Vec<String>>, asset: &str,
+) -> Vec<&'s S> {
+    index
+        .get(asset)
+        .map(|names| names.iter().filter_map(|name| symbols.get(name)).collect())
+        .unwrap_or_default()
+}
@@ -19,6 +19,7 @@ pub struct FuturesAccount {
     pub recv_window: u64,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ContractType {
     Perpetual,
     CurrentMonth,
@@ -398,6 +399,15 @@ impl FuturesAccount {
 
     // Custom order for for professional traders
     pub fn custom_order(&self, order_request: CustomOrderRequest) -> Result<Transaction> {
+        let order = self.build_custom_order(order_request);
+        let request = build_signed_request(order, self.recv_window)?;
+        self.client
+            .post_signed(API::Futures(Futures::Order), request)
+    }
+
+    pub(crate) fn build_custom_order(
+        &self, order_request: CustomOrderRequest,
+    ) -> BTreeMap<String, String> {
         let order = OrderRequest {
             symbol: order_request.symbol,
             side: order_request.side,
@@ -414,10 +424,7 @@ impl FuturesAccount {
             working_type: order_request.working_type,
             price_protect: order_request.price_protect,
         };
-        let order = self.build_order(order);
-        let request = build_signed_request(order, self.recv_window)?;
-        self.client
-            .post_signed(API::Futures(Futures::Order), request)
+        self.build_order(order)
     }
 
     // Custom order for for professional traders
@@ -5,8 +5,13 @@
 - [ ] `Old Trades Lookup (MARKET_DATA)`
 - [x] `Compressed/Aggregate Trades List`
 - [x] `Kline/Candlestick Data`
+- [x] `Continuous Contract Kline/Candlestick Data`
+- [x] `Index Price Kline/Candlestick Data`
+- [x] `Mark Price Kline/Candlestick Data`
+- [x] `Premium Index Kline Data`
 - [x] `Mark Price`
-- [ ] `Get Funding Rate History (MARKET_DATA)`
+- [x] `Get Funding Rate History (MARKET_DATA)`
+- [x] `Get Funding Rate Info`
 - [x] `24hr Ticker Price Change Statistics`
 - [x] `Symbol Price Ticker`
 - [x] `Symbol Order Book Ticker`
@@ -22,10 +27,12 @@
 
 use crate::util::{build_request, build_signed_request};
 use crate::futures::model::{
-    AggTrades, BookTickers, KlineSummary, LiquidationOrders, MarkPrices, OpenInterest,
-    OpenInterestHist, OrderBook, PriceStats, SymbolPrice, Tickers, Trades,
+    AggTrades, BookTickers, FundingInfo, FundingRate, KlineSummary, LiquidationOrders, MarkPrices,
+    OpenInterest, OpenInterestHist, OrderBook, PremiumIndex, PriceStats, SymbolPrice, Tickers,
+    Trades,
 };
 use crate::client::Client;
+use crate::futures::account::ContractType;
 use crate::errors::Result;
 use std::collections::BTreeMap;
 use serde_json::Value;
@@ -37,6 +44,9 @@ use crate::api::Futures;
 // Add limit parameters to functions
 // Implement all functions
 
+// Most funding rates returned by one request
+pub const FUNDING_RATE_LIMIT: u16 = 1000;
+
 #[derive(Clone)]
 pub struct FuturesMarket {
     pub client: Client,
@@ -159,6 +169,121 @@ impl FuturesMarket {
         parameters.insert("symbol".into(), symbol.into());
         parameters.insert("interval".into(), interval.into());
 
+        self.get_kline_family(Futures::Klines, parameters, limit, start_time, end_time)
+    }
+
+    // Klines of a pair ("BTCUSDT") and contract type, over the contracts it rolls to
+    pub fn get_continuous_klines<S1, S2, S3, S4, S5>(
+        &self, pair: S1, contract_type: ContractType, interval: S2, limit: S3, start_time: S4,
+        end_time: S5,
+    ) -> Result<Vec<KlineSummary>>
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+        S3: Into<Option<u16>>,
+        S4: Into<Option<u64>>,
+        S5: Into<Option<u64>>,
+    {
+        let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+
+        parameters.insert("pair".into(), pair.into());
+        parameters.insert("contractType".into(), contract_type.into());
+        parameters.insert("interval".into(), interval.into());
+
+        self.get_kline_family(
+            Futures::ContinuousKlines,
+            parameters,
+            limit,
+            start_time,
+            end_time,
+        )
+    }
+
+    // Klines of the index price of a pair, volumes are 0
+    pub fn get_index_price_klines<S1, S2, S3, S4, S5>(
+        &self, pair: S1, interval: S2, limit: S3, start_time: S4, end_time: S5,
+    ) -> Result<Vec<KlineSummary>>
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+        S3: Into<Option<u16>>,
+        S4: Into<Option<u64>>,
+        S5: Into<Option<u64>>,
+    {
+        let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+
+        parameters.insert("pair".into(), pair.into());
+        parameters.insert("interval".into(), interval.into());
+
+        self.get_kline_family(
+            Futures::IndexPriceKlines,
+            parameters,
+            limit,
+            start_time,
+            end_time,
+        )
+    }
+
+    // Klines of the mark price of a symbol, volumes are 0
+    pub fn get_mark_price_klines<S1, S2, S3, S4, S5>(
+        &self, symbol: S1, interval: S2, limit: S3, start_time: S4, end_time: S5,
+    ) -> Result<Vec<KlineSummary>>
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+        S3: Into<Option<u16>>,
+        S4: Into<Option<u64>>,
+        S5: Into<Option<u64>>,
+    {
+        let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+
+        parameters.insert("symbol".into(), symbol.into());
+        parameters.insert("interval".into(), interval.into());
+
+        self.get_kline_family(
+            Futures::MarkPriceKlines,
+            parameters,
+            limit,
+            start_time,
+            end_time,
+        )
+    }
+
+    // Klines of the premium index of a symbol, volumes are 0
+    pub fn get_premium_index_klines<S1, S2, S3, S4, S5>(
+        &self, symbol: S1, interval: S2, limit: S3, start_time: S4, end_time: S5,
+    ) -> Result<Vec<KlineSummary>>
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+        S3: Into<Option<u16>>,
+        S4: Into<Option<u64>>,
+        S5: Into<Option<u64>>,
+    {
+        let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+
+        parameters.insert("symbol".into(), symbol.into());
+        parameters.insert("interval".into(), interval.into());
+
+        self.get_kline_family(
+            Futures::PremiumIndexKlines,
+            parameters,
+            limit,
+            start_time,
+            end_time,
+        )
+    }
+
+    // Every kline endpoint answers rows of the same layout
+    fn get_kline_family<S3, S4, S5>(
+        &self, route: Futures, mut parameters: BTreeMap<String, String>, limit: S3, start_time: S4,
+        end_time: S5,
+    ) -> Result<Vec<KlineSummary>>
+    where
+        S3: Into<Option<u16>>,
+        S4: Into<Option<u64>>,
+        S5: Into<Option<u64>>,
+    {
         // Add three optional parameters
         if let Some(lt) = limit.into() {
             parameters.insert("limit".into(), format!("{}", lt));
@@ -172,9 +297,7 @@ impl FuturesMarket {
 
         let request = build_request(parameters);
 
-        let data: Vec<Vec<Value>> = self
-            .client
-            .get(API::Futures(Futures::Klines), Some(request))?;
+        let data: Vec<Vec<Value>> = self.client.get(API::Futures(route), Some(request))?;
 
         let klines = data
             .iter()
@@ -244,6 +367,80 @@ impl FuturesMarket {
         self.client.get(API::Futures(Futures::PremiumIndex), None)
     }
 
+    // Mark price, index price and funding rate of ONE symbol
+    pub fn get_premium_index<S>(&self, symbol: S) -> Result<PremiumIndex>
+    where
+        S: Into<String>,
+    {
+        let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+        parameters.insert("symbol".into(), symbol.into());
+        let request = build_request(parameters);
+        self.client
+            .get(API::Futures(Futures::PremiumIndex), Some(request))
+    }
+
+    // Fundings of a symbol, oldest first (Default 100; max 1000). Without a start time
+    // the latest ones are returned.
+    pub fn get_funding_rate_history<S1, S2, S3, S4>(
+        &self, symbol: S1, start_time: S2, end_time: S3, limit: S4,
+    ) -> Result<Vec<FundingRate>>
+    where
+        S1: Into<String>,
+        S2: Into<Option<u64>>,
+        S3: Into<Option<u64>>,
+        S4: Into<Option<u16>>,
+    {
+        let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+        parameters.insert("symbol".into(), symbol.into());
+
+        if let Some(st) = start_time.into() {
+            parameters.insert("startTime".into(), format!("{}", st));
+        }
+        if let Some(et) = end_time.into() {
+            parameters.insert("endTime".into(), format!("{}", et));
+        }
+        if let Some(lt) = limit.into() {
+            parameters.insert("limit".into(), format!("{}", lt));
+        }
+
+        let request = build_request(parameters);
+        self.client
+            .get(API::Futures(Futures::FundingRate), Some(request))
+    }
+
+    // Every funding of a symbol between the times, fetched page by page
+    pub fn get_all_funding_rate_history<S1, S2>(
+        &self, symbol: S1, start_time: u64, end_time: S2,
+    ) -> Result<Vec<FundingRate>>
+    where
+        S1: Into<String>,
+        S2: Into<Option<u64>>,
+    {
+        let symbol = symbol.into();
+        let end_time = end_time.into();
+        let mut fundings: Vec<FundingRate> = Vec::new();
+        let mut start_time = start_time;
+        loop {
+            let page = self.get_funding_rate_history(
+                symbol.clone(),
+                start_time,
+                end_time,
+                FUNDING_RATE_LIMIT,
+            )?;
+            let full = page.len() == usize::from(FUNDING_RATE_LIMIT);
+            fundings.extend(page);
+            match fundings.last() {
+                Some(last) if full => start_time = last.funding_time + 1,
+                _ => return Ok(fundings),
+            }
+        }
+    }
+
+    // Symbols with an adjusted funding rate cap, floor or interval
+    pub fn get_funding_info(&self) -> Result<Vec<FundingInfo>> {
+        self.client.get(API::Futures(Futures::FundingInfo), None)
+    }
+
     pub fn get_all_liquidation_orders(&self) -> Result<LiquidationOrders> {
         self.client.get(API::Futures(Futures::AllForceOrders), None)
     }
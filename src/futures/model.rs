@@ -1,9 +1,9 @@
 use serde::{Deserialize, Serialize};
-use crate::model::{string_or_float, string_or_float_opt, string_or_bool};
+use crate::model::{self, string_or_float, string_or_float_opt, string_or_bool};
 
 pub use crate::model::{
-    Asks, Bids, BookTickers, Filters, KlineSummaries, KlineSummary, RateLimit, ServerTime,
-    SymbolPrice, Tickers,
+    Asks, Bids, BookTickers, EventBalance, EventPosition, Filters, KlineSummaries, KlineSummary,
+    RateLimit, ServerTime, SymbolPrice, Tickers,
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -35,6 +35,28 @@ pub struct Symbol {
     pub time_in_force: Vec<String>,
 }
 
+impl Symbol {
+    // Price increment from the PRICE_FILTER
+    pub fn tick_size(&self) -> Option<f64> {
+        model::tick_size(&self.filters)
+    }
+
+    // Quantity increment from the LOT_SIZE filter
+    pub fn step_size(&self) -> Option<f64> {
+        model::step_size(&self.filters)
+    }
+
+    // Minimum order value from the MIN_NOTIONAL filter
+    pub fn min_notional(&self) -> Option<f64> {
+        model::min_notional(&self.filters)
+    }
+
+    // Maximum number of open orders from the MAX_NUM_ORDERS filter
+    pub fn max_num_orders(&self) -> Option<u16> {
+        model::max_num_orders(&self.filters)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct OrderBook {
@@ -164,6 +186,51 @@ pub struct MarkPrice {
     pub time: u64,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PremiumIndex {
+    pub symbol: String,
+    #[serde(with = "string_or_float")]
+    pub mark_price: f64,
+    #[serde(with = "string_or_float")]
+    pub index_price: f64,
+    // Only meaningful in the last hour before delivery
+    #[serde(with = "string_or_float")]
+    pub estimated_settle_price: f64,
+    // Empty for delivery contracts
+    #[serde(default, with = "string_or_float_opt")]
+    pub last_funding_rate: Option<f64>,
+    #[serde(default, with = "string_or_float_opt")]
+    pub interest_rate: Option<f64>,
+    pub next_funding_time: u64,
+    pub time: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FundingRate {
+    pub symbol: String,
+    #[serde(with = "string_or_float")]
+    pub funding_rate: f64,
+    pub funding_time: u64,
+    // Empty for the oldest fundings
+    #[serde(default, with = "string_or_float_opt")]
+    pub mark_price: Option<f64>,
+}
+
+// Symbols with an adjusted funding rate cap, floor or interval
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FundingInfo {
+    pub symbol: String,
+    #[serde(with = "string_or_float")]
+    pub adjusted_funding_rate_cap: f64,
+    #[serde(with = "string_or_float")]
+    pub adjusted_funding_rate_floor: f64,
+    pub funding_interval_hours: u64,
+    pub disclaimer: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(untagged)]
 pub enum LiquidationOrders {
@@ -612,6 +679,303 @@ pub struct OrderTradeEvent {
     pub order: OrderUpdate,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountUpdateEvent {
+    #[serde(rename = "e")]
+    pub event_type: String,
+
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    #[serde(rename = "T")]
+    pub transaction_time: u64,
+
+    #[serde(rename = "a")]
+    pub data: AccountUpdateData,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountUpdateData {
+    #[serde(rename = "m")]
+    pub reason: AccountUpdateReason,
+
+    #[serde(rename = "B")]
+    pub balances: Vec<EventBalance>,
+
+    #[serde(rename = "P")]
+    pub positions: Vec<EventPosition>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AccountUpdateReason {
+    Deposit,
+    Withdraw,
+    Order,
+    FundingFee,
+    WithdrawReject,
+    Adjustment,
+    InsuranceClear,
+    AdminDeposit,
+    AdminWithdraw,
+    MarginTransfer,
+    MarginTypeChange,
+    AssetTransfer,
+    OptionsPremiumFee,
+    OptionsSettleProfit,
+    AutoExchange,
+    CoinSwapDeposit,
+    CoinSwapWithdraw,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MarginCallEvent {
+    #[serde(rename = "e")]
+    pub event_type: String,
+
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    // Only pushed when crossed positions are at risk
+    #[serde(rename = "cw", default)]
+    pub cross_wallet_balance: Option<String>,
+
+    #[serde(rename = "p")]
+    pub positions: Vec<MarginCallPosition>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MarginCallPosition {
+    #[serde(rename = "s")]
+    pub symbol: String,
+
+    #[serde(rename = "ps")]
+    pub position_side: String,
+
+    #[serde(rename = "pa")]
+    pub position_amount: String,
+
+    #[serde(rename = "mt")]
+    pub margin_type: String,
+
+    #[serde(rename = "iw")]
+    pub isolated_wallet: String,
+
+    #[serde(rename = "mp")]
+    pub mark_price: String,
+
+    #[serde(rename = "up")]
+    pub unrealized_pnl: String,
+
+    #[serde(rename = "mm")]
+    pub maintenance_margin_required: String,
+}
+
+/// Leverage or multi-assets mode change, only one of them is set.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountConfigUpdateEvent {
+    #[serde(rename = "e")]
+    pub event_type: String,
+
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    #[serde(rename = "T")]
+    pub transaction_time: u64,
+
+    #[serde(rename = "ac", default)]
+    pub leverage: Option<LeverageUpdate>,
+
+    #[serde(rename = "ai", default)]
+    pub multi_assets: Option<MultiAssetsUpdate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LeverageUpdate {
+    #[serde(rename = "s")]
+    pub symbol: String,
+
+    #[serde(rename = "l")]
+    pub leverage: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiAssetsUpdate {
+    #[serde(rename = "j")]
+    pub multi_assets_margin: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StrategyUpdateEvent {
+    #[serde(rename = "e")]
+    pub event_type: String,
+
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    #[serde(rename = "T")]
+    pub transaction_time: u64,
+
+    #[serde(rename = "su")]
+    pub strategy: StrategyUpdate,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StrategyUpdate {
+    #[serde(rename = "si")]
+    pub strategy_id: u64,
+
+    #[serde(rename = "st")]
+    pub strategy_type: String,
+
+    #[serde(rename = "ss")]
+    pub strategy_status: String,
+
+    #[serde(rename = "s")]
+    pub symbol: String,
+
+    #[serde(rename = "ut")]
+    pub update_time: u64,
+
+    // 8001: parameters updated, 8002: user canceled, 8003: order updated, 8004: liquidated
+    #[serde(rename = "c")]
+    pub op_code: u16,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GridUpdateEvent {
+    #[serde(rename = "e")]
+    pub event_type: String,
+
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    #[serde(rename = "T")]
+    pub transaction_time: u64,
+
+    #[serde(rename = "gu")]
+    pub grid: GridUpdate,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GridUpdate {
+    #[serde(rename = "si")]
+    pub strategy_id: u64,
+
+    #[serde(rename = "st")]
+    pub strategy_type: String,
+
+    #[serde(rename = "ss")]
+    pub strategy_status: String,
+
+    #[serde(rename = "s")]
+    pub symbol: String,
+
+    #[serde(rename = "r")]
+    pub realized_pnl: String,
+
+    #[serde(rename = "up")]
+    pub unmatched_average_price: String,
+
+    #[serde(rename = "uq")]
+    pub unmatched_qty: String,
+
+    #[serde(rename = "uf")]
+    pub unmatched_fee: String,
+
+    #[serde(rename = "mp")]
+    pub matched_pnl: String,
+
+    #[serde(rename = "ut")]
+    pub update_time: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionalOrderTriggerRejectEvent {
+    #[serde(rename = "e")]
+    pub event_type: String,
+
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    #[serde(rename = "T")]
+    pub transaction_time: u64,
+
+    #[serde(rename = "or")]
+    pub order: RejectedOrder,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedOrder {
+    #[serde(rename = "s")]
+    pub symbol: String,
+
+    #[serde(rename = "i")]
+    pub order_id: u64,
+
+    #[serde(rename = "r")]
+    pub reject_reason: String,
+}
+
+/// Trade of an order, pushed faster than `ORDER_TRADE_UPDATE` with fewer fields.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TradeLiteEvent {
+    #[serde(rename = "e")]
+    pub event_type: String,
+
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    #[serde(rename = "T")]
+    pub transaction_time: u64,
+
+    #[serde(rename = "s")]
+    pub symbol: String,
+
+    #[serde(rename = "q")]
+    pub original_qty: String,
+
+    #[serde(rename = "p")]
+    pub original_price: String,
+
+    #[serde(rename = "m")]
+    pub is_maker: bool,
+
+    #[serde(rename = "c")]
+    pub client_order_id: String,
+
+    #[serde(rename = "S")]
+    pub side: String,
+
+    #[serde(rename = "L")]
+    pub last_filled_price: String,
+
+    #[serde(rename = "l")]
+    pub last_filled_qty: String,
+
+    #[serde(rename = "t")]
+    pub trade_id: u64,
+
+    #[serde(rename = "i")]
+    pub order_id: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Income {
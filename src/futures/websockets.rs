@@ -1,26 +1,28 @@
 use crate::errors::Result;
+use crate::websockets::combined_endpoint;
 use crate::config::Config;
+use crate::connection::{StreamEvent, WebSocketConnection};
+use crate::reconnect::ConnectionEvent;
+use crate::streams::{join_stream_names, ToStreamName};
+use crate::decoder::{self, Kind};
+use crate::subscription::StreamResponse;
 use crate::model::{
-    AccountUpdateEvent, AggrTradesEvent, BookTickerEvent, ContinuousKlineEvent, DayTickerEvent,
-    DepthOrderBookEvent, IndexKlineEvent, IndexPriceEvent, KlineEvent, LiquidationEvent,
-    MarkPriceEvent, MiniTickerEvent, OrderBook, TradeEvent, UserDataStreamExpiredEvent,
+    AccountPositionEvent, AggrTradesEvent, BalanceUpdateEvent, BookTickerEvent,
+    ContinuousKlineEvent, DayTickerEvent, DepthOrderBookEvent, IndexKlineEvent, IndexPriceEvent,
+    KlineEvent, LiquidationEvent, MarkPriceEvent, MiniTickerEvent, OrderBook, OrderTradeEvent,
+    TradeEvent, UserDataStreamExpiredEvent,
 };
 use crate::futures::model;
 use error_chain::bail;
-use url::Url;
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::net::TcpStream;
-use tungstenite::{connect, Message};
-use tungstenite::protocol::WebSocket;
-use tungstenite::stream::MaybeTlsStream;
-use tungstenite::handshake::client::Response;
+use std::sync::atomic::AtomicBool;
 
 #[allow(clippy::all)]
-enum FuturesWebsocketAPI {
+pub(crate) enum FuturesWebsocketAPI {
     Default,
     MultiStream,
     Custom(String),
+    CustomMultiStream(String),
 }
 
 pub enum FuturesMarket {
@@ -29,22 +31,32 @@ pub enum FuturesMarket {
     Vanilla,
 }
 
-impl FuturesWebsocketAPI {
-    fn params(self, market: &FuturesMarket, subscription: &str) -> String {
-        let baseurl = match market {
-            FuturesMarket::USDM => "wss://fstream.binance.com",
-            FuturesMarket::COINM => "wss://dstream.binance.com",
-            FuturesMarket::Vanilla => "wss://vstream.binance.com",
-        };
+impl FuturesMarket {
+    // Raw stream endpoint of the market
+    pub(crate) fn ws_endpoint<'c>(&self, config: &'c Config) -> &'c str {
+        match self {
+            FuturesMarket::USDM => &config.futures_ws_endpoint,
+            FuturesMarket::COINM => &config.futures_cm_ws_endpoint,
+            FuturesMarket::Vanilla => &config.options_ws_endpoint,
+        }
+    }
+}
 
+impl FuturesWebsocketAPI {
+    pub(crate) fn params(self, market: &FuturesMarket, subscription: &str) -> String {
         match self {
             FuturesWebsocketAPI::Default => {
-                format!("{}/ws/{}", baseurl, subscription)
+                let url = market.ws_endpoint(&Config::default()).to_string();
+                FuturesWebsocketAPI::Custom(url).params(market, subscription)
             }
             FuturesWebsocketAPI::MultiStream => {
-                format!("{}/stream?streams={}", baseurl, subscription)
+                let url = market.ws_endpoint(&Config::default()).to_string();
+                FuturesWebsocketAPI::CustomMultiStream(url).params(market, subscription)
+            }
+            FuturesWebsocketAPI::Custom(url) => format!("{}/{}", url, subscription),
+            FuturesWebsocketAPI::CustomMultiStream(url) => {
+                format!("{}?streams={}", combined_endpoint(&url), subscription)
             }
-            FuturesWebsocketAPI::Custom(url) => url,
         }
     }
 }
@@ -52,8 +64,18 @@ impl FuturesWebsocketAPI {
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum FuturesWebsocketEvent {
-    AccountUpdate(AccountUpdateEvent),
+    AccountUpdate(model::AccountUpdateEvent),
     OrderTrade(model::OrderTradeEvent),
+    MarginCall(model::MarginCallEvent),
+    AccountConfigUpdate(model::AccountConfigUpdateEvent),
+    StrategyUpdate(model::StrategyUpdateEvent),
+    GridUpdate(model::GridUpdateEvent),
+    ConditionalOrderTriggerReject(model::ConditionalOrderTriggerRejectEvent),
+    TradeLite(model::TradeLiteEvent),
+    // Margin events of the portfolio margin user data stream
+    MarginOrderTrade(OrderTradeEvent),
+    MarginAccountPosition(AccountPositionEvent),
+    MarginBalanceUpdate(BalanceUpdateEvent),
     AggrTrades(AggrTradesEvent),
     Trade(TradeEvent),
     OrderBook(OrderBook),
@@ -71,144 +93,210 @@ pub enum FuturesWebsocketEvent {
     DepthOrderBook(DepthOrderBookEvent),
     BookTicker(BookTickerEvent),
     UserDataStreamExpiredEvent(UserDataStreamExpiredEvent),
+    Connection(ConnectionEvent),
+    Response(StreamResponse),
+    // Message which is not a known event, as received
+    Unknown(String),
 }
 
-pub struct FuturesWebSockets<'a> {
-    pub socket: Option<(WebSocket<MaybeTlsStream<TcpStream>>, Response)>,
-    handler: Box<dyn FnMut(FuturesWebsocketEvent) -> Result<()> + 'a>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(untagged)]
-enum FuturesEvents {
-    Vec(Vec<DayTickerEvent>),
-    DayTickerEvent(DayTickerEvent),
-    BookTickerEvent(BookTickerEvent),
-    MiniTickerEvent(MiniTickerEvent),
-    VecMiniTickerEvent(Vec<MiniTickerEvent>),
-    AccountUpdateEvent(AccountUpdateEvent),
-    OrderTradeEvent(model::OrderTradeEvent),
-    AggrTradesEvent(AggrTradesEvent),
-    IndexPriceEvent(IndexPriceEvent),
-    MarkPriceEvent(MarkPriceEvent),
-    VecMarkPriceEvent(Vec<MarkPriceEvent>),
-    TradeEvent(TradeEvent),
-    KlineEvent(KlineEvent),
-    ContinuousKlineEvent(ContinuousKlineEvent),
-    IndexKlineEvent(IndexKlineEvent),
-    LiquidationEvent(LiquidationEvent),
-    OrderBook(OrderBook),
-    DepthOrderBookEvent(DepthOrderBookEvent),
-    UserDataStreamExpiredEvent(UserDataStreamExpiredEvent),
-}
-
-impl<'a> FuturesWebSockets<'a> {
-    pub fn new<Callback>(handler: Callback) -> FuturesWebSockets<'a>
-    where
-        Callback: FnMut(FuturesWebsocketEvent) -> Result<()> + 'a,
-    {
-        FuturesWebSockets {
-            socket: None,
-            handler: Box::new(handler),
+impl FuturesWebsocketEvent {
+    /// Time in milliseconds the event was sent by Binance, None for events without it.
+    pub fn event_time(&self) -> Option<u64> {
+        match self {
+            FuturesWebsocketEvent::AccountUpdate(event) => Some(event.event_time),
+            FuturesWebsocketEvent::OrderTrade(event) => Some(event.event_time),
+            FuturesWebsocketEvent::MarginCall(event) => Some(event.event_time),
+            FuturesWebsocketEvent::AccountConfigUpdate(event) => Some(event.event_time),
+            FuturesWebsocketEvent::StrategyUpdate(event) => Some(event.event_time),
+            FuturesWebsocketEvent::GridUpdate(event) => Some(event.event_time),
+            FuturesWebsocketEvent::ConditionalOrderTriggerReject(event) => Some(event.event_time),
+            FuturesWebsocketEvent::TradeLite(event) => Some(event.event_time),
+            FuturesWebsocketEvent::MarginOrderTrade(event) => Some(event.event_time),
+            FuturesWebsocketEvent::MarginAccountPosition(event) => Some(event.event_time),
+            FuturesWebsocketEvent::MarginBalanceUpdate(event) => Some(event.event_time),
+            FuturesWebsocketEvent::AggrTrades(event) => Some(event.event_time),
+            FuturesWebsocketEvent::Trade(event) => Some(event.event_time),
+            FuturesWebsocketEvent::DayTicker(event) => Some(event.event_time),
+            FuturesWebsocketEvent::MiniTicker(event) => Some(event.event_time),
+            FuturesWebsocketEvent::MiniTickerAll(events) => {
+                events.first().map(|event| event.event_time)
+            }
+            FuturesWebsocketEvent::IndexPrice(event) => Some(event.event_time),
+            FuturesWebsocketEvent::MarkPrice(event) => Some(event.event_time),
+            FuturesWebsocketEvent::MarkPriceAll(events) => {
+                events.first().map(|event| event.event_time)
+            }
+            FuturesWebsocketEvent::DayTickerAll(events) => {
+                events.first().map(|event| event.event_time)
+            }
+            FuturesWebsocketEvent::Kline(event) => Some(event.event_time),
+            FuturesWebsocketEvent::ContinuousKline(event) => Some(event.event_time),
+            FuturesWebsocketEvent::IndexKline(event) => Some(event.event_time),
+            FuturesWebsocketEvent::Liquidation(event) => Some(event.event_time),
+            FuturesWebsocketEvent::DepthOrderBook(event) => Some(event.event_time),
+            FuturesWebsocketEvent::UserDataStreamExpiredEvent(event) => Some(event.event_time),
+            FuturesWebsocketEvent::OrderBook(_)
+            | FuturesWebsocketEvent::BookTicker(_)
+            | FuturesWebsocketEvent::Connection(_)
+            | FuturesWebsocketEvent::Response(_)
+            | FuturesWebsocketEvent::Unknown(_) => None,
         }
     }
+}
 
-    pub fn connect(&mut self, market: &FuturesMarket, subscription: &'a str) -> Result<()> {
-        self.connect_wss(&FuturesWebsocketAPI::Default.params(market, subscription))
-    }
+impl StreamEvent for FuturesWebsocketEvent {
+    // Futures connections accept 10 messages per second
+    const MESSAGES_PER_SECOND: usize = 10;
 
-    pub fn connect_with_config(
-        &mut self, market: &FuturesMarket, subscription: &'a str, config: &'a Config,
-    ) -> Result<()> {
-        self.connect_wss(
-            &FuturesWebsocketAPI::Custom(config.ws_endpoint.clone()).params(market, subscription),
-        )
+    fn parse(msg: &str) -> Result<(Option<String>, Self)> {
+        parse_stream_event(msg)
     }
 
-    pub fn connect_multiple_streams(
-        &mut self, market: &FuturesMarket, endpoints: &[String],
-    ) -> Result<()> {
-        self.connect_wss(&FuturesWebsocketAPI::MultiStream.params(market, &endpoints.join("/")))
+    fn from_connection(event: ConnectionEvent) -> Self {
+        FuturesWebsocketEvent::Connection(event)
     }
 
-    fn connect_wss(&mut self, wss: &str) -> Result<()> {
-        let url = Url::parse(wss)?;
-        match connect(url) {
-            Ok(answer) => {
-                self.socket = Some(answer);
-                Ok(())
-            }
-            Err(e) => bail!(format!("Error during handshake {}", e)),
+    fn response_mut(&mut self) -> Option<&mut StreamResponse> {
+        match self {
+            FuturesWebsocketEvent::Response(response) => Some(response),
+            _ => None,
         }
     }
 
-    pub fn disconnect(&mut self) -> Result<()> {
-        if let Some(ref mut socket) = self.socket {
-            socket.0.close(None)?;
-            return Ok(());
-        }
-        bail!("Not able to close the connection");
+    fn event_time(&self) -> Option<u64> {
+        FuturesWebsocketEvent::event_time(self)
     }
+}
+
+pub type FuturesWebSockets<'a> = WebSocketConnection<'a, FuturesWebsocketEvent>;
 
-    pub fn test_handle_msg(&mut self, msg: &str) -> Result<()> {
-        self.handle_msg(msg)
+impl<'a> WebSocketConnection<'a, FuturesWebsocketEvent> {
+    pub fn connect<S>(&mut self, market: &FuturesMarket, subscription: &S) -> Result<()>
+    where
+        S: ToStreamName + ?Sized,
+    {
+        let subscription = subscription.to_stream_name(market.into())?;
+        self.connect_wss(&FuturesWebsocketAPI::Default.params(market, &subscription))
     }
 
-    pub fn handle_msg(&mut self, msg: &str) -> Result<()> {
-        let value: serde_json::Value = serde_json::from_str(msg)?;
+    pub fn connect_with_config<S>(
+        &mut self, market: &FuturesMarket, subscription: &S, config: &Config,
+    ) -> Result<()>
+    where
+        S: ToStreamName + ?Sized,
+    {
+        let subscription = subscription.to_stream_name(market.into())?;
+        let url = market.ws_endpoint(config).to_string();
+        self.connect_wss(&FuturesWebsocketAPI::Custom(url).params(market, &subscription))
+    }
 
-        if let Some(data) = value.get("data") {
-            self.handle_msg(&data.to_string())?;
-            return Ok(());
-        }
+    pub fn connect_multiple_streams<S>(
+        &mut self, market: &FuturesMarket, endpoints: &[S],
+    ) -> Result<()>
+    where
+        S: ToStreamName,
+    {
+        let endpoints = join_stream_names(endpoints, market.into())?;
+        self.connect_wss(&FuturesWebsocketAPI::MultiStream.params(market, &endpoints))
+    }
 
-        if let Ok(events) = serde_json::from_value::<FuturesEvents>(value) {
-            let action = match events {
-                FuturesEvents::Vec(v) => FuturesWebsocketEvent::DayTickerAll(v),
-                FuturesEvents::DayTickerEvent(v) => FuturesWebsocketEvent::DayTicker(v),
-                FuturesEvents::BookTickerEvent(v) => FuturesWebsocketEvent::BookTicker(v),
-                FuturesEvents::MiniTickerEvent(v) => FuturesWebsocketEvent::MiniTicker(v),
-                FuturesEvents::VecMiniTickerEvent(v) => FuturesWebsocketEvent::MiniTickerAll(v),
-                FuturesEvents::AccountUpdateEvent(v) => FuturesWebsocketEvent::AccountUpdate(v),
-                FuturesEvents::OrderTradeEvent(v) => FuturesWebsocketEvent::OrderTrade(v),
-                FuturesEvents::IndexPriceEvent(v) => FuturesWebsocketEvent::IndexPrice(v),
-                FuturesEvents::MarkPriceEvent(v) => FuturesWebsocketEvent::MarkPrice(v),
-                FuturesEvents::VecMarkPriceEvent(v) => FuturesWebsocketEvent::MarkPriceAll(v),
-                FuturesEvents::TradeEvent(v) => FuturesWebsocketEvent::Trade(v),
-                FuturesEvents::ContinuousKlineEvent(v) => FuturesWebsocketEvent::ContinuousKline(v),
-                FuturesEvents::IndexKlineEvent(v) => FuturesWebsocketEvent::IndexKline(v),
-                FuturesEvents::LiquidationEvent(v) => FuturesWebsocketEvent::Liquidation(v),
-                FuturesEvents::KlineEvent(v) => FuturesWebsocketEvent::Kline(v),
-                FuturesEvents::OrderBook(v) => FuturesWebsocketEvent::OrderBook(v),
-                FuturesEvents::DepthOrderBookEvent(v) => FuturesWebsocketEvent::DepthOrderBook(v),
-                FuturesEvents::AggrTradesEvent(v) => FuturesWebsocketEvent::AggrTrades(v),
-                FuturesEvents::UserDataStreamExpiredEvent(v) => {
-                    FuturesWebsocketEvent::UserDataStreamExpiredEvent(v)
-                }
-            };
-            (self.handler)(action)?;
-        }
-        Ok(())
+    pub fn connect_multiple_streams_with_config<S>(
+        &mut self, market: &FuturesMarket, endpoints: &[S], config: &Config,
+    ) -> Result<()>
+    where
+        S: ToStreamName,
+    {
+        let endpoints = join_stream_names(endpoints, market.into())?;
+        let url = market.ws_endpoint(config).to_string();
+        self.connect_wss(&FuturesWebsocketAPI::CustomMultiStream(url).params(market, &endpoints))
     }
 
     pub fn event_loop(&mut self, running: &AtomicBool) -> Result<()> {
-        while running.load(Ordering::Relaxed) {
-            if let Some(ref mut socket) = self.socket {
-                let message = socket.0.read()?;
-                match message {
-                    Message::Text(msg) => {
-                        if let Err(e) = self.handle_msg(&msg) {
-                            bail!(format!("Error on handling stream message: {}", e));
-                        }
-                    }
-                    Message::Ping(payload) => {
-                        socket.0.write(Message::Pong(payload)).unwrap();
-                    }
-                    Message::Pong(_) | Message::Binary(_) | Message::Frame(_) => (),
-                    Message::Close(e) => bail!(format!("Disconnected {:?}", e)),
-                }
-            }
-        }
+        self.read_while_running(running)?;
         bail!("running loop closed");
     }
 }
+
+// Decodes a stream message into the type given by its event type or stream name
+pub(crate) fn parse_event(msg: &str) -> Result<FuturesWebsocketEvent> {
+    parse_stream_event(msg).map(|(_, event)| event)
+}
+
+// Decodes a stream message, with the name of the stream it was received on. Events which
+// do not decode into the type of their event type are returned as `Unknown`, so that a
+// change of their fields doesn't stop the connection.
+pub(crate) fn parse_stream_event(msg: &str) -> Result<(Option<String>, FuturesWebsocketEvent)> {
+    let message = decoder::peek(msg)?;
+    let event = decode_event(message.kind, message.payload, msg)
+        .unwrap_or_else(|_| FuturesWebsocketEvent::Unknown(msg.to_string()));
+    Ok((message.stream.map(String::from), event))
+}
+
+fn decode_event(
+    kind: Kind<'_>, payload: &str, msg: &str,
+) -> serde_json::Result<FuturesWebsocketEvent> {
+    let event = match kind {
+        Kind::Event(event_type) => match event_type.as_ref() {
+            "24hrTicker" => FuturesWebsocketEvent::DayTicker(serde_json::from_str(payload)?),
+            "24hrMiniTicker" => FuturesWebsocketEvent::MiniTicker(serde_json::from_str(payload)?),
+            "bookTicker" => FuturesWebsocketEvent::BookTicker(serde_json::from_str(payload)?),
+            "ACCOUNT_UPDATE" => {
+                FuturesWebsocketEvent::AccountUpdate(serde_json::from_str(payload)?)
+            }
+            "ORDER_TRADE_UPDATE" => {
+                FuturesWebsocketEvent::OrderTrade(serde_json::from_str(payload)?)
+            }
+            "MARGIN_CALL" => FuturesWebsocketEvent::MarginCall(serde_json::from_str(payload)?),
+            "ACCOUNT_CONFIG_UPDATE" => {
+                FuturesWebsocketEvent::AccountConfigUpdate(serde_json::from_str(payload)?)
+            }
+            "STRATEGY_UPDATE" => {
+                FuturesWebsocketEvent::StrategyUpdate(serde_json::from_str(payload)?)
+            }
+            "GRID_UPDATE" => FuturesWebsocketEvent::GridUpdate(serde_json::from_str(payload)?),
+            "CONDITIONAL_ORDER_TRIGGER_REJECT" => {
+                FuturesWebsocketEvent::ConditionalOrderTriggerReject(serde_json::from_str(payload)?)
+            }
+            "TRADE_LITE" => FuturesWebsocketEvent::TradeLite(serde_json::from_str(payload)?),
+            "executionReport" => {
+                FuturesWebsocketEvent::MarginOrderTrade(serde_json::from_str(payload)?)
+            }
+            "outboundAccountPosition" => {
+                FuturesWebsocketEvent::MarginAccountPosition(serde_json::from_str(payload)?)
+            }
+            "balanceUpdate" => {
+                FuturesWebsocketEvent::MarginBalanceUpdate(serde_json::from_str(payload)?)
+            }
+            "indexPriceUpdate" => FuturesWebsocketEvent::IndexPrice(serde_json::from_str(payload)?),
+            "markPriceUpdate" => FuturesWebsocketEvent::MarkPrice(serde_json::from_str(payload)?),
+            "aggTrade" => FuturesWebsocketEvent::AggrTrades(serde_json::from_str(payload)?),
+            "trade" => FuturesWebsocketEvent::Trade(serde_json::from_str(payload)?),
+            "kline" => FuturesWebsocketEvent::Kline(serde_json::from_str(payload)?),
+            "continuous_kline" => {
+                FuturesWebsocketEvent::ContinuousKline(serde_json::from_str(payload)?)
+            }
+            "indexPrice_kline" => FuturesWebsocketEvent::IndexKline(serde_json::from_str(payload)?),
+            "forceOrder" => FuturesWebsocketEvent::Liquidation(serde_json::from_str(payload)?),
+            "depthUpdate" => FuturesWebsocketEvent::DepthOrderBook(serde_json::from_str(payload)?),
+            "listenKeyExpired" => {
+                FuturesWebsocketEvent::UserDataStreamExpiredEvent(serde_json::from_str(payload)?)
+            }
+            _ => FuturesWebsocketEvent::Unknown(msg.to_string()),
+        },
+        Kind::Events(event_type) => match event_type.as_ref() {
+            "24hrTicker" => FuturesWebsocketEvent::DayTickerAll(serde_json::from_str(payload)?),
+            "24hrMiniTicker" => {
+                FuturesWebsocketEvent::MiniTickerAll(serde_json::from_str(payload)?)
+            }
+            "markPriceUpdate" => {
+                FuturesWebsocketEvent::MarkPriceAll(serde_json::from_str(payload)?)
+            }
+            _ => FuturesWebsocketEvent::Unknown(msg.to_string()),
+        },
+        Kind::Response => FuturesWebsocketEvent::Response(serde_json::from_str(payload)?),
+        Kind::OrderBook => FuturesWebsocketEvent::OrderBook(serde_json::from_str(payload)?),
+        Kind::BookTicker => FuturesWebsocketEvent::BookTicker(serde_json::from_str(payload)?),
+        Kind::Unknown => FuturesWebsocketEvent::Unknown(msg.to_string()),
+    };
+    Ok(event)
+}
@@ -0,0 +1,89 @@
+use crate::errors::Result;
+use crate::futures::account::{CustomOrderRequest, FuturesAccount};
+use crate::futures::model::{AccountInformation, CanceledOrder, Order, Transaction};
+use crate::model::SessionStatus;
+use crate::ws_api::{logon_params, signed_params, WsApiConnection};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+
+/// USD-M futures trading through the WebSocket API.
+pub struct FuturesWsApi<'a> {
+    pub(crate) account: FuturesAccount,
+    pub connection: WsApiConnection<'a>,
+    logged_on: bool,
+}
+
+impl<'a> FuturesWsApi<'a> {
+    pub(crate) fn new_with_account(account: FuturesAccount, endpoint: String) -> Self {
+        FuturesWsApi {
+            account,
+            connection: WsApiConnection::new(endpoint),
+            logged_on: false,
+        }
+    }
+
+    pub fn connect(&mut self) -> Result<()> {
+        self.logged_on = false;
+        self.connection.connect()
+    }
+
+    pub fn disconnect(&mut self) -> Result<()> {
+        self.connection.disconnect()
+    }
+
+    /// Place an order, see `FuturesAccount::custom_order`
+    pub fn place_order(&mut self, order_request: CustomOrderRequest) -> Result<Transaction> {
+        let order = self.account.build_custom_order(order_request);
+        self.signed_request("order.place", order)
+    }
+
+    pub fn cancel_order<S>(&mut self, symbol: S, order_id: u64) -> Result<CanceledOrder>
+    where
+        S: Into<String>,
+    {
+        let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+        parameters.insert("symbol".into(), symbol.into());
+        parameters.insert("orderId".into(), order_id.to_string());
+        self.signed_request("order.cancel", parameters)
+    }
+
+    pub fn order_status<S>(&mut self, symbol: S, order_id: u64) -> Result<Order>
+    where
+        S: Into<String>,
+    {
+        let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+        parameters.insert("symbol".into(), symbol.into());
+        parameters.insert("orderId".into(), order_id.to_string());
+        self.signed_request("order.status", parameters)
+    }
+
+    pub fn account_status(&mut self) -> Result<AccountInformation> {
+        self.signed_request("account.status", BTreeMap::new())
+    }
+
+    /// Authenticate the connection with an Ed25519 key, see `WsApi::session_logon`
+    pub fn session_logon<F>(&mut self, sign: F) -> Result<SessionStatus>
+    where
+        F: FnOnce(&str) -> String,
+    {
+        let params = logon_params(&self.account.client, self.account.recv_window, sign)?;
+        let status = self
+            .connection
+            .request("session.logon", params)?
+            .into_result()?;
+        self.logged_on = true;
+        Ok(status)
+    }
+
+    fn signed_request<T: DeserializeOwned>(
+        &mut self, method: &str, parameters: BTreeMap<String, String>,
+    ) -> Result<T> {
+        let params = signed_params(
+            &self.account.client,
+            self.account.recv_window,
+            parameters,
+            self.logged_on,
+        )?;
+        self.connection.request(method, params)?.into_result()
+    }
+}
@@ -1,8 +1,11 @@
+use std::collections::BTreeMap;
+
 use error_chain::bail;
 
 use crate::model::{Empty, ExchangeInformation, ServerTime, Symbol};
 use crate::client::Client;
 use crate::errors::Result;
+use crate::util::{build_list_parameter, build_request};
 use crate::api::API;
 use crate::api::Spot;
 
@@ -29,6 +32,23 @@ impl General {
         self.client.get(API::Spot(Spot::ExchangeInfo), None)
     }
 
+    // Obtain exchange information for a list of symbols
+    pub fn exchange_info_for_symbols<S>(&self, symbols: &[S]) -> Result<ExchangeInformation>
+    where
+        S: AsRef<str>,
+    {
+        self.filtered_exchange_info("symbols", symbols)
+    }
+
+    // Obtain exchange information for the symbols having any of the
+    // given permissions ("SPOT", "MARGIN", "LEVERAGED", ...)
+    pub fn exchange_info_for_permissions<S>(&self, permissions: &[S]) -> Result<ExchangeInformation>
+    where
+        S: AsRef<str>,
+    {
+        self.filtered_exchange_info("permissions", permissions)
+    }
+
     // Get Symbol information
     pub fn get_symbol_info<S>(&self, symbol: S) -> Result<Symbol>
     where
@@ -47,4 +67,15 @@ impl General {
             Err(e) => Err(e),
         }
     }
+
+    fn filtered_exchange_info<S>(&self, key: &str, values: &[S]) -> Result<ExchangeInformation>
+    where
+        S: AsRef<str>,
+    {
+        let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+        parameters.insert(key.into(), build_list_parameter(values)?);
+        let request = build_request(parameters);
+        self.client
+            .get(API::Spot(Spot::ExchangeInfo), Some(request))
+    }
 }
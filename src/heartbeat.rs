@@ -0,0 +1,279 @@
+use crate::errors::Result;
+use crate::util::current_millis;
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tungstenite::stream::MaybeTlsStream;
+
+const MIN_READ_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// Liveness checks of the connections of `WebSockets` and `FuturesWebSockets`.
+///
+/// Without any of them reads block until a message is received, as before.
+#[derive(Clone, Debug, Default)]
+pub struct HeartbeatConfig {
+    // How long a read waits before the loop checks the connection and 'running' again,
+    // at most a second when the other checks are set
+    pub read_timeout: Option<Duration>,
+    // The connection is lost when nothing, not even a ping, is received for so long
+    pub idle_timeout: Option<Duration>,
+    // Pings sent by the client, their round trip is measured
+    pub ping_interval: Option<Duration>,
+}
+
+impl HeartbeatConfig {
+    pub fn set_read_timeout(mut self, read_timeout: Duration) -> Self {
+        self.read_timeout = Some(read_timeout);
+        self
+    }
+
+    pub fn set_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    pub fn set_ping_interval(mut self, ping_interval: Duration) -> Self {
+        self.ping_interval = Some(ping_interval);
+        self
+    }
+
+    fn effective_read_timeout(&self) -> Option<Duration> {
+        if self.read_timeout.is_some() {
+            return self.read_timeout;
+        }
+        [self.idle_timeout, self.ping_interval]
+            .iter()
+            .flatten()
+            .fold(None, |timeout: Option<Duration>, interval| {
+                Some(timeout.unwrap_or(Duration::from_secs(1)).min(*interval))
+            })
+    }
+}
+
+/// Receive time minus event time of the events of a stream, in milliseconds. Negative
+/// when the local clock is behind the one of Binance.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LatencyStats {
+    pub count: u64,
+    pub last: i64,
+    pub min: i64,
+    pub max: i64,
+    pub mean: f64,
+    // Local time in milliseconds
+    pub last_received_at: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConnectionStats {
+    // Local time in milliseconds of the last message of any kind
+    pub last_received_at: Option<u64>,
+    pub pings_sent: u64,
+    pub pongs_received: u64,
+    // Of the last answered client ping
+    pub ping_round_trip: Option<Duration>,
+    // By stream, raw stream connections use the stream of their url
+    pub latency: BTreeMap<String, LatencyStats>,
+}
+
+/// Stats of a connection, shared with the handler and other threads. They are kept when
+/// the connection is reopened.
+#[derive(Clone, Debug, Default)]
+pub struct Monitor {
+    stats: Arc<Mutex<ConnectionStats>>,
+}
+
+impl Monitor {
+    fn lock(&self) -> MutexGuard<'_, ConnectionStats> {
+        self.stats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn stats(&self) -> ConnectionStats {
+        self.lock().clone()
+    }
+
+    pub fn latency(&self, stream: &str) -> Option<LatencyStats> {
+        self.lock().latency.get(stream).cloned()
+    }
+
+    /// Whether the stream had no event within `max_age`, or never had one.
+    pub fn is_stale(&self, stream: &str, max_age: Duration) -> bool {
+        match self.lock().latency.get(stream) {
+            Some(latency) => {
+                current_millis().saturating_sub(latency.last_received_at)
+                    > max_age.as_millis() as u64
+            }
+            None => true,
+        }
+    }
+
+    pub(crate) fn event(&self, stream: &str, event_time: u64, received_at: u64) {
+        let latency = received_at as i64 - event_time as i64;
+        let mut stats = self.lock();
+        let stats = stats.latency.entry(stream.to_string()).or_default();
+        if stats.count == 0 {
+            stats.min = latency;
+            stats.max = latency;
+        }
+        stats.count += 1;
+        stats.last = latency;
+        stats.min = stats.min.min(latency);
+        stats.max = stats.max.max(latency);
+        stats.mean += (latency as f64 - stats.mean) / stats.count as f64;
+        stats.last_received_at = received_at;
+    }
+}
+
+// Timers of the current connection
+pub(crate) struct Heartbeat {
+    config: HeartbeatConfig,
+    last_received: Instant,
+    last_ping: Instant,
+    // Payload and send time of the unanswered ping
+    pending_ping: Option<(Vec<u8>, Instant)>,
+    // Set by a `Multiplexer`, reads return at once
+    nonblocking: bool,
+    // Set by `spawn_reader`, so that its thread sees it was stopped without a message
+    max_read_timeout: Option<Duration>,
+    pub(crate) monitor: Monitor,
+}
+
+impl Heartbeat {
+    pub(crate) fn new(config: HeartbeatConfig) -> Self {
+        Heartbeat {
+            config,
+            last_received: Instant::now(),
+            last_ping: Instant::now(),
+            pending_ping: None,
+            nonblocking: false,
+            max_read_timeout: None,
+            monitor: Monitor::default(),
+        }
+    }
+
+    pub(crate) fn set_config(&mut self, config: HeartbeatConfig) {
+        self.config = config;
+    }
+
+    pub(crate) fn connected(&mut self, stream: &MaybeTlsStream<TcpStream>) -> Result<()> {
+        self.last_received = Instant::now();
+        self.last_ping = Instant::now();
+        self.pending_ping = None;
+        if let Some(stream) = tcp_stream(stream) {
+            stream.set_read_timeout(self.read_timeout())?;
+            stream.set_nonblocking(self.nonblocking)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_max_read_timeout(&mut self, max_read_timeout: Duration) {
+        self.max_read_timeout = Some(max_read_timeout);
+    }
+
+    fn read_timeout(&self) -> Option<Duration> {
+        let timeout = match (self.config.effective_read_timeout(), self.max_read_timeout) {
+            (Some(timeout), Some(max)) => Some(timeout.min(max)),
+            (timeout, max) => timeout.or(max),
+        };
+        // A zero timeout is rejected by the socket
+        timeout.map(|timeout| timeout.max(MIN_READ_TIMEOUT))
+    }
+
+    pub(crate) fn set_nonblocking(
+        &mut self, stream: Option<&MaybeTlsStream<TcpStream>>,
+    ) -> Result<()> {
+        self.nonblocking = true;
+        if let Some(stream) = stream.and_then(tcp_stream) {
+            stream.set_nonblocking(true)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn received(&mut self) {
+        self.last_received = Instant::now();
+        self.monitor.lock().last_received_at = Some(current_millis());
+    }
+
+    pub(crate) fn pong(&mut self, payload: &[u8]) {
+        let mut stats = self.monitor.lock();
+        stats.pongs_received += 1;
+        if let Some((ping, sent_at)) = self.pending_ping.take() {
+            if ping == payload {
+                stats.ping_round_trip = Some(sent_at.elapsed());
+            } else {
+                self.pending_ping = Some((ping, sent_at));
+            }
+        }
+    }
+
+    // The reason the connection is lost if it has been silent for too long
+    pub(crate) fn idle(&self) -> Option<String> {
+        let idle_timeout = self.config.idle_timeout?;
+        let silent_for = self.last_received.elapsed();
+        if silent_for < idle_timeout {
+            return None;
+        }
+        Some(format!(
+            "No message received for {} ms",
+            silent_for.as_millis()
+        ))
+    }
+
+    // Payload of the ping to send, if one is due
+    pub(crate) fn ping(&mut self) -> Option<Vec<u8>> {
+        let ping_interval = self.config.ping_interval?;
+        if self.last_ping.elapsed() < ping_interval {
+            return None;
+        }
+        self.last_ping = Instant::now();
+        let payload = current_millis().to_string().into_bytes();
+        self.pending_ping = Some((payload.clone(), Instant::now()));
+        self.monitor.lock().pings_sent += 1;
+        Some(payload)
+    }
+}
+
+pub(crate) fn tcp_stream(stream: &MaybeTlsStream<TcpStream>) -> Option<&TcpStream> {
+    match stream {
+        MaybeTlsStream::Plain(stream) => Some(stream),
+        MaybeTlsStream::NativeTls(stream) => Some(stream.get_ref()),
+        _ => None,
+    }
+}
+
+// What a read on a connection gave
+pub(crate) enum ReadOutcome {
+    Handled,
+    // No message within the read timeout, or none pending on a non-blocking connection
+    Timeout,
+    Lost(String),
+}
+
+impl ReadOutcome {
+    pub(crate) fn unless_lost(self, lost: Option<String>) -> Self {
+        match lost {
+            Some(reason) => ReadOutcome::Lost(reason),
+            None => self,
+        }
+    }
+}
+
+// The reason a send lost the connection. A frame which could not be written at once on a
+// non-blocking connection stays queued and is written by the next reads.
+pub(crate) fn send_failure(result: tungstenite::Result<()>) -> Option<String> {
+    match result {
+        Err(ref e) if !is_timeout(e) => Some(e.to_string()),
+        _ => None,
+    }
+}
+
+// Whether a read failed only because of the read timeout
+pub(crate) fn is_timeout(error: &tungstenite::Error) -> bool {
+    match error {
+        tungstenite::Error::Io(e) => {
+            e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut
+        }
+        _ => false,
+    }
+}
@@ -16,6 +16,7 @@
 )]
 
 mod client;
+mod decoder;
 pub mod errors;
 pub mod util;
 
@@ -23,12 +24,31 @@ pub mod model;
 
 pub mod account;
 pub mod api;
+pub mod archive;
+#[cfg(feature = "async")]
+pub mod async_websockets;
+pub mod candles;
 pub mod config;
+pub mod connection;
+pub mod event_channel;
+pub mod exchange_info;
 pub mod general;
+pub mod heartbeat;
 pub mod market;
+pub mod multiplexer;
+pub mod orderbook;
+pub mod reconnect;
+pub mod recorder;
+#[cfg(feature = "replay-server")]
+pub mod replay_server;
 pub mod savings;
+pub mod sbe;
+pub mod streams;
+pub mod subscription;
+pub mod user_data_stream;
 pub mod userstream;
 pub mod websockets;
+pub mod ws_api;
 
 pub mod futures;
 pub mod futures_cm;
@@ -1,12 +1,14 @@
-use crate::util::build_request;
+use crate::util::{build_list_parameter, build_request};
 use crate::model::{
-    AggTrade, AveragePrice, BookTickers, KlineSummaries, KlineSummary, OrderBook, PriceStats,
-    Prices, SymbolPrice, Tickers,
+    AggTrade, AveragePrice, BookTickers, KlineSummaries, KlineSummary, MiniTickerStats, OrderBook,
+    PriceStats, Prices, SymbolPrice, TickerStats, Tickers,
 };
 use crate::client::Client;
 use crate::errors::Result;
 use std::collections::BTreeMap;
+use serde::de::DeserializeOwned;
 use serde_json::Value;
+use url::form_urlencoded::byte_serialize;
 use crate::api::API;
 use crate::api::Spot;
 
@@ -58,6 +60,15 @@ impl Market {
         self.client.get(API::Spot(Spot::Price), Some(request))
     }
 
+    // Latest price for a list of symbols.
+    pub fn get_symbols_prices<S>(&self, symbols: &[S]) -> Result<Vec<SymbolPrice>>
+    where
+        S: AsRef<str>,
+    {
+        let request = self.build_symbols_request(symbols)?;
+        self.client.get(API::Spot(Spot::Price), Some(request))
+    }
+
     // Average price for ONE symbol.
     pub fn get_average_price<S>(&self, symbol: S) -> Result<AveragePrice>
     where
@@ -86,6 +97,15 @@ impl Market {
         self.client.get(API::Spot(Spot::BookTicker), Some(request))
     }
 
+    // -> Best price/qty on the order book for a list of symbols
+    pub fn get_symbols_book_tickers<S>(&self, symbols: &[S]) -> Result<Vec<Tickers>>
+    where
+        S: AsRef<str>,
+    {
+        let request = self.build_symbols_request(symbols)?;
+        self.client.get(API::Spot(Spot::BookTicker), Some(request))
+    }
+
     // 24hr ticker price change statistics
     pub fn get_24h_price_stats<S>(&self, symbol: S) -> Result<PriceStats>
     where
@@ -97,11 +117,88 @@ impl Market {
         self.client.get(API::Spot(Spot::Ticker24hr), Some(request))
     }
 
+    // 24hr ticker price change statistics for a list of symbols
+    pub fn get_symbols_24h_price_stats<S>(&self, symbols: &[S]) -> Result<Vec<PriceStats>>
+    where
+        S: AsRef<str>,
+    {
+        let request = self.build_symbols_request(symbols)?;
+        self.client.get(API::Spot(Spot::Ticker24hr), Some(request))
+    }
+
     // 24hr ticker price change statistics for all symbols
     pub fn get_all_24h_price_stats(&self) -> Result<Vec<PriceStats>> {
         self.client.get(API::Spot(Spot::Ticker24hr), None)
     }
 
+    /// Rolling window price change statistics.
+    ///
+    /// The window size is given in minutes ("1m" to "59m"), hours ("1h" to "23h")
+    /// or days ("1d" to "7d"). Binance defaults to "1d".
+    pub fn get_rolling_window_price_stats<S1, S2>(
+        &self, symbol: S1, window_size: S2,
+    ) -> Result<TickerStats>
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        self.get_ticker_stats(
+            API::Spot(Spot::Ticker),
+            symbol.into(),
+            ("windowSize", Some(window_size.into())),
+            "FULL",
+        )
+    }
+
+    /// Rolling window price change statistics, without the price change fields.
+    pub fn get_mini_rolling_window_price_stats<S1, S2>(
+        &self, symbol: S1, window_size: S2,
+    ) -> Result<MiniTickerStats>
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        self.get_ticker_stats(
+            API::Spot(Spot::Ticker),
+            symbol.into(),
+            ("windowSize", Some(window_size.into())),
+            "MINI",
+        )
+    }
+
+    /// Price change statistics for the current trading day.
+    ///
+    /// The time zone is given in hours and minutes ("-1:00", "05:45") or hours only ("0", "8"),
+    /// and defaults to UTC.
+    pub fn get_trading_day_price_stats<S>(
+        &self, symbol: S, time_zone: Option<String>,
+    ) -> Result<TickerStats>
+    where
+        S: Into<String>,
+    {
+        self.get_ticker_stats(
+            API::Spot(Spot::TickerTradingDay),
+            symbol.into(),
+            ("timeZone", time_zone),
+            "FULL",
+        )
+    }
+
+    /// Price change statistics for the current trading day, without the price change fields.
+    pub fn get_mini_trading_day_price_stats<S>(
+        &self, symbol: S, time_zone: Option<String>,
+    ) -> Result<MiniTickerStats>
+    where
+        S: Into<String>,
+    {
+        self.get_ticker_stats(
+            API::Spot(Spot::TickerTradingDay),
+            symbol.into(),
+            ("timeZone", time_zone),
+            "MINI",
+        )
+    }
+
     /// Get aggregated historical trades.
     ///
     /// If you provide start_time, you also need to provide end_time.
@@ -168,7 +265,59 @@ impl Market {
         }
 
         let request = build_request(parameters);
-        let data: Vec<Vec<Value>> = self.client.get(API::Spot(Spot::Klines), Some(request))?;
+        self.get_kline_summaries(API::Spot(Spot::Klines), request)
+    }
+
+    /// Returns up to 'limit' klines for given symbol and interval, modified for
+    /// presentation of candlestick charts.
+    ///
+    /// The time zone only changes the interval boundaries; start_time and end_time
+    /// are always interpreted in UTC.
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_ui_klines<S1, S2, S3, S4, S5>(
+        &self, symbol: S1, interval: S2, limit: S3, start_time: S4, end_time: S5,
+        time_zone: Option<String>,
+    ) -> Result<KlineSummaries>
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+        S3: Into<Option<u16>>,
+        S4: Into<Option<u64>>,
+        S5: Into<Option<u64>>,
+    {
+        let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+
+        parameters.insert("symbol".into(), symbol.into());
+        parameters.insert("interval".into(), interval.into());
+
+        if let Some(lt) = limit.into() {
+            parameters.insert("limit".into(), format!("{}", lt));
+        }
+        if let Some(st) = start_time.into() {
+            parameters.insert("startTime".into(), format!("{}", st));
+        }
+        if let Some(et) = end_time.into() {
+            parameters.insert("endTime".into(), format!("{}", et));
+        }
+        if let Some(tz) = time_zone {
+            parameters.insert("timeZone".into(), byte_serialize(tz.as_bytes()).collect());
+        }
+
+        let request = build_request(parameters);
+        self.get_kline_summaries(API::Spot(Spot::UiKlines), request)
+    }
+
+    fn build_symbols_request<S>(&self, symbols: &[S]) -> Result<String>
+    where
+        S: AsRef<str>,
+    {
+        let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+        parameters.insert("symbols".into(), build_list_parameter(symbols)?);
+        Ok(build_request(parameters))
+    }
+
+    fn get_kline_summaries(&self, endpoint: API, request: String) -> Result<KlineSummaries> {
+        let data: Vec<Vec<Value>> = self.client.get(endpoint, Some(request))?;
 
         let klines = KlineSummaries::AllKlineSummaries(
             data.iter()
@@ -178,4 +327,19 @@ impl Market {
 
         Ok(klines)
     }
+
+    fn get_ticker_stats<T: DeserializeOwned>(
+        &self, endpoint: API, symbol: String, option: (&str, Option<String>), response_type: &str,
+    ) -> Result<T> {
+        let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+        parameters.insert("symbol".into(), symbol);
+        parameters.insert("type".into(), response_type.into());
+
+        if let (key, Some(value)) = option {
+            parameters.insert(key.into(), byte_serialize(value.as_bytes()).collect());
+        }
+
+        let request = build_request(parameters);
+        self.client.get(endpoint, Some(request))
+    }
 }
@@ -27,6 +27,9 @@ pub struct RateLimit {
     pub interval: String,
     pub interval_num: u16,
     pub limit: u64,
+    // Usage, returned with WebSocket API responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -43,6 +46,32 @@ pub struct Symbol {
     pub is_spot_trading_allowed: bool,
     pub is_margin_trading_allowed: bool,
     pub filters: Vec<Filters>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    #[serde(default)]
+    pub permission_sets: Vec<Vec<String>>,
+}
+
+impl Symbol {
+    // Price increment from the PRICE_FILTER
+    pub fn tick_size(&self) -> Option<f64> {
+        tick_size(&self.filters)
+    }
+
+    // Quantity increment from the LOT_SIZE filter
+    pub fn step_size(&self) -> Option<f64> {
+        step_size(&self.filters)
+    }
+
+    // Minimum order value from the NOTIONAL or MIN_NOTIONAL filter
+    pub fn min_notional(&self) -> Option<f64> {
+        min_notional(&self.filters)
+    }
+
+    // Maximum number of open orders from the MAX_NUM_ORDERS filter
+    pub fn max_num_orders(&self) -> Option<u16> {
+        max_num_orders(&self.filters)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -99,10 +128,17 @@ pub enum Filters {
     IcebergParts { limit: Option<u16> },
     #[serde(rename = "MAX_NUM_ORDERS")]
     #[serde(rename_all = "camelCase")]
-    MaxNumOrders { max_num_orders: Option<u16> },
+    MaxNumOrders {
+        // "limit" on futures
+        #[serde(alias = "limit")]
+        max_num_orders: Option<u16>,
+    },
     #[serde(rename = "MAX_NUM_ALGO_ORDERS")]
     #[serde(rename_all = "camelCase")]
-    MaxNumAlgoOrders { max_num_algo_orders: Option<u16> },
+    MaxNumAlgoOrders {
+        #[serde(alias = "limit")]
+        max_num_algo_orders: Option<u16>,
+    },
     #[serde(rename = "MAX_NUM_ICEBERG_ORDERS")]
     #[serde(rename_all = "camelCase")]
     MaxNumIcebergOrders { max_num_iceberg_orders: u16 },
@@ -126,6 +162,43 @@ pub enum Filters {
     },
 }
 
+pub(crate) fn tick_size(filters: &[Filters]) -> Option<f64> {
+    filters.iter().find_map(|filter| match filter {
+        Filters::PriceFilter { tick_size, .. } => tick_size.parse().ok(),
+        _ => None,
+    })
+}
+
+pub(crate) fn step_size(filters: &[Filters]) -> Option<f64> {
+    filters.iter().find_map(|filter| match filter {
+        Filters::LotSize { step_size, .. } => step_size.parse().ok(),
+        _ => None,
+    })
+}
+
+pub(crate) fn min_notional(filters: &[Filters]) -> Option<f64> {
+    filters.iter().find_map(|filter| match filter {
+        Filters::Notional {
+            notional,
+            min_notional,
+            ..
+        }
+        | Filters::MinNotional {
+            notional,
+            min_notional,
+            ..
+        } => min_notional.as_ref().or(notional.as_ref())?.parse().ok(),
+        _ => None,
+    })
+}
+
+pub(crate) fn max_num_orders(filters: &[Filters]) -> Option<u16> {
+    filters.iter().find_map(|filter| match filter {
+        Filters::MaxNumOrders { max_num_orders } => *max_num_orders,
+        _ => None,
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct AccountInformation {
@@ -173,6 +246,28 @@ pub struct Order {
     pub orig_quote_order_qty: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelReplaceResult {
+    pub cancel_result: String,
+    pub new_order_result: String,
+    pub cancel_response: Option<OrderCanceled>,
+    pub new_order_response: Option<Transaction>,
+}
+
+/// Authentication of a WebSocket API connection.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStatus {
+    pub api_key: Option<String>,
+    pub authorized_since: Option<u64>,
+    pub connected_since: u64,
+    pub return_rate_limits: bool,
+    pub server_time: u64,
+    #[serde(default)]
+    pub user_data_stream: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct OrderCanceled {
@@ -378,6 +473,59 @@ pub struct PriceStats {
     pub count: u64,
 }
 
+/// Price change statistics over a rolling window or a trading day (response type FULL).
+///
+/// Returned by the /api/v3/ticker and /api/v3/ticker/tradingDay endpoints.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TickerStats {
+    pub symbol: String,
+    pub price_change: String,
+    pub price_change_percent: String,
+    pub weighted_avg_price: String,
+    #[serde(with = "string_or_float")]
+    pub open_price: f64,
+    #[serde(with = "string_or_float")]
+    pub high_price: f64,
+    #[serde(with = "string_or_float")]
+    pub low_price: f64,
+    #[serde(with = "string_or_float")]
+    pub last_price: f64,
+    #[serde(with = "string_or_float")]
+    pub volume: f64,
+    #[serde(with = "string_or_float")]
+    pub quote_volume: f64,
+    pub open_time: u64,
+    pub close_time: u64,
+    pub first_id: i64,
+    pub last_id: i64,
+    pub count: u64,
+}
+
+/// Price change statistics over a rolling window or a trading day (response type MINI).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MiniTickerStats {
+    pub symbol: String,
+    #[serde(with = "string_or_float")]
+    pub open_price: f64,
+    #[serde(with = "string_or_float")]
+    pub high_price: f64,
+    #[serde(with = "string_or_float")]
+    pub low_price: f64,
+    #[serde(with = "string_or_float")]
+    pub last_price: f64,
+    #[serde(with = "string_or_float")]
+    pub volume: f64,
+    #[serde(with = "string_or_float")]
+    pub quote_volume: f64,
+    pub open_time: u64,
+    pub close_time: u64,
+    pub first_id: i64,
+    pub last_id: i64,
+    pub count: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AggTrade {
     #[serde(rename = "T")]
@@ -471,9 +619,25 @@ pub struct EventPosition {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct BalanceUpdateEvent {
-    #[serde(rename = "B")]
-    pub balance: Vec<EventBalance>,
+    #[serde(rename = "e")]
+    pub event_type: String,
+
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    #[serde(rename = "a")]
+    pub asset: String,
+
+    #[serde(rename = "d")]
+    pub balance_delta: String,
 
+    #[serde(rename = "T")]
+    pub clear_time: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountPositionEvent {
     #[serde(rename = "e")]
     pub event_type: String,
 
@@ -482,6 +646,152 @@ pub struct BalanceUpdateEvent {
 
     #[serde(rename = "u")]
     pub last_account_update_time: u64,
+
+    #[serde(rename = "B")]
+    pub balances: Vec<AccountPositionBalance>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountPositionBalance {
+    #[serde(rename = "a")]
+    pub asset: String,
+
+    #[serde(rename = "f")]
+    pub free: String,
+
+    #[serde(rename = "l")]
+    pub locked: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ListStatusEvent {
+    #[serde(rename = "e")]
+    pub event_type: String,
+
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    #[serde(rename = "s")]
+    pub symbol: String,
+
+    #[serde(rename = "g")]
+    pub order_list_id: i64,
+
+    // OCO or OTO
+    #[serde(rename = "c")]
+    pub contingency_type: String,
+
+    #[serde(rename = "l")]
+    pub list_status_type: String,
+
+    #[serde(rename = "L")]
+    pub list_order_status: String,
+
+    #[serde(rename = "r")]
+    pub list_reject_reason: String,
+
+    #[serde(rename = "C")]
+    pub list_client_order_id: String,
+
+    #[serde(rename = "T")]
+    pub transaction_time: u64,
+
+    #[serde(rename = "O")]
+    pub orders: Vec<ListStatusOrder>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ListStatusOrder {
+    #[serde(rename = "s")]
+    pub symbol: String,
+
+    #[serde(rename = "i")]
+    pub order_id: u64,
+
+    #[serde(rename = "c")]
+    pub client_order_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalLockUpdateEvent {
+    #[serde(rename = "e")]
+    pub event_type: String,
+
+    #[serde(rename = "E")]
+    pub event_time: u64,
+
+    #[serde(rename = "a")]
+    pub asset: String,
+
+    #[serde(rename = "d")]
+    pub delta: String,
+
+    #[serde(rename = "T")]
+    pub transaction_time: u64,
+}
+
+// Sent on WebSocket API connections when the user data stream stops
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EventStreamTerminatedEvent {
+    #[serde(rename = "e")]
+    pub event_type: String,
+
+    #[serde(rename = "E")]
+    pub event_time: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ExecutionType {
+    New,
+    Canceled,
+    Replaced,
+    Rejected,
+    Trade,
+    Expired,
+    // Expired because of self-trade prevention
+    TradePrevention,
+    Amendment,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderStatus {
+    New,
+    PendingNew,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    PendingCancel,
+    Rejected,
+    Expired,
+    ExpiredInMatch,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderRejectReason {
+    None,
+    UnknownInstrument,
+    MarketClosed,
+    PriceQtyExceedHardLimits,
+    UnknownOrder,
+    DuplicateOrder,
+    UnknownAccount,
+    InsufficientBalance,
+    AccountInactive,
+    AccountCannotSettle,
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -527,13 +837,13 @@ pub struct OrderTradeEvent {
     pub c_ignore: Option<String>,
 
     #[serde(rename = "x")]
-    pub execution_type: String,
+    pub execution_type: ExecutionType,
 
     #[serde(rename = "X")]
-    pub order_status: String,
+    pub order_status: OrderStatus,
 
     #[serde(rename = "r")]
-    pub order_reject_reason: String,
+    pub order_reject_reason: OrderRejectReason,
 
     #[serde(rename = "i")]
     pub order_id: u64,
@@ -570,6 +880,79 @@ pub struct OrderTradeEvent {
 
     #[serde(skip, rename = "M")]
     pub m_ignore: bool,
+
+    #[serde(rename = "O", default)]
+    pub order_creation_time: Option<u64>,
+
+    #[serde(rename = "Z", default)]
+    pub cumulative_quote_qty: Option<String>,
+
+    #[serde(rename = "Y", default)]
+    pub last_quote_qty: Option<String>,
+
+    #[serde(rename = "Q", default)]
+    pub quote_order_qty: Option<String>,
+
+    // Time the order was put on the book
+    #[serde(rename = "W", default)]
+    pub working_time: Option<u64>,
+
+    #[serde(rename = "V", default)]
+    pub self_trade_prevention_mode: Option<String>,
+
+    // Trailing stop orders only
+    #[serde(rename = "d", default)]
+    pub trailing_delta: Option<u64>,
+
+    #[serde(rename = "D", default)]
+    pub trailing_time: Option<u64>,
+
+    #[serde(rename = "j", default)]
+    pub strategy_id: Option<u64>,
+
+    #[serde(rename = "J", default)]
+    pub strategy_type: Option<u64>,
+
+    // Orders expired by self-trade prevention only
+    #[serde(rename = "v", default)]
+    pub prevented_match_id: Option<u64>,
+
+    #[serde(rename = "A", default)]
+    pub prevented_quantity: Option<String>,
+
+    #[serde(rename = "B", default)]
+    pub last_prevented_quantity: Option<String>,
+
+    #[serde(rename = "u", default)]
+    pub trade_group_id: Option<u64>,
+
+    #[serde(rename = "U", default)]
+    pub counter_order_id: Option<u64>,
+
+    #[serde(rename = "Cs", default)]
+    pub counter_symbol: Option<String>,
+
+    #[serde(rename = "pl", default)]
+    pub prevented_execution_quantity: Option<String>,
+
+    #[serde(rename = "pL", default)]
+    pub prevented_execution_price: Option<String>,
+
+    #[serde(rename = "pY", default)]
+    pub prevented_execution_quote_qty: Option<String>,
+
+    // Orders filled through SOR or allocations only
+    #[serde(rename = "b", default)]
+    pub match_type: Option<String>,
+
+    #[serde(rename = "a", default)]
+    pub allocation_id: Option<u64>,
+
+    #[serde(rename = "k", default)]
+    pub working_floor: Option<String>,
+
+    #[serde(rename = "uS", default)]
+    pub used_sor: Option<bool>,
 }
 
 /// The Aggregate Trade Streams push trade information that is aggregated for a single taker order.
@@ -1371,7 +1754,7 @@ pub(crate) mod string_or_float {
 pub(crate) mod string_or_float_opt {
     use std::fmt;
 
-    use serde::{Serializer, Deserialize, Deserializer};
+    use serde::{de, Serializer, Deserialize, Deserializer};
 
     pub fn serialize<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -1384,6 +1767,7 @@ pub(crate) mod string_or_float_opt {
         }
     }
 
+    // Empty strings and nulls are None
     pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
     where
         D: Deserializer<'de>,
@@ -1395,9 +1779,13 @@ pub(crate) mod string_or_float_opt {
             Float(f64),
         }
 
-        Ok(Some(crate::model::string_or_float::deserialize(
-            deserializer,
-        )?))
+        match Option::<StringOrFloat>::deserialize(deserializer)? {
+            Some(StringOrFloat::String(s)) if s.is_empty() => Ok(None),
+            Some(StringOrFloat::String(s)) if s == "INF" => Ok(Some(f64::INFINITY)),
+            Some(StringOrFloat::String(s)) => s.parse().map(Some).map_err(de::Error::custom),
+            Some(StringOrFloat::Float(f)) => Ok(Some(f)),
+            None => Ok(None),
+        }
     }
 }
 
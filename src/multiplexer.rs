@@ -0,0 +1,228 @@
+use crate::errors::Result;
+use crate::futures::websockets::FuturesWebSockets;
+use crate::heartbeat::ReadOutcome;
+use crate::reconnect::ReconnectPolicy;
+use crate::websockets::WebSockets;
+use error_chain::bail;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A connection driven by a `Multiplexer`, with its own handler.
+pub enum MultiplexedSocket<'a> {
+    Spot(WebSockets<'a>),
+    Futures(FuturesWebSockets<'a>),
+}
+
+impl<'a> From<WebSockets<'a>> for MultiplexedSocket<'a> {
+    fn from(web_socket: WebSockets<'a>) -> Self {
+        MultiplexedSocket::Spot(web_socket)
+    }
+}
+
+impl<'a> From<FuturesWebSockets<'a>> for MultiplexedSocket<'a> {
+    fn from(web_socket: FuturesWebSockets<'a>) -> Self {
+        MultiplexedSocket::Futures(web_socket)
+    }
+}
+
+impl<'a> MultiplexedSocket<'a> {
+    fn set_nonblocking(&mut self) -> Result<()> {
+        match self {
+            MultiplexedSocket::Spot(web_socket) => web_socket.set_nonblocking(),
+            MultiplexedSocket::Futures(web_socket) => web_socket.set_nonblocking(),
+        }
+    }
+
+    fn poll_message(&mut self) -> Result<ReadOutcome> {
+        match self {
+            MultiplexedSocket::Spot(web_socket) => web_socket.poll_message(),
+            MultiplexedSocket::Futures(web_socket) => web_socket.poll_message(),
+        }
+    }
+
+    fn rotate(&mut self) -> Result<Option<String>> {
+        match self {
+            MultiplexedSocket::Spot(web_socket) => web_socket.rotate(),
+            MultiplexedSocket::Futures(web_socket) => web_socket.rotate(),
+        }
+    }
+
+    fn disconnected(&mut self, reason: String) -> Result<()> {
+        match self {
+            MultiplexedSocket::Spot(web_socket) => web_socket.disconnected(reason),
+            MultiplexedSocket::Futures(web_socket) => web_socket.disconnected(reason),
+        }
+    }
+
+    fn reconnecting(&mut self, attempt: u32, policy: &ReconnectPolicy) -> Result<Duration> {
+        match self {
+            MultiplexedSocket::Spot(web_socket) => web_socket.reconnecting(attempt, policy),
+            MultiplexedSocket::Futures(web_socket) => web_socket.reconnecting(attempt, policy),
+        }
+    }
+
+    fn reopen(&mut self) -> Result<bool> {
+        match self {
+            MultiplexedSocket::Spot(web_socket) => web_socket.reopen(),
+            MultiplexedSocket::Futures(web_socket) => web_socket.reopen(),
+        }
+    }
+}
+
+struct Connection<'a> {
+    socket: MultiplexedSocket<'a>,
+    connected_at: Instant,
+    // Attempts since the connection was lost, 0 while it is open
+    attempt: u32,
+    // When the next attempt is due
+    reconnect_at: Option<Instant>,
+}
+
+/// Reads many spot and futures connections on one thread, instead of a thread with a
+/// blocking `event_loop` per connection. Every message is passed to the handler of its
+/// connection. A small pool is a `Multiplexer` per thread.
+pub struct Multiplexer<'a> {
+    connections: Vec<Connection<'a>>,
+    // Sleep after a round in which no connection had a message
+    poll_interval: Duration,
+    // None to stop the loop when a connection is lost
+    reconnect_policy: Option<ReconnectPolicy>,
+}
+
+impl<'a> Default for Multiplexer<'a> {
+    fn default() -> Self {
+        Multiplexer {
+            connections: Vec::new(),
+            poll_interval: Duration::from_millis(1),
+            reconnect_policy: None,
+        }
+    }
+}
+
+impl<'a> Multiplexer<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Reconnects lost connections with the backoff of the policy, without holding up the
+    /// others, and replaces them before Binance closes them after 24 hours.
+    pub fn set_reconnect_policy(mut self, reconnect_policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = Some(reconnect_policy);
+        self
+    }
+
+    /// Adds a connected socket, returns its index. Its reads no longer block.
+    pub fn add<S>(&mut self, socket: S) -> Result<usize>
+    where
+        S: Into<MultiplexedSocket<'a>>,
+    {
+        let mut socket = socket.into();
+        socket.set_nonblocking()?;
+        self.connections.push(Connection {
+            socket,
+            connected_at: Instant::now(),
+            attempt: 0,
+            reconnect_at: None,
+        });
+        Ok(self.connections.len() - 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    // To subscribe, unsubscribe or disconnect a connection
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut MultiplexedSocket<'a>> {
+        self.connections
+            .get_mut(index)
+            .map(|connection| &mut connection.socket)
+    }
+
+    pub fn into_sockets(self) -> Vec<MultiplexedSocket<'a>> {
+        self.connections
+            .into_iter()
+            .map(|connection| connection.socket)
+            .collect()
+    }
+
+    /// Reads the connections until `running` is cleared. Without a reconnect policy a lost
+    /// connection stops the loop with an error. Errors returned by the handlers stop it too.
+    pub fn event_loop(&mut self, running: &AtomicBool) -> Result<()> {
+        while running.load(Ordering::Relaxed) {
+            if !self.poll()? {
+                thread::sleep(self.poll_interval);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads every connection once, returns whether any of them had a message.
+    pub fn poll(&mut self) -> Result<bool> {
+        let mut handled = false;
+        for (index, connection) in self.connections.iter_mut().enumerate() {
+            let outcome = match self.reconnect_policy {
+                Some(ref policy) => connection.poll_with_reconnect(policy)?,
+                None => connection.socket.poll_message()?,
+            };
+            match outcome {
+                ReadOutcome::Handled => handled = true,
+                ReadOutcome::Timeout => (),
+                ReadOutcome::Lost(reason) => {
+                    bail!(format!("Connection {} lost: {}", index, reason))
+                }
+            }
+        }
+        Ok(handled)
+    }
+}
+
+impl<'a> Connection<'a> {
+    fn poll_with_reconnect(&mut self, policy: &ReconnectPolicy) -> Result<ReadOutcome> {
+        if let Some(reconnect_at) = self.reconnect_at {
+            if Instant::now() < reconnect_at {
+                return Ok(ReadOutcome::Timeout);
+            }
+            if self.socket.reopen()? {
+                self.connected_at = Instant::now();
+                self.attempt = 0;
+                self.reconnect_at = None;
+            } else {
+                self.schedule(policy)?;
+            }
+            return Ok(ReadOutcome::Timeout);
+        }
+
+        let lost = if self.connected_at.elapsed() >= policy.rotate_after {
+            self.connected_at = Instant::now();
+            self.socket.rotate()?
+        } else {
+            match self.socket.poll_message()? {
+                ReadOutcome::Lost(reason) => Some(reason),
+                outcome => return Ok(outcome),
+            }
+        };
+        if let Some(reason) = lost {
+            self.socket.disconnected(reason)?;
+            self.schedule(policy)?;
+        }
+        Ok(ReadOutcome::Timeout)
+    }
+
+    // Plans the next attempt, failing when there are no more
+    fn schedule(&mut self, policy: &ReconnectPolicy) -> Result<()> {
+        self.attempt += 1;
+        let delay = self.socket.reconnecting(self.attempt, policy)?;
+        self.reconnect_at = Some(Instant::now() + delay);
+        Ok(())
+    }
+}
@@ -0,0 +1,349 @@
+use crate::errors::{Error, Result};
+use crate::futures::market::FuturesMarket;
+use crate::market::Market;
+use crate::model::{Asks, Bids, DepthOrderBookEvent, OrderBook};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// Source of the REST depth snapshots used to seed a local order book.
+pub trait DepthSnapshot {
+    fn depth_snapshot(&self, symbol: &str, limit: u64) -> Result<OrderBook>;
+}
+
+impl DepthSnapshot for Market {
+    fn depth_snapshot(&self, symbol: &str, limit: u64) -> Result<OrderBook> {
+        self.get_custom_depth(symbol, limit)
+    }
+}
+
+impl DepthSnapshot for FuturesMarket {
+    fn depth_snapshot(&self, symbol: &str, limit: u64) -> Result<OrderBook> {
+        let book = self.get_custom_depth(symbol, limit)?;
+        Ok(OrderBook {
+            last_update_id: book.last_update_id,
+            bids: book.bids,
+            asks: book.asks,
+        })
+    }
+}
+
+// Spot and USD-M futures streams use different sequencing rules:
+// https://developers.binance.com/docs/binance-spot-api-docs/web-socket-streams#how-to-manage-a-local-order-book-correctly
+// https://developers.binance.com/docs/derivatives/usds-margined-futures/websocket-market-streams/How-to-manage-a-local-order-book-correctly
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SequenceRules {
+    Spot,
+    Futures,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Price(f64);
+
+impl Eq for Price {}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Price {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.partial_cmp(&other.0).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Local copy of the order book of one symbol.
+#[derive(Clone, Debug)]
+pub struct LocalOrderBook {
+    pub symbol: String,
+    pub last_update_id: u64,
+    bids: BTreeMap<Price, f64>,
+    asks: BTreeMap<Price, f64>,
+}
+
+impl LocalOrderBook {
+    fn from_snapshot(symbol: &str, snapshot: OrderBook) -> Self {
+        LocalOrderBook {
+            symbol: symbol.into(),
+            last_update_id: snapshot.last_update_id,
+            bids: snapshot
+                .bids
+                .into_iter()
+                .map(|b| (Price(b.price), b.qty))
+                .collect(),
+            asks: snapshot
+                .asks
+                .into_iter()
+                .map(|a| (Price(a.price), a.qty))
+                .collect(),
+        }
+    }
+
+    fn apply(&mut self, event: &DepthOrderBookEvent) {
+        for bid in &event.bids {
+            update_level(&mut self.bids, bid.price, bid.qty);
+        }
+        for ask in &event.asks {
+            update_level(&mut self.asks, ask.price, ask.qty);
+        }
+        self.last_update_id = event.final_update_id;
+    }
+
+    pub fn best_bid(&self) -> Option<Bids> {
+        self.bids
+            .iter()
+            .next_back()
+            .map(|(price, qty)| Bids::new(price.0, *qty))
+    }
+
+    pub fn best_ask(&self) -> Option<Asks> {
+        self.asks.iter().next().map(|(price, qty)| Asks {
+            price: price.0,
+            qty: *qty,
+        })
+    }
+
+    // Bids from the best price down, at most 'levels' of them
+    pub fn bids(&self, levels: usize) -> Vec<Bids> {
+        self.bids
+            .iter()
+            .rev()
+            .take(levels)
+            .map(|(price, qty)| Bids::new(price.0, *qty))
+            .collect()
+    }
+
+    // Asks from the best price up, at most 'levels' of them
+    pub fn asks(&self, levels: usize) -> Vec<Asks> {
+        self.asks
+            .iter()
+            .take(levels)
+            .map(|(price, qty)| Asks {
+                price: price.0,
+                qty: *qty,
+            })
+            .collect()
+    }
+}
+
+fn update_level(side: &mut BTreeMap<Price, f64>, price: f64, qty: f64) {
+    if qty == 0.0 {
+        side.remove(&Price(price));
+    } else {
+        side.insert(Price(price), qty);
+    }
+}
+
+enum BookState {
+    // Waiting for a snapshot that overlaps the buffered events, the last one fetched was
+    // older than them or a gap was found. The next one is fetched at 'retry_at'.
+    Buffering {
+        events: Vec<DepthOrderBookEvent>,
+        retry_at: Instant,
+    },
+    // Snapshot fetched, waiting for the first event following it
+    Snapshot(LocalOrderBook),
+    Synced(LocalOrderBook),
+}
+
+fn buffering(events: Vec<DepthOrderBookEvent>, retry_at: Instant) -> BookState {
+    BookState::Buffering { events, retry_at }
+}
+
+type ChangeHandler<'a> = Box<dyn FnMut(&LocalOrderBook) -> Result<()> + 'a>;
+
+/// Maintains local order books from a diff depth stream (`<symbol>@depth`)
+/// and REST snapshots.
+///
+/// A snapshot is fetched on the first event of a symbol, stale events are discarded and a
+/// new snapshot is fetched whenever a sequence gap is detected. Events are buffered while
+/// waiting for a snapshot which is not older than them.
+pub struct OrderBookManager<'a> {
+    source: Box<dyn DepthSnapshot + 'a>,
+    rules: SequenceRules,
+    depth: u64,
+    // Wait before fetching again a snapshot which was older than the buffered events
+    resync_delay: Duration,
+    books: HashMap<String, BookState>,
+    handler: Option<ChangeHandler<'a>>,
+}
+
+impl<'a> OrderBookManager<'a> {
+    pub fn new<D>(source: D, rules: SequenceRules, depth: u64) -> OrderBookManager<'a>
+    where
+        D: DepthSnapshot + 'a,
+    {
+        OrderBookManager {
+            source: Box::new(source),
+            rules,
+            depth,
+            resync_delay: Duration::from_secs(1),
+            books: HashMap::new(),
+            handler: None,
+        }
+    }
+
+    // Snapshots at a custom depth, up to 5000 levels
+    pub fn spot(market: Market, depth: u64) -> OrderBookManager<'a> {
+        Self::new(market, SequenceRules::Spot, depth)
+    }
+
+    // Snapshots at a custom depth, up to 1000 levels
+    pub fn futures(market: FuturesMarket, depth: u64) -> OrderBookManager<'a> {
+        Self::new(market, SequenceRules::Futures, depth)
+    }
+
+    pub fn set_resync_delay(mut self, resync_delay: Duration) -> Self {
+        self.resync_delay = resync_delay;
+        self
+    }
+
+    /// Called with the updated book every time an event has been applied.
+    pub fn on_change<Callback>(&mut self, handler: Callback)
+    where
+        Callback: FnMut(&LocalOrderBook) -> Result<()> + 'a,
+    {
+        self.handler = Some(Box::new(handler));
+    }
+
+    /// Returns the book of a symbol, once an event following its snapshot was applied.
+    pub fn book(&self, symbol: &str) -> Option<&LocalOrderBook> {
+        match self.books.get(&symbol.to_uppercase()) {
+            Some(BookState::Synced(book)) => Some(book),
+            _ => None,
+        }
+    }
+
+    pub fn is_synced(&self, symbol: &str) -> bool {
+        self.book(symbol).is_some()
+    }
+
+    pub fn handle_event(&mut self, event: DepthOrderBookEvent) -> Result<()> {
+        let symbol = event.symbol.to_uppercase();
+        let synchronized = match self.books.remove(&symbol) {
+            None => self.synchronize(&symbol, vec![event]),
+            Some(BookState::Buffering {
+                mut events,
+                retry_at,
+            }) => {
+                events.push(event);
+                if Instant::now() < retry_at {
+                    Ok((BookState::Buffering { events, retry_at }, false))
+                } else {
+                    self.synchronize(&symbol, events)
+                }
+            }
+            Some(state) => match self.advance(state, &event) {
+                Ok(advanced) => Ok(advanced),
+                Err(()) => self.synchronize(&symbol, vec![event]),
+            },
+        };
+        let (state, changed) = match synchronized {
+            Ok(synchronized) => synchronized,
+            Err((state, e)) => {
+                self.books.insert(symbol, state);
+                return Err(e);
+            }
+        };
+
+        // Kept even if the handler fails
+        self.books.insert(symbol.clone(), state);
+        if changed {
+            self.notify(&symbol)?;
+        }
+        Ok(())
+    }
+
+    // Fetches a snapshot and replays the buffered events on top of it, telling whether
+    // the book was synchronized
+    fn synchronize(
+        &mut self, symbol: &str, events: Vec<DepthOrderBookEvent>,
+    ) -> std::result::Result<(BookState, bool), (BookState, Error)> {
+        let snapshot = match self.source.depth_snapshot(symbol, self.depth) {
+            Ok(snapshot) => snapshot,
+            // The events are kept, the snapshot is fetched again after the delay
+            Err(e) => return Err((buffering(events, Instant::now() + self.resync_delay), e)),
+        };
+
+        // The snapshot is older than the buffered events
+        if snapshot.last_update_id < events[0].first_update_id {
+            return Ok((buffering(events, Instant::now() + self.resync_delay), false));
+        }
+
+        let mut state = BookState::Snapshot(LocalOrderBook::from_snapshot(symbol, snapshot));
+        for event in events {
+            state = match self.advance(state, &event) {
+                Ok((state, _)) => state,
+                // A gap in the buffered events, the next snapshot is fetched at once
+                Err(()) => return Ok((buffering(vec![event], Instant::now()), false)),
+            };
+        }
+
+        let synced = matches!(state, BookState::Synced(_));
+        Ok((state, synced))
+    }
+
+    // Applies an event on a book, telling whether it changed it.
+    // Fails when the event does not follow the book, which then has to be rebuilt.
+    fn advance(
+        &self, state: BookState, event: &DepthOrderBookEvent,
+    ) -> std::result::Result<(BookState, bool), ()> {
+        match state {
+            BookState::Snapshot(book) if self.is_stale(&book, event) => {
+                Ok((BookState::Snapshot(book), false))
+            }
+            BookState::Snapshot(mut book) if self.overlaps(&book, event) => {
+                book.apply(event);
+                Ok((BookState::Synced(book), true))
+            }
+            BookState::Synced(mut book) if self.is_continuous(&book, event) => {
+                book.apply(event);
+                Ok((BookState::Synced(book), true))
+            }
+            // Already applied
+            BookState::Synced(book) if event.final_update_id <= book.last_update_id => {
+                Ok((BookState::Synced(book), false))
+            }
+            _ => Err(()),
+        }
+    }
+
+    fn is_stale(&self, book: &LocalOrderBook, event: &DepthOrderBookEvent) -> bool {
+        match self.rules {
+            SequenceRules::Spot => event.final_update_id <= book.last_update_id,
+            SequenceRules::Futures => event.final_update_id < book.last_update_id,
+        }
+    }
+
+    // The first event applied on a snapshot must contain its last update id
+    fn overlaps(&self, book: &LocalOrderBook, event: &DepthOrderBookEvent) -> bool {
+        match self.rules {
+            SequenceRules::Spot => {
+                event.first_update_id <= book.last_update_id + 1
+                    && event.final_update_id > book.last_update_id
+            }
+            SequenceRules::Futures => {
+                event.first_update_id <= book.last_update_id
+                    && event.final_update_id >= book.last_update_id
+            }
+        }
+    }
+
+    fn is_continuous(&self, book: &LocalOrderBook, event: &DepthOrderBookEvent) -> bool {
+        match self.rules {
+            SequenceRules::Spot => event.first_update_id == book.last_update_id + 1,
+            SequenceRules::Futures => event.previous_final_update_id == Some(book.last_update_id),
+        }
+    }
+
+    fn notify(&mut self, symbol: &str) -> Result<()> {
+        if let (Some(handler), Some(BookState::Synced(book))) =
+            (self.handler.as_mut(), self.books.get(symbol))
+        {
+            handler(book)?;
+        }
+        Ok(())
+    }
+}
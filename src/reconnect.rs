@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How `event_loop_with_reconnect` recovers lost connections.
+#[derive(Clone, Debug)]
+pub struct ReconnectPolicy {
+    // Delay before the first attempt, doubled after every failure
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    // None to retry forever
+    pub max_attempts: Option<u32>,
+    // Binance closes every connection after 24 hours, a new one is opened before that
+    pub rotate_after: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            max_attempts: None,
+            rotate_after: Duration::from_secs(23 * 60 * 60),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    pub fn set_initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    pub fn set_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn set_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    pub fn set_rotate_after(mut self, rotate_after: Duration) -> Self {
+        self.rotate_after = rotate_after;
+        self
+    }
+
+    // Delay before the given attempt, starting at 1
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
+
+/// Connection changes reported to the handler by `event_loop_with_reconnect`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    Disconnected { reason: String },
+    // Delay in milliseconds before the attempt
+    Reconnecting { attempt: u32, delay: u64 },
+    Connected { url: String },
+    // Replaced by a new connection before the 24 hours limit
+    Rotated { url: String },
+}
+
+// Sleeps, returning early when 'running' is cleared
+pub(crate) fn sleep_while_running(duration: Duration, running: &AtomicBool) {
+    let deadline = Instant::now() + duration;
+    while running.load(Ordering::Relaxed) {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        thread::sleep((deadline - now).min(Duration::from_millis(100)));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::errors::Result;
+use error_chain::bail;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Appends the raw frames of websocket connections to a log file.
+///
+/// Every frame is written as a `<received_at> <connection_id> <length>` header, a space,
+/// the frame and a newline, so frames containing newlines can be read back. The length of
+/// binary frames is prefixed with `b`.
+pub struct SessionRecorder {
+    writer: BufWriter<File>,
+}
+
+impl SessionRecorder {
+    /// Opens the log, appending to it if it exists.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(SessionRecorder {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    // Received time in milliseconds, as in `EventEnvelope`
+    pub fn record(&mut self, received_at: u64, connection_id: u64, frame: &str) -> Result<()> {
+        self.write(received_at, connection_id, "", frame.as_bytes())
+    }
+
+    pub fn record_binary(
+        &mut self, received_at: u64, connection_id: u64, frame: &[u8],
+    ) -> Result<()> {
+        self.write(received_at, connection_id, "b", frame)
+    }
+
+    fn write(
+        &mut self, received_at: u64, connection_id: u64, marker: &str, frame: &[u8],
+    ) -> Result<()> {
+        write!(
+            self.writer,
+            "{} {} {}{} ",
+            received_at,
+            connection_id,
+            marker,
+            frame.len()
+        )?;
+        self.writer.write_all(frame)?;
+        self.writer.write_all(b"\n")?;
+        // Frames are not lost if the process stops
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// A frame read from a session log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame {
+    pub received_at: u64,
+    pub connection_id: u64,
+    pub frame: Frame,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Text(String),
+    // Only the SBE streams send binary frames
+    Binary(Vec<u8>),
+}
+
+/// Reads the frames of a session log, in the order they were recorded.
+pub struct SessionReader<R> {
+    reader: BufReader<R>,
+}
+
+impl SessionReader<File> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> SessionReader<R> {
+    pub fn new(reader: R) -> Self {
+        SessionReader {
+            reader: BufReader::new(reader),
+        }
+    }
+
+    pub fn read_frame(&mut self) -> Result<Option<RecordedFrame>> {
+        let mut header = Vec::new();
+        if self.reader.read_until(b' ', &mut header)? == 0 {
+            return Ok(None);
+        }
+        let received_at = parse_field(&header)?;
+        header.clear();
+        self.reader.read_until(b' ', &mut header)?;
+        let connection_id = parse_field(&header)?;
+        header.clear();
+        self.reader.read_until(b' ', &mut header)?;
+        let binary = header.first() == Some(&b'b');
+        let length: usize = parse_field(&header[binary as usize..])?;
+
+        // The frame and its newline
+        let mut frame = vec![0; length + 1];
+        self.reader.read_exact(&mut frame)?;
+        if frame.pop() != Some(b'\n') {
+            bail!("Session log frame is not followed by a newline");
+        }
+        let frame = if binary {
+            Frame::Binary(frame)
+        } else {
+            match String::from_utf8(frame) {
+                Ok(frame) => Frame::Text(frame),
+                Err(e) => bail!(format!("Session log frame is not valid UTF-8: {}", e)),
+            }
+        };
+        Ok(Some(RecordedFrame {
+            received_at,
+            connection_id,
+            frame,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for SessionReader<R> {
+    type Item = Result<RecordedFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_frame().transpose()
+    }
+}
+
+fn parse_field<T: std::str::FromStr>(field: &[u8]) -> Result<T> {
+    let field = String::from_utf8_lossy(field);
+    match field.trim_end_matches(' ').parse() {
+        Ok(value) => Ok(value),
+        Err(_) => bail!(format!("Invalid session log header field '{}'", field)),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplaySpeed {
+    AsFastAsPossible,
+    // Waits between frames as long as between their receive times
+    Recorded,
+    // Like Recorded, with the waits divided by the given factor
+    Scaled(f64),
+}
+
+// Passes the frames of a log to 'handle' at the given speed, returns how many were replayed
+pub(crate) fn replay<R, F>(
+    reader: SessionReader<R>, speed: ReplaySpeed, mut handle: F,
+) -> Result<u64>
+where
+    R: Read,
+    F: FnMut(RecordedFrame) -> Result<()>,
+{
+    let factor = match speed {
+        ReplaySpeed::AsFastAsPossible => None,
+        ReplaySpeed::Recorded => Some(1.0),
+        ReplaySpeed::Scaled(factor) if factor > 0.0 => Some(factor),
+        ReplaySpeed::Scaled(factor) => bail!(format!("Invalid replay speed {}", factor)),
+    };
+
+    let mut start: Option<(Instant, u64)> = None;
+    let mut count = 0;
+    for frame in reader {
+        let frame = frame?;
+        if let Some(factor) = factor {
+            let (started_at, first_received_at) =
+                *start.get_or_insert((Instant::now(), frame.received_at));
+            let offset = frame.received_at.saturating_sub(first_received_at) as f64 / factor;
+            let due = started_at + Duration::from_micros((offset * 1000.0) as u64);
+            let now = Instant::now();
+            if due > now {
+                thread::sleep(due - now);
+            }
+        }
+        handle(frame)?;
+        count += 1;
+    }
+    Ok(count)
+}
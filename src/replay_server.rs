@@ -0,0 +1,366 @@
+use crate::errors::Result;
+use crate::recorder::{Frame, SessionReader};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, VecDeque};
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::handshake::server::{Request, Response};
+use tungstenite::{Message, WebSocket};
+use url::Url;
+
+// How often connections check for actions and the server being stopped
+static POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// What the server does on a connection.
+#[derive(Clone, Debug)]
+pub enum ServerAction {
+    // Sends the event if the connection is subscribed to the stream,
+    // in a `{"stream":..,"data":..}` envelope on combined stream connections
+    Event { stream: String, payload: String },
+    // Sends a text frame as is
+    Frame(String),
+    // Sends a binary frame as is
+    Binary(Vec<u8>),
+    // Sends a text frame which is not valid JSON
+    Malformed,
+    Ping,
+    // Closes the connection with a close frame
+    Close,
+    // Drops the connection without a close frame
+    Drop,
+    Wait(Duration),
+    // Waits until the connection is subscribed to the stream
+    AwaitSubscription(String),
+}
+
+impl ServerAction {
+    pub fn event<S, P>(stream: S, payload: P) -> Self
+    where
+        S: Into<String>,
+        P: Into<String>,
+    {
+        ServerAction::Event {
+            stream: stream.into(),
+            payload: payload.into(),
+        }
+    }
+
+    /// The frames of a session log, waiting between them as long as when they were recorded.
+    pub fn from_session_log<P: AsRef<Path>>(path: P) -> Result<Vec<ServerAction>> {
+        let mut actions = Vec::new();
+        let mut previous: Option<u64> = None;
+        for frame in SessionReader::open(path)? {
+            let frame = frame?;
+            if let Some(previous) = previous {
+                let wait = frame.received_at.saturating_sub(previous);
+                actions.push(ServerAction::Wait(Duration::from_millis(wait)));
+            }
+            previous = Some(frame.received_at);
+            actions.push(match frame.frame {
+                Frame::Text(text) => ServerAction::Frame(text),
+                Frame::Binary(bytes) => ServerAction::Binary(bytes),
+            });
+        }
+        Ok(actions)
+    }
+}
+
+#[derive(Default)]
+struct ServerState {
+    scripts: VecDeque<Vec<ServerAction>>,
+    connections: Vec<Sender<ServerAction>>,
+    requests: Vec<String>,
+    received: Vec<String>,
+    pongs: usize,
+}
+
+/// Local websocket server speaking the stream protocol of Binance, to test stream
+/// consumers offline.
+///
+/// The nth connection runs the nth script, streams are subscribed by the connection
+/// path (`/ws/<stream>`, `/stream?streams=<stream>/<stream>`) and by `SUBSCRIBE` requests.
+/// The server is stopped when dropped.
+pub struct ReplayServer {
+    address: SocketAddr,
+    running: Arc<AtomicBool>,
+    state: Arc<Mutex<ServerState>>,
+}
+
+impl ReplayServer {
+    pub fn start(scripts: Vec<Vec<ServerAction>>) -> Result<ReplayServer> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let server = ReplayServer {
+            address: listener.local_addr()?,
+            running: Arc::new(AtomicBool::new(true)),
+            state: Arc::new(Mutex::new(ServerState {
+                scripts: scripts.into(),
+                ..Default::default()
+            })),
+        };
+
+        let running = server.running.clone();
+        let state = server.state.clone();
+        thread::spawn(move || {
+            while running.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let (sender, commands) = mpsc::channel();
+                        let script = {
+                            let mut state = state.lock().unwrap();
+                            state.connections.push(sender);
+                            state.scripts.pop_front().unwrap_or_default()
+                        };
+                        let mut connection = Connection {
+                            actions: script.into(),
+                            commands,
+                            running: running.clone(),
+                            state: state.clone(),
+                            streams: BTreeSet::new(),
+                            combined: false,
+                            waiting_until: None,
+                            awaiting: None,
+                        };
+                        thread::spawn(move || connection.serve(stream));
+                    }
+                    Err(ref e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                    Err(_) => break,
+                }
+            }
+        });
+        Ok(server)
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    // "ws://127.0.0.1:<port>"
+    pub fn endpoint(&self) -> String {
+        format!("ws://{}", self.address)
+    }
+
+    // Endpoint of raw streams, as in `Config::ws_endpoint`
+    pub fn ws_endpoint(&self) -> String {
+        format!("{}/ws", self.endpoint())
+    }
+
+    /// Runs an action on every open connection, after their scripts.
+    pub fn push(&self, action: ServerAction) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .connections
+            .retain(|connection| connection.send(action.clone()).is_ok());
+    }
+
+    // Path and query of every connection, in the order they were opened
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    // Text frames sent by the clients
+    pub fn received(&self) -> Vec<String> {
+        self.state.lock().unwrap().received.clone()
+    }
+
+    pub fn pongs(&self) -> usize {
+        self.state.lock().unwrap().pongs
+    }
+}
+
+impl Drop for ReplayServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+struct Connection {
+    actions: VecDeque<ServerAction>,
+    commands: Receiver<ServerAction>,
+    running: Arc<AtomicBool>,
+    state: Arc<Mutex<ServerState>>,
+    streams: BTreeSet<String>,
+    combined: bool,
+    waiting_until: Option<Instant>,
+    awaiting: Option<String>,
+}
+
+impl Connection {
+    fn serve(&mut self, stream: TcpStream) {
+        let mut uri = String::new();
+        let socket = stream.set_nonblocking(false).ok().and_then(|_| {
+            tungstenite::accept_hdr(stream, |request: &Request, response: Response| {
+                uri = request.uri().to_string();
+                Ok(response)
+            })
+            .ok()
+        });
+        let mut socket = match socket {
+            Some(socket) => socket,
+            None => return,
+        };
+        self.open(&uri);
+        if socket
+            .get_ref()
+            .set_read_timeout(Some(POLL_INTERVAL))
+            .is_err()
+        {
+            return;
+        }
+
+        while self.running.load(Ordering::Relaxed) {
+            let open = match self.next_action() {
+                Some(action) => self.run(&mut socket, action),
+                None => self.read(&mut socket),
+            };
+            if !open {
+                return;
+            }
+        }
+    }
+
+    fn open(&mut self, uri: &str) {
+        self.state.lock().unwrap().requests.push(uri.to_string());
+        let url = match Url::parse(&format!("ws://localhost{}", uri)) {
+            Ok(url) => url,
+            Err(_) => return,
+        };
+        if url.path().starts_with("/stream") {
+            self.combined = true;
+            if let Some((_, streams)) = url.query_pairs().find(|(key, _)| key == "streams") {
+                self.subscribe(streams.split('/'));
+            }
+        } else if let Some(streams) = url.path().strip_prefix("/ws/") {
+            self.subscribe(streams.split('/'));
+        }
+    }
+
+    fn subscribe<'s, I: Iterator<Item = &'s str>>(&mut self, streams: I) {
+        self.streams.extend(
+            streams
+                .filter(|stream| !stream.is_empty())
+                .map(String::from),
+        );
+    }
+
+    // The next action which can be run, scripts first
+    fn next_action(&mut self) -> Option<ServerAction> {
+        if let Some(until) = self.waiting_until {
+            if Instant::now() < until {
+                return None;
+            }
+            self.waiting_until = None;
+        }
+        if let Some(ref stream) = self.awaiting {
+            if !self.streams.contains(stream) {
+                return None;
+            }
+            self.awaiting = None;
+        }
+        self.actions
+            .pop_front()
+            .or_else(|| self.commands.try_recv().ok())
+    }
+
+    // Returns false when the connection is over
+    fn run(&mut self, socket: &mut WebSocket<TcpStream>, action: ServerAction) -> bool {
+        let message = match action {
+            ServerAction::Event { stream, payload } => {
+                if !self.streams.contains(&stream) {
+                    return true;
+                }
+                if self.combined {
+                    Message::Text(format!(r#"{{"stream":"{}","data":{}}}"#, stream, payload))
+                } else {
+                    Message::Text(payload)
+                }
+            }
+            ServerAction::Frame(frame) => Message::Text(frame),
+            ServerAction::Binary(frame) => Message::Binary(frame),
+            ServerAction::Malformed => Message::Text(r#"{"e":"trade","E":"#.into()),
+            ServerAction::Ping => Message::Ping(Vec::new()),
+            ServerAction::Close => {
+                if socket.close(None).is_ok() {
+                    while socket.read().is_ok() {}
+                }
+                return false;
+            }
+            ServerAction::Drop => return false,
+            ServerAction::Wait(duration) => {
+                self.waiting_until = Some(Instant::now() + duration);
+                return true;
+            }
+            ServerAction::AwaitSubscription(stream) => {
+                self.awaiting = Some(stream);
+                return true;
+            }
+        };
+        socket.send(message).is_ok()
+    }
+
+    // Returns false when the connection is over
+    fn read(&mut self, socket: &mut WebSocket<TcpStream>) -> bool {
+        match socket.read() {
+            Ok(Message::Text(msg)) => {
+                self.state.lock().unwrap().received.push(msg.clone());
+                let response = self.answer(&msg);
+                socket.send(Message::Text(response.to_string())).is_ok()
+            }
+            Ok(Message::Pong(_)) => {
+                self.state.lock().unwrap().pongs += 1;
+                true
+            }
+            Ok(Message::Close(_)) => false,
+            Ok(_) => true,
+            Err(tungstenite::Error::Io(ref e))
+                if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut =>
+            {
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    // Answer to a request of the client, as sent by Binance
+    fn answer(&mut self, msg: &str) -> Value {
+        let request: Value = match serde_json::from_str(msg) {
+            Ok(request) => request,
+            Err(e) => {
+                return json!({"error": {"code": 3, "msg": format!("Invalid JSON: {}", e)}, "id": null})
+            }
+        };
+        let id = request["id"].clone();
+        let streams: Vec<String> = request["params"]
+            .as_array()
+            .map(|params| {
+                params
+                    .iter()
+                    .filter_map(|param| param.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        match request["method"].as_str() {
+            Some("SUBSCRIBE") => {
+                self.streams.extend(streams);
+                json!({"result": null, "id": id})
+            }
+            Some("UNSUBSCRIBE") => {
+                for stream in streams {
+                    self.streams.remove(&stream);
+                }
+                json!({"result": null, "id": id})
+            }
+            Some("LIST_SUBSCRIPTIONS") => json!({"result": self.streams, "id": id}),
+            Some("SET_PROPERTY") => json!({"result": null, "id": id}),
+            Some("GET_PROPERTY") => json!({"result": false, "id": id}),
+            _ => json!({"error": {"code": 2, "msg": "Invalid request: unknown method"}, "id": id}),
+        }
+    }
+}
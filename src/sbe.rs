@@ -0,0 +1,279 @@
+// Decoder of the Simple Binary Encoding market data streams, hand-written against the
+// `stream_1_0` schema published by Binance (schema id 1, version 0). Fields added by later
+// versions are skipped by honoring the block lengths of the messages and groups.
+use crate::errors::Result;
+use crate::model::{Asks, Bids, BookTickerEvent, DepthOrderBookEvent, OrderBook, TradeEvent};
+use crate::websockets::WebsocketEvent;
+use error_chain::bail;
+use std::convert::TryInto;
+
+pub const SCHEMA_ID: u16 = 1;
+
+pub const TRADES_STREAM_EVENT: u16 = 10000;
+pub const BEST_BID_ASK_STREAM_EVENT: u16 = 10001;
+pub const DEPTH_SNAPSHOT_STREAM_EVENT: u16 = 10002;
+pub const DEPTH_DIFF_STREAM_EVENT: u16 = 10003;
+
+/// Decodes a binary frame into the events of the JSON streams. A trades message can hold
+/// several trades, messages of unknown templates are ignored.
+pub fn decode(frame: &[u8]) -> Result<Vec<WebsocketEvent>> {
+    decode_stream(frame).map(|(_, events)| events)
+}
+
+/// Like `decode`, with the name of the stream the frame was received on, `bnbbtc@trade` for
+/// a trades message of BNBBTC.
+pub fn decode_stream(frame: &[u8]) -> Result<(Option<String>, Vec<WebsocketEvent>)> {
+    let mut cursor = Cursor::new(frame);
+    let block_length = cursor.u16()? as usize;
+    let template_id = cursor.u16()?;
+    let schema_id = cursor.u16()?;
+    let _version = cursor.u16()?;
+    if schema_id != SCHEMA_ID {
+        bail!(format!("Unknown SBE schema id {}", schema_id));
+    }
+
+    let (symbol, events) = match template_id {
+        TRADES_STREAM_EVENT => decode_trades(&mut cursor, block_length)?,
+        BEST_BID_ASK_STREAM_EVENT => decode_best_bid_ask(&mut cursor, block_length)?,
+        DEPTH_SNAPSHOT_STREAM_EVENT => decode_depth_snapshot(&mut cursor, block_length)?,
+        DEPTH_DIFF_STREAM_EVENT => decode_depth_diff(&mut cursor, block_length)?,
+        _ => return Ok((None, Vec::new())),
+    };
+    // Each template is sent on a single stream, the snapshots on `depth20` only
+    let stream = match template_id {
+        TRADES_STREAM_EVENT => "trade",
+        BEST_BID_ASK_STREAM_EVENT => "bestBidAsk",
+        DEPTH_SNAPSHOT_STREAM_EVENT => "depth20",
+        _ => "depth",
+    };
+    Ok((
+        Some(format!("{}@{}", symbol.to_lowercase(), stream)),
+        events,
+    ))
+}
+
+fn decode_trades(
+    cursor: &mut Cursor, block_length: usize,
+) -> Result<(String, Vec<WebsocketEvent>)> {
+    let mut block = cursor.block(block_length, 18)?;
+    let event_time = block.i64()?;
+    let transact_time = block.i64()?;
+    let price_exponent = block.i8()?;
+    let qty_exponent = block.i8()?;
+
+    let (entry_length, count) = cursor.group_size()?;
+    let mut trades = Vec::new();
+    for _ in 0..count {
+        let mut entry = cursor.block(entry_length, 25)?;
+        let id = entry.i64()?;
+        let price = entry.i64()?;
+        let qty = entry.i64()?;
+        let is_buyer_maker = entry.u8()? == 1;
+        trades.push((id, price, qty, is_buyer_maker));
+    }
+    let symbol = cursor.var_string8()?;
+
+    let events = trades
+        .into_iter()
+        .map(|(id, price, qty, is_buyer_maker)| {
+            WebsocketEvent::Trade(TradeEvent {
+                event_type: "trade".into(),
+                event_time: to_millis(event_time),
+                symbol: symbol.clone(),
+                trade_id: id as u64,
+                price: decimal(price, price_exponent),
+                qty: decimal(qty, qty_exponent),
+                // Not sent on the binary streams
+                buyer_order_id: 0,
+                seller_order_id: 0,
+                trade_order_time: to_millis(transact_time),
+                is_buyer_maker,
+                m_ignore: true,
+            })
+        })
+        .collect();
+    Ok((symbol, events))
+}
+
+fn decode_best_bid_ask(
+    cursor: &mut Cursor, block_length: usize,
+) -> Result<(String, Vec<WebsocketEvent>)> {
+    let mut block = cursor.block(block_length, 50)?;
+    let _event_time = block.i64()?;
+    let book_update_id = block.i64()?;
+    let price_exponent = block.i8()?;
+    let qty_exponent = block.i8()?;
+    let bid_price = block.i64()?;
+    let bid_qty = block.i64()?;
+    let ask_price = block.i64()?;
+    let ask_qty = block.i64()?;
+    let symbol = cursor.var_string8()?;
+
+    let event = WebsocketEvent::BookTicker(BookTickerEvent {
+        update_id: book_update_id as u64,
+        symbol: symbol.clone(),
+        best_bid: decimal(bid_price, price_exponent),
+        best_bid_qty: decimal(bid_qty, qty_exponent),
+        best_ask: decimal(ask_price, price_exponent),
+        best_ask_qty: decimal(ask_qty, qty_exponent),
+    });
+    Ok((symbol, vec![event]))
+}
+
+fn decode_depth_snapshot(
+    cursor: &mut Cursor, block_length: usize,
+) -> Result<(String, Vec<WebsocketEvent>)> {
+    let mut block = cursor.block(block_length, 18)?;
+    let _event_time = block.i64()?;
+    let book_update_id = block.i64()?;
+    let price_exponent = block.i8()?;
+    let qty_exponent = block.i8()?;
+    let bids = cursor.levels(price_exponent, qty_exponent)?;
+    let asks = cursor.levels(price_exponent, qty_exponent)?;
+    let symbol = cursor.var_string8()?;
+
+    let event = WebsocketEvent::OrderBook(OrderBook {
+        last_update_id: book_update_id as u64,
+        bids: bids
+            .into_iter()
+            .map(|(price, qty)| Bids { price, qty })
+            .collect(),
+        asks: asks
+            .into_iter()
+            .map(|(price, qty)| Asks { price, qty })
+            .collect(),
+    });
+    Ok((symbol, vec![event]))
+}
+
+fn decode_depth_diff(
+    cursor: &mut Cursor, block_length: usize,
+) -> Result<(String, Vec<WebsocketEvent>)> {
+    let mut block = cursor.block(block_length, 26)?;
+    let event_time = block.i64()?;
+    let first_book_update_id = block.i64()?;
+    let last_book_update_id = block.i64()?;
+    let price_exponent = block.i8()?;
+    let qty_exponent = block.i8()?;
+    let bids = cursor.levels(price_exponent, qty_exponent)?;
+    let asks = cursor.levels(price_exponent, qty_exponent)?;
+    let symbol = cursor.var_string8()?;
+
+    let event = WebsocketEvent::DepthOrderBook(DepthOrderBookEvent {
+        event_type: "depthUpdate".into(),
+        event_time: to_millis(event_time),
+        symbol: symbol.clone(),
+        first_update_id: first_book_update_id as u64,
+        final_update_id: last_book_update_id as u64,
+        previous_final_update_id: None,
+        bids: bids
+            .into_iter()
+            .map(|(price, qty)| Bids { price, qty })
+            .collect(),
+        asks: asks
+            .into_iter()
+            .map(|(price, qty)| Asks { price, qty })
+            .collect(),
+    });
+    Ok((symbol, vec![event]))
+}
+
+// The binary streams send times in microseconds
+fn to_millis(micros: i64) -> u64 {
+    (micros / 1000) as u64
+}
+
+/// Formats `mantissa * 10^exponent` as the JSON streams do, "0.00100000" for 100000 and -8.
+pub fn decimal(mantissa: i64, exponent: i8) -> String {
+    let digits = mantissa.unsigned_abs().to_string();
+    let sign = if mantissa < 0 { "-" } else { "" };
+    if exponent >= 0 {
+        return format!("{}{}{}", sign, digits, "0".repeat(exponent as usize));
+    }
+
+    let scale = exponent.unsigned_abs() as usize;
+    let digits = format!("{:0>width$}", digits, width = scale + 1);
+    let (units, decimals) = digits.split_at(digits.len() - scale);
+    format!("{}{}.{}", sign, units, decimals)
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Cursor { data, position: 0 }
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        match self.data.get(self.position..self.position + length) {
+            Some(bytes) => {
+                self.position += length;
+                Ok(bytes)
+            }
+            None => bail!("SBE message is truncated"),
+        }
+    }
+
+    // A block of fields, of which the first 'expected' bytes are known
+    fn block(&mut self, length: usize, expected: usize) -> Result<Cursor<'a>> {
+        if length < expected {
+            bail!(format!(
+                "SBE block of {} bytes is shorter than {} bytes",
+                length, expected
+            ));
+        }
+        Ok(Cursor::new(self.take(length)?))
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i8(&mut self) -> Result<i8> {
+        Ok(self.take(1)?[0] as i8)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    // groupSizeEncoding, the entry length and count
+    fn group_size(&mut self) -> Result<(usize, usize)> {
+        let entry_length = self.u16()? as usize;
+        let count = self.u32()? as usize;
+        Ok((entry_length, count))
+    }
+
+    // groupSize16Encoding of price levels
+    fn levels(&mut self, price_exponent: i8, qty_exponent: i8) -> Result<Vec<(f64, f64)>> {
+        let entry_length = self.u16()? as usize;
+        let count = self.u16()? as usize;
+        let mut levels = Vec::new();
+        for _ in 0..count {
+            let mut entry = self.block(entry_length, 16)?;
+            let price = decimal(entry.i64()?, price_exponent).parse()?;
+            let qty = decimal(entry.i64()?, qty_exponent).parse()?;
+            levels.push((price, qty));
+        }
+        Ok(levels)
+    }
+
+    fn var_string8(&mut self) -> Result<String> {
+        let length = self.u8()? as usize;
+        match std::str::from_utf8(self.take(length)?) {
+            Ok(value) => Ok(value.to_string()),
+            Err(e) => bail!(format!("SBE string is not valid UTF-8: {}", e)),
+        }
+    }
+}
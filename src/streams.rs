@@ -0,0 +1,297 @@
+use crate::errors::Result;
+use crate::futures::account::ContractType;
+use crate::futures::websockets::FuturesMarket;
+use error_chain::bail;
+use std::fmt::Display;
+
+/// Market a stream name is rendered for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamMarket {
+    Spot,
+    UsdM,
+    CoinM,
+    Options,
+}
+
+impl From<&FuturesMarket> for StreamMarket {
+    fn from(market: &FuturesMarket) -> Self {
+        match market {
+            FuturesMarket::USDM => StreamMarket::UsdM,
+            FuturesMarket::COINM => StreamMarket::CoinM,
+            FuturesMarket::Vanilla => StreamMarket::Options,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KlineInterval {
+    // Spot only
+    Seconds1,
+    Minutes1,
+    Minutes3,
+    Minutes5,
+    Minutes15,
+    Minutes30,
+    Hours1,
+    Hours2,
+    Hours4,
+    Hours6,
+    Hours8,
+    Hours12,
+    Days1,
+    Days3,
+    Weeks1,
+    Months1,
+}
+
+impl Display for KlineInterval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Seconds1 => write!(f, "1s"),
+            Self::Minutes1 => write!(f, "1m"),
+            Self::Minutes3 => write!(f, "3m"),
+            Self::Minutes5 => write!(f, "5m"),
+            Self::Minutes15 => write!(f, "15m"),
+            Self::Minutes30 => write!(f, "30m"),
+            Self::Hours1 => write!(f, "1h"),
+            Self::Hours2 => write!(f, "2h"),
+            Self::Hours4 => write!(f, "4h"),
+            Self::Hours6 => write!(f, "6h"),
+            Self::Hours8 => write!(f, "8h"),
+            Self::Hours12 => write!(f, "12h"),
+            Self::Days1 => write!(f, "1d"),
+            Self::Days3 => write!(f, "3d"),
+            Self::Weeks1 => write!(f, "1w"),
+            Self::Months1 => write!(f, "1M"),
+        }
+    }
+}
+
+/// Window of the rolling window ticker streams (spot only).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RollingWindow {
+    Hours1,
+    Hours4,
+    Days1,
+}
+
+impl Display for RollingWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Hours1 => write!(f, "1h"),
+            Self::Hours4 => write!(f, "4h"),
+            Self::Days1 => write!(f, "1d"),
+        }
+    }
+}
+
+/// Number of levels of the partial depth streams.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DepthLevels {
+    Five,
+    Ten,
+    Twenty,
+}
+
+impl Display for DepthLevels {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Five => write!(f, "5"),
+            Self::Ten => write!(f, "10"),
+            Self::Twenty => write!(f, "20"),
+        }
+    }
+}
+
+/// Update speed of the depth streams. When not given, spot streams update every
+/// 1000ms and futures streams every 250ms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpdateSpeed {
+    Ms100,
+    // Futures only
+    Ms250,
+    // Futures only
+    Ms500,
+    // Spot only
+    Ms1000,
+}
+
+impl Display for UpdateSpeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ms100 => write!(f, "100ms"),
+            Self::Ms250 => write!(f, "250ms"),
+            Self::Ms500 => write!(f, "500ms"),
+            Self::Ms1000 => write!(f, "1000ms"),
+        }
+    }
+}
+
+/// A market stream, rendered into its name for a given market.
+///
+/// Symbols can be given in any case, stream names are lowercase.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Stream {
+    AggTrade(String),
+    Trade(String),
+    Kline(String, KlineInterval),
+    MiniTicker(String),
+    AllMiniTickers,
+    Ticker(String),
+    AllTickers,
+    RollingWindowTicker(String, RollingWindow),
+    AllRollingWindowTickers(RollingWindow),
+    BookTicker(String),
+    // Futures only
+    AllBookTickers,
+    PartialDepth(String, DepthLevels, Option<UpdateSpeed>),
+    DiffDepth(String, Option<UpdateSpeed>),
+    // Futures only, updated every 3s or every second when 'every_second' is set
+    MarkPrice { symbol: String, every_second: bool },
+    AllMarkPrices { every_second: bool },
+    // Futures only
+    ForceOrder(String),
+    AllForceOrders,
+    ContinuousKline(String, ContractType, KlineInterval),
+    // USD-M only
+    CompositeIndex(String),
+}
+
+impl Stream {
+    pub fn name(&self, market: StreamMarket) -> Result<String> {
+        let is_spot = market == StreamMarket::Spot;
+        if market == StreamMarket::Options {
+            bail!("Typed streams are not available for options");
+        }
+
+        let name = match self {
+            Stream::AggTrade(symbol) => format!("{}@aggTrade", lower(symbol)),
+            Stream::Trade(symbol) => format!("{}@trade", lower(symbol)),
+            Stream::Kline(symbol, interval) => {
+                if !is_spot && *interval == KlineInterval::Seconds1 {
+                    bail!("1s klines are only available on spot");
+                }
+                format!("{}@kline_{}", lower(symbol), interval)
+            }
+            Stream::MiniTicker(symbol) => format!("{}@miniTicker", lower(symbol)),
+            Stream::AllMiniTickers => "!miniTicker@arr".into(),
+            Stream::Ticker(symbol) => format!("{}@ticker", lower(symbol)),
+            Stream::AllTickers => "!ticker@arr".into(),
+            Stream::RollingWindowTicker(symbol, window) if is_spot => {
+                format!("{}@ticker_{}", lower(symbol), window)
+            }
+            Stream::AllRollingWindowTickers(window) if is_spot => {
+                format!("!ticker_{}@arr", window)
+            }
+            Stream::BookTicker(symbol) => format!("{}@bookTicker", lower(symbol)),
+            Stream::AllBookTickers if !is_spot => "!bookTicker".into(),
+            Stream::PartialDepth(symbol, levels, speed) => format!(
+                "{}@depth{}{}",
+                lower(symbol),
+                levels,
+                depth_speed(market, *speed)?
+            ),
+            Stream::DiffDepth(symbol, speed) => {
+                format!("{}@depth{}", lower(symbol), depth_speed(market, *speed)?)
+            }
+            Stream::MarkPrice {
+                symbol,
+                every_second,
+            } if !is_spot => format!("{}@markPrice{}", lower(symbol), per_second(*every_second)),
+            Stream::AllMarkPrices { every_second } if !is_spot => {
+                format!("!markPrice@arr{}", per_second(*every_second))
+            }
+            Stream::ForceOrder(symbol) if !is_spot => format!("{}@forceOrder", lower(symbol)),
+            Stream::AllForceOrders if !is_spot => "!forceOrder@arr".into(),
+            Stream::ContinuousKline(pair, contract_type, interval) if !is_spot => {
+                if *interval == KlineInterval::Seconds1 {
+                    bail!("1s klines are only available on spot");
+                }
+                format!(
+                    "{}_{}@continuousKline_{}",
+                    lower(pair),
+                    String::from(*contract_type).to_lowercase(),
+                    interval
+                )
+            }
+            Stream::CompositeIndex(symbol) if market == StreamMarket::UsdM => {
+                format!("{}@compositeIndex", lower(symbol))
+            }
+            _ => bail!(format!("{:?} is not available on {:?}", self, market)),
+        };
+        Ok(name)
+    }
+}
+
+fn lower(symbol: &str) -> String {
+    symbol.to_lowercase()
+}
+
+fn per_second(every_second: bool) -> &'static str {
+    if every_second {
+        "@1s"
+    } else {
+        ""
+    }
+}
+
+fn depth_speed(market: StreamMarket, speed: Option<UpdateSpeed>) -> Result<String> {
+    let valid = match (market, speed) {
+        (_, None) => return Ok(String::new()),
+        (StreamMarket::Spot, Some(speed)) => {
+            speed == UpdateSpeed::Ms100 || speed == UpdateSpeed::Ms1000
+        }
+        (_, Some(speed)) => speed != UpdateSpeed::Ms1000,
+    };
+    match speed {
+        Some(speed) if valid => Ok(format!("@{}", speed)),
+        _ => bail!(format!(
+            "{:?} depth updates are not available on {:?}",
+            speed, market
+        )),
+    }
+}
+
+/// Anything `connect` and `connect_multiple_streams` accept: stream names or typed streams.
+pub trait ToStreamName {
+    fn to_stream_name(&self, market: StreamMarket) -> Result<String>;
+}
+
+impl ToStreamName for Stream {
+    fn to_stream_name(&self, market: StreamMarket) -> Result<String> {
+        self.name(market)
+    }
+}
+
+impl ToStreamName for str {
+    fn to_stream_name(&self, _market: StreamMarket) -> Result<String> {
+        Ok(self.to_string())
+    }
+}
+
+impl ToStreamName for String {
+    fn to_stream_name(&self, _market: StreamMarket) -> Result<String> {
+        Ok(self.clone())
+    }
+}
+
+impl<T> ToStreamName for &T
+where
+    T: ToStreamName + ?Sized,
+{
+    fn to_stream_name(&self, market: StreamMarket) -> Result<String> {
+        (**self).to_stream_name(market)
+    }
+}
+
+// Joins stream names for a combined stream connection
+pub(crate) fn join_stream_names<S>(streams: &[S], market: StreamMarket) -> Result<String>
+where
+    S: ToStreamName,
+{
+    let names = streams
+        .iter()
+        .map(|stream| stream.to_stream_name(market))
+        .collect::<Result<Vec<String>>>()?;
+    Ok(names.join("/"))
+}
@@ -0,0 +1,276 @@
+use crate::errors::Result;
+use error_chain::bail;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use url::Url;
+
+// A single connection can listen to a maximum of 1024 streams
+pub const MAX_STREAMS: usize = 1024;
+
+// Requests without an answer for so long are forgotten
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Message sent on an open connection to change its streams.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StreamRequest {
+    pub method: String,
+    pub params: Vec<Value>,
+    pub id: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StreamError {
+    pub code: i64,
+    pub msg: String,
+}
+
+/// Answer to a `StreamRequest`, with the request it answers when it was
+/// sent on the same connection.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StreamResponse {
+    pub id: Option<u64>,
+    #[serde(default)]
+    pub result: Option<Value>,
+    #[serde(default)]
+    pub error: Option<StreamError>,
+    #[serde(default)]
+    pub request: Option<StreamRequest>,
+}
+
+impl StreamResponse {
+    pub fn is_error(&self) -> bool {
+        self.error.is_some()
+    }
+
+    // Streams returned by LIST_SUBSCRIPTIONS
+    pub fn streams(&self) -> Vec<String> {
+        match self.result {
+            Some(Value::Array(ref streams)) => streams
+                .iter()
+                .filter_map(|stream| stream.as_str().map(String::from))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+// Streams of a connection url, "/ws/<stream>" or "/stream?streams=<stream>/<stream>"
+fn url_streams(wss: &str) -> BTreeSet<String> {
+    let url = match Url::parse(wss) {
+        Ok(url) => url,
+        Err(_) => return BTreeSet::new(),
+    };
+    if let Some((_, streams)) = url.query_pairs().find(|(key, _)| key == "streams") {
+        return streams
+            .split('/')
+            .filter(|stream| !stream.is_empty())
+            .map(String::from)
+            .collect();
+    }
+    match url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+    {
+        Some(stream) if !stream.is_empty() && stream != "ws" && stream != "stream" => {
+            vec![stream.to_string()].into_iter().collect()
+        }
+        _ => BTreeSet::new(),
+    }
+}
+
+struct Pending {
+    request: StreamRequest,
+    sent_at: Instant,
+    changed: Vec<String>,
+}
+
+/// Streams of a connection and the requests changing them.
+///
+/// Requests are queued until the rate limit of the connection allows to send them, and
+/// change the streams once they were sent.
+pub(crate) struct Subscriptions {
+    next_id: u64,
+    // Sent requests waiting for their answer, with their send time and the streams
+    // they added or removed
+    pending: HashMap<u64, Pending>,
+    queued: VecDeque<StreamRequest>,
+    // Streams the connection should listen to
+    streams: BTreeSet<String>,
+    // Streams the connection listens to when it is opened
+    url_streams: BTreeSet<String>,
+    sent: VecDeque<Instant>,
+    messages_per_second: usize,
+}
+
+impl Subscriptions {
+    pub(crate) fn new(messages_per_second: usize) -> Self {
+        Subscriptions {
+            next_id: 1,
+            pending: HashMap::new(),
+            queued: VecDeque::new(),
+            streams: BTreeSet::new(),
+            url_streams: BTreeSet::new(),
+            sent: VecDeque::new(),
+            messages_per_second,
+        }
+    }
+
+    // Streams once the queued requests are sent
+    pub(crate) fn streams(&self) -> Vec<String> {
+        let mut streams = self.streams.clone();
+        for request in &self.queued {
+            apply(&mut streams, &request.method, &request.params);
+        }
+        streams.into_iter().collect()
+    }
+
+    // A connection was opened, the requests sent on the previous one are lost
+    pub(crate) fn opened(&mut self, wss: &str) {
+        self.pending.clear();
+        self.url_streams = url_streams(wss);
+    }
+
+    // A new set of streams was requested through the url
+    pub(crate) fn reset(&mut self) {
+        self.queued.clear();
+        self.streams = self.url_streams.clone();
+    }
+
+    // Queues the requests giving a reopened connection the streams of the previous one,
+    // before the requests which were not sent yet
+    pub(crate) fn restore(&mut self) {
+        let missing: Vec<String> = self
+            .streams
+            .difference(&self.url_streams)
+            .cloned()
+            .collect();
+        let removed: Vec<String> = self
+            .url_streams
+            .difference(&self.streams)
+            .cloned()
+            .collect();
+
+        if !removed.is_empty() {
+            let request = self.request("UNSUBSCRIBE", to_params(&removed));
+            self.queued.push_front(request);
+        }
+        if !missing.is_empty() {
+            let request = self.request("SUBSCRIBE", to_params(&missing));
+            self.queued.push_front(request);
+        }
+    }
+
+    pub(crate) fn subscribe(&mut self, streams: &[String]) -> Result<StreamRequest> {
+        let current = self.streams();
+        let added = streams
+            .iter()
+            .filter(|stream| !current.contains(*stream))
+            .count();
+        if current.len() + added > MAX_STREAMS {
+            bail!(format!(
+                "A connection can listen to at most {} streams",
+                MAX_STREAMS
+            ));
+        }
+        Ok(self.request("SUBSCRIBE", to_params(streams)))
+    }
+
+    pub(crate) fn unsubscribe(&mut self, streams: &[String]) -> StreamRequest {
+        self.request("UNSUBSCRIBE", to_params(streams))
+    }
+
+    pub(crate) fn request(&mut self, method: &str, params: Vec<Value>) -> StreamRequest {
+        let request = StreamRequest {
+            method: method.into(),
+            params,
+            id: self.next_id,
+        };
+        self.next_id += 1;
+        request
+    }
+
+    pub(crate) fn enqueue(&mut self, request: StreamRequest) {
+        self.queued.push_back(request);
+    }
+
+    // The next queued request, if the rate limit allows to send it now
+    pub(crate) fn next_due(&mut self) -> Option<StreamRequest> {
+        let window = Duration::from_secs(1);
+        while let Some(first) = self.sent.front() {
+            if first.elapsed() < window {
+                break;
+            }
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= self.messages_per_second {
+            return None;
+        }
+        self.queued.pop_front()
+    }
+
+    // A request which could not be sent, it is sent first once the connection is back
+    pub(crate) fn requeue(&mut self, request: StreamRequest) {
+        self.queued.push_front(request);
+    }
+
+    // A request was sent, its streams are the ones of the connection until it is answered
+    pub(crate) fn sent(&mut self, request: StreamRequest) {
+        let now = Instant::now();
+        self.sent.push_back(now);
+        self.pending
+            .retain(|_, pending| pending.sent_at.elapsed() < RESPONSE_TIMEOUT);
+        let changed = apply(&mut self.streams, &request.method, &request.params);
+        self.pending.insert(
+            request.id,
+            Pending {
+                request,
+                sent_at: now,
+                changed,
+            },
+        );
+    }
+
+    // Matches a response with its request, failed subscriptions are rolled back
+    pub(crate) fn handle_response(&mut self, response: &mut StreamResponse) {
+        let pending = match response.id.and_then(|id| self.pending.remove(&id)) {
+            Some(pending) => pending,
+            None => return,
+        };
+
+        // Only the streams the request changed, the others were there before it
+        if response.is_error() {
+            let streams = pending.changed.into_iter();
+            match pending.request.method.as_str() {
+                "SUBSCRIBE" => streams.for_each(|stream| {
+                    self.streams.remove(&stream);
+                }),
+                "UNSUBSCRIBE" => self.streams.extend(streams),
+                _ => (),
+            }
+        }
+        response.request = Some(pending.request);
+    }
+}
+
+// Changes the streams as the request does once it is accepted, returns the streams
+// which were added or removed
+fn apply(streams: &mut BTreeSet<String>, method: &str, params: &[Value]) -> Vec<String> {
+    let params = params.iter().filter_map(Value::as_str);
+    match method {
+        "SUBSCRIBE" => params
+            .filter(|stream| streams.insert(stream.to_string()))
+            .map(String::from)
+            .collect(),
+        "UNSUBSCRIBE" => params
+            .filter(|stream| streams.remove(*stream))
+            .map(String::from)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn to_params(streams: &[String]) -> Vec<Value> {
+    streams.iter().cloned().map(Value::String).collect()
+}
@@ -0,0 +1,416 @@
+use crate::api::{Futures, FuturesCM, PortfolioMargin, Sapi, Spot, API};
+use crate::client::Client;
+use crate::config::Config;
+use crate::connection::{StreamEvent, WebSocketConnection};
+use crate::errors::Result;
+use crate::futures::websockets::{FuturesWebSockets, FuturesWebsocketEvent};
+use crate::model::{Success, UserDataStream};
+use crate::reconnect::{sleep_while_running, ConnectionEvent, ReconnectPolicy};
+use crate::websockets::{WebSockets, WebsocketEvent};
+use error_chain::bail;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+// Reads time out on a quiet connection so that the keepalive is still sent on time
+const KEEP_ALIVE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Markets with a user data stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UserDataMarket {
+    Spot,
+    // Cross margin
+    Margin,
+    UsdM,
+    CoinM,
+    PortfolioMargin,
+}
+
+impl UserDataMarket {
+    fn api(self) -> API {
+        match self {
+            UserDataMarket::Spot => API::Spot(Spot::UserDataStream),
+            UserDataMarket::Margin => API::Savings(Sapi::UserDataStream),
+            UserDataMarket::UsdM => API::Futures(Futures::UserDataStream),
+            UserDataMarket::CoinM => API::FuturesCM(FuturesCM::UserDataStream),
+            UserDataMarket::PortfolioMargin => {
+                API::PortfolioMargin(PortfolioMargin::UserDataStream)
+            }
+        }
+    }
+
+    fn is_spot(self) -> bool {
+        self == UserDataMarket::Spot || self == UserDataMarket::Margin
+    }
+}
+
+/// Starts, keeps alive and closes the listen keys of a market.
+#[derive(Clone)]
+pub struct ListenKeys {
+    pub client: Client,
+    pub market: UserDataMarket,
+    // Listen keys are appended to it
+    pub ws_endpoint: String,
+}
+
+impl ListenKeys {
+    pub fn new(api_key: Option<String>, market: UserDataMarket) -> Self {
+        Self::new_with_config(api_key, market, &Config::default())
+    }
+
+    pub fn new_with_config(
+        api_key: Option<String>, market: UserDataMarket, config: &Config,
+    ) -> Self {
+        let (rest_api_endpoint, ws_endpoint) = match market {
+            UserDataMarket::Spot | UserDataMarket::Margin => {
+                (&config.rest_api_endpoint, &config.ws_endpoint)
+            }
+            UserDataMarket::UsdM => (
+                &config.futures_rest_api_endpoint,
+                &config.futures_ws_endpoint,
+            ),
+            UserDataMarket::CoinM => (
+                &config.futures_cm_rest_api_endpoint,
+                &config.futures_cm_ws_endpoint,
+            ),
+            UserDataMarket::PortfolioMargin => (
+                &config.portfolio_margin_rest_api_endpoint,
+                &config.portfolio_margin_ws_endpoint,
+            ),
+        };
+        ListenKeys {
+            client: Client::new(api_key, None, rest_api_endpoint.clone()),
+            market,
+            ws_endpoint: ws_endpoint.clone(),
+        }
+    }
+
+    // Returns the current listen key, or a new one if it expired
+    pub fn start(&self) -> Result<UserDataStream> {
+        self.client.post(self.market.api())
+    }
+
+    pub fn keep_alive(&self, listen_key: &str) -> Result<Success> {
+        self.client.put(self.market.api(), listen_key)
+    }
+
+    pub fn close(&self, listen_key: &str) -> Result<Success> {
+        self.client.delete(self.market.api(), listen_key)
+    }
+
+    pub fn ws_url(&self, listen_key: &str) -> String {
+        format!("{}/{}", self.ws_endpoint, listen_key)
+    }
+}
+
+/// Options of `UserStreamManager` and `FuturesUserStreamManager`.
+#[derive(Clone, Debug)]
+pub struct UserStreamConfig {
+    // Listen keys expire 60 minutes after the last keepalive
+    pub keep_alive_interval: Duration,
+    pub reconnect_policy: ReconnectPolicy,
+}
+
+impl Default for UserStreamConfig {
+    fn default() -> Self {
+        UserStreamConfig {
+            keep_alive_interval: Duration::from_secs(30 * 60),
+            reconnect_policy: ReconnectPolicy::default(),
+        }
+    }
+}
+
+impl UserStreamConfig {
+    pub fn set_keep_alive_interval(mut self, keep_alive_interval: Duration) -> Self {
+        self.keep_alive_interval = keep_alive_interval;
+        self
+    }
+
+    pub fn set_reconnect_policy(mut self, reconnect_policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = reconnect_policy;
+        self
+    }
+}
+
+// Connection the manager reads the user data events from
+trait UserDataSocket {
+    fn open(&mut self, url: &str) -> Result<()>;
+    // Reads one message, returns why the connection was lost if it was
+    fn read(&mut self) -> Result<Option<String>>;
+    fn connection_event(&mut self, event: ConnectionEvent) -> Result<()>;
+    // Opens a new connection to the same url before closing the current one
+    fn rotate(&mut self) -> Result<Option<String>>;
+    fn set_max_read_timeout(&mut self, max_read_timeout: Duration);
+    fn close(&mut self);
+}
+
+impl<'a, E: StreamEvent> UserDataSocket for WebSocketConnection<'a, E> {
+    fn open(&mut self, url: &str) -> Result<()> {
+        self.connect_wss(url)
+    }
+
+    fn read(&mut self) -> Result<Option<String>> {
+        self.read_message()
+    }
+
+    fn connection_event(&mut self, event: ConnectionEvent) -> Result<()> {
+        self.emit(E::from_connection(event))
+    }
+
+    fn rotate(&mut self) -> Result<Option<String>> {
+        WebSocketConnection::rotate(self)
+    }
+
+    fn set_max_read_timeout(&mut self, max_read_timeout: Duration) {
+        WebSocketConnection::set_max_read_timeout(self, max_read_timeout);
+    }
+
+    fn close(&mut self) {
+        let _ = self.disconnect();
+        self.socket = None;
+    }
+}
+
+type ResyncHandler<'a> = Box<dyn FnMut() -> Result<()> + 'a>;
+
+// Listen key lifecycle shared by the spot and futures managers
+struct Manager<'a, S> {
+    listen_keys: ListenKeys,
+    config: UserStreamConfig,
+    socket: S,
+    // Set by the event handler when the listen key expired
+    expired: Rc<Cell<bool>>,
+    listen_key: Option<String>,
+    kept_alive_at: Instant,
+    connected_at: Instant,
+    resync: Option<ResyncHandler<'a>>,
+}
+
+impl<'a, S: UserDataSocket> Manager<'a, S> {
+    fn new(
+        listen_keys: ListenKeys, config: UserStreamConfig, mut socket: S, expired: Rc<Cell<bool>>,
+    ) -> Self {
+        socket.set_max_read_timeout(KEEP_ALIVE_CHECK_INTERVAL);
+        Manager {
+            listen_keys,
+            config,
+            socket,
+            expired,
+            listen_key: None,
+            kept_alive_at: Instant::now(),
+            connected_at: Instant::now(),
+            resync: None,
+        }
+    }
+
+    fn event_loop(&mut self, running: &AtomicBool) -> Result<()> {
+        if self.listen_key.is_none() {
+            self.open()?;
+        }
+        while running.load(Ordering::Relaxed) {
+            let mut lost = if self.expired.replace(false) {
+                Some("Listen key expired".to_string())
+            } else if self.connected_at.elapsed() >= self.config.reconnect_policy.rotate_after {
+                // The listen key stays valid, the new connection is opened before the
+                // current one is closed so no event is missed
+                self.connected_at = Instant::now();
+                self.socket.rotate()?
+            } else if self.kept_alive_at.elapsed() >= self.config.keep_alive_interval {
+                self.keep_alive()
+            } else {
+                None
+            };
+            if lost.is_none() {
+                lost = self.socket.read()?;
+            }
+
+            if let Some(reason) = lost {
+                self.reconnect(reason, running)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<()> {
+        let listen_key = self.listen_keys.start()?.listen_key;
+        self.socket.open(&self.listen_keys.ws_url(&listen_key))?;
+        self.listen_key = Some(listen_key);
+        self.expired.set(false);
+        self.kept_alive_at = Instant::now();
+        self.connected_at = Instant::now();
+        Ok(())
+    }
+
+    fn keep_alive(&mut self) -> Option<String> {
+        let listen_key = self.listen_key.as_ref()?;
+        match self.listen_keys.keep_alive(listen_key) {
+            Ok(_) => {
+                self.kept_alive_at = Instant::now();
+                None
+            }
+            // The listen key is recreated if it is not valid anymore
+            Err(e) => Some(format!("Keepalive failed: {}", e)),
+        }
+    }
+
+    // Opens a connection with a valid listen key, then resyncs the state missed meanwhile
+    fn reconnect(&mut self, reason: String, running: &AtomicBool) -> Result<()> {
+        self.socket.close();
+        self.socket
+            .connection_event(ConnectionEvent::Disconnected { reason })?;
+
+        let policy = self.config.reconnect_policy.clone();
+        let mut attempt = 0;
+        while running.load(Ordering::Relaxed) {
+            attempt += 1;
+            if let Some(max_attempts) = policy.max_attempts {
+                if attempt > max_attempts {
+                    bail!(format!(
+                        "Not able to reconnect after {} attempts",
+                        max_attempts
+                    ));
+                }
+            }
+
+            let delay = policy.backoff(attempt);
+            self.socket
+                .connection_event(ConnectionEvent::Reconnecting {
+                    attempt,
+                    delay: delay.as_millis() as u64,
+                })?;
+            sleep_while_running(delay, running);
+
+            if running.load(Ordering::Relaxed) && self.open().is_ok() {
+                let url = self
+                    .listen_keys
+                    .ws_url(self.listen_key.as_deref().unwrap_or(""));
+                self.socket
+                    .connection_event(ConnectionEvent::Connected { url })?;
+                if let Some(ref mut resync) = self.resync {
+                    resync()?;
+                }
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.socket.close();
+        if let Some(listen_key) = self.listen_key.take() {
+            self.listen_keys.close(&listen_key)?;
+        }
+        Ok(())
+    }
+}
+
+/// Spot and margin user data stream, with its listen key kept alive.
+///
+/// The listen key is recreated and the stream reconnected when the key expires or the
+/// connection is lost. Events may have been missed during the gap, the resync handler
+/// is called after every reconnection to reload the state through the REST API.
+/// Connections are rotated before Binance closes them, without a gap. Reads time out
+/// every second so that the keepalive is sent on time on a quiet connection.
+pub struct UserStreamManager<'a> {
+    manager: Manager<'a, WebSockets<'a>>,
+}
+
+impl<'a> UserStreamManager<'a> {
+    pub fn new<Callback>(
+        listen_keys: ListenKeys, config: UserStreamConfig, mut handler: Callback,
+    ) -> Self
+    where
+        Callback: FnMut(WebsocketEvent) -> Result<()> + 'a,
+    {
+        let expired = Rc::new(Cell::new(false));
+        let expired_by_event = expired.clone();
+        let web_socket = WebSockets::new(move |event: WebsocketEvent| {
+            if let WebsocketEvent::ListenKeyExpired(_) = event {
+                expired_by_event.set(true);
+            }
+            handler(event)
+        });
+        UserStreamManager {
+            manager: Manager::new(listen_keys, config, web_socket, expired),
+        }
+    }
+
+    pub fn set_resync_handler<F>(&mut self, resync: F)
+    where
+        F: FnMut() -> Result<()> + 'a,
+    {
+        self.manager.resync = Some(Box::new(resync));
+    }
+
+    pub fn listen_key(&self) -> Option<&str> {
+        self.manager.listen_key.as_deref()
+    }
+
+    /// Opens the stream if needed, then handles its events until 'running' is cleared.
+    pub fn event_loop(&mut self, running: &AtomicBool) -> Result<()> {
+        if !self.manager.listen_keys.market.is_spot() {
+            bail!(format!(
+                "{:?} user data events are received with FuturesUserStreamManager",
+                self.manager.listen_keys.market
+            ));
+        }
+        self.manager.event_loop(running)
+    }
+
+    /// Disconnects and closes the listen key.
+    pub fn close(&mut self) -> Result<()> {
+        self.manager.close()
+    }
+}
+
+/// USD-M, COIN-M and portfolio margin user data stream, see `UserStreamManager`.
+pub struct FuturesUserStreamManager<'a> {
+    manager: Manager<'a, FuturesWebSockets<'a>>,
+}
+
+impl<'a> FuturesUserStreamManager<'a> {
+    pub fn new<Callback>(
+        listen_keys: ListenKeys, config: UserStreamConfig, mut handler: Callback,
+    ) -> Self
+    where
+        Callback: FnMut(FuturesWebsocketEvent) -> Result<()> + 'a,
+    {
+        let expired = Rc::new(Cell::new(false));
+        let expired_by_event = expired.clone();
+        let web_socket = FuturesWebSockets::new(move |event: FuturesWebsocketEvent| {
+            if let FuturesWebsocketEvent::UserDataStreamExpiredEvent(_) = event {
+                expired_by_event.set(true);
+            }
+            handler(event)
+        });
+        FuturesUserStreamManager {
+            manager: Manager::new(listen_keys, config, web_socket, expired),
+        }
+    }
+
+    pub fn set_resync_handler<F>(&mut self, resync: F)
+    where
+        F: FnMut() -> Result<()> + 'a,
+    {
+        self.manager.resync = Some(Box::new(resync));
+    }
+
+    pub fn listen_key(&self) -> Option<&str> {
+        self.manager.listen_key.as_deref()
+    }
+
+    pub fn event_loop(&mut self, running: &AtomicBool) -> Result<()> {
+        if self.manager.listen_keys.market.is_spot() {
+            bail!(format!(
+                "{:?} user data events are received with UserStreamManager",
+                self.manager.listen_keys.market
+            ));
+        }
+        self.manager.event_loop(running)
+    }
+
+    pub fn close(&mut self) -> Result<()> {
+        self.manager.close()
+    }
+}
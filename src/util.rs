@@ -3,6 +3,7 @@ use std::collections::BTreeMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 use error_chain::bail;
 use serde_json::Value;
+use url::form_urlencoded::byte_serialize;
 
 pub fn build_request(parameters: BTreeMap<String, String>) -> String {
     let mut request = String::new();
@@ -14,6 +15,17 @@ pub fn build_request(parameters: BTreeMap<String, String>) -> String {
     request
 }
 
+// Encodes a list of values as a JSON array, escaped to be used as a query
+// parameter value, e.g. symbols=%5B%22BTCUSDT%22%2C%22BNBBTC%22%5D
+pub fn build_list_parameter<S>(values: &[S]) -> Result<String>
+where
+    S: AsRef<str>,
+{
+    let values: Vec<&str> = values.iter().map(AsRef::as_ref).collect();
+    let json = serde_json::to_string(&values)?;
+    Ok(byte_serialize(json.as_bytes()).collect())
+}
+
 pub fn build_signed_request(
     parameters: BTreeMap<String, String>, recv_window: u64,
 ) -> Result<String> {
@@ -23,12 +35,20 @@ pub fn build_signed_request(
 pub fn build_signed_request_custom(
     mut parameters: BTreeMap<String, String>, recv_window: u64, start: SystemTime,
 ) -> Result<String> {
+    add_timestamp(&mut parameters, recv_window, start)?;
+    Ok(build_request(parameters))
+}
+
+// Adds the parameters every signed request needs
+pub(crate) fn add_timestamp(
+    parameters: &mut BTreeMap<String, String>, recv_window: u64, start: SystemTime,
+) -> Result<()> {
     if recv_window > 0 {
         parameters.insert("recvWindow".into(), recv_window.to_string());
     }
     if let Ok(timestamp) = get_timestamp(start) {
         parameters.insert("timestamp".into(), timestamp.to_string());
-        return Ok(build_request(parameters));
+        return Ok(());
     }
     bail!("Failed to get timestamp")
 }
@@ -41,6 +61,11 @@ pub fn to_f64(v: &Value) -> f64 {
     v.as_str().unwrap().parse().unwrap()
 }
 
+// Local time in milliseconds
+pub(crate) fn current_millis() -> u64 {
+    get_timestamp(SystemTime::now()).unwrap_or(0)
+}
+
 fn get_timestamp(start: SystemTime) -> Result<u64> {
     let since_epoch = start.duration_since(UNIX_EPOCH)?;
     Ok(since_epoch.as_secs() * 1000 + u64::from(since_epoch.subsec_nanos()) / 1_000_000)
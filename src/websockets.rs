@@ -1,45 +1,61 @@
 use crate::errors::Result;
 use crate::config::Config;
+use crate::connection::{StreamEvent, WebSocketConnection};
+use crate::sbe;
+use crate::reconnect::ConnectionEvent;
+use crate::streams::{join_stream_names, StreamMarket, ToStreamName};
+use crate::decoder::{self, Kind};
+use crate::subscription::StreamResponse;
 use crate::model::{
-    AccountUpdateEvent, AggrTradesEvent, BalanceUpdateEvent, BookTickerEvent, DayTickerEvent,
-    WindowTickerEvent, DepthOrderBookEvent, KlineEvent, OrderBook, OrderTradeEvent, TradeEvent,
+    AccountPositionEvent, AggrTradesEvent, BalanceUpdateEvent, BookTickerEvent, DayTickerEvent,
+    WindowTickerEvent, DepthOrderBookEvent, EventStreamTerminatedEvent, ExternalLockUpdateEvent,
+    KlineEvent, ListStatusEvent, MiniTickerEvent, OrderBook, OrderTradeEvent, TradeEvent,
+    UserDataStreamExpiredEvent,
 };
-use error_chain::bail;
-use url::Url;
 use serde::{Deserialize, Serialize};
 
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::net::TcpStream;
-use tungstenite::{connect, Message};
-use tungstenite::protocol::WebSocket;
-use tungstenite::stream::MaybeTlsStream;
-use tungstenite::handshake::client::Response;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 #[allow(clippy::all)]
-enum WebsocketAPI {
+pub(crate) enum WebsocketAPI {
     Default,
     MultiStream,
     Custom(String),
+    CustomMultiStream(String),
 }
 
 impl WebsocketAPI {
-    fn params(self, subscription: &str) -> String {
+    pub(crate) fn params(self, subscription: &str) -> String {
         match self {
-            WebsocketAPI::Default => format!("wss://stream.binance.com/ws/{}", subscription),
+            WebsocketAPI::Default => {
+                WebsocketAPI::Custom(Config::default().ws_endpoint).params(subscription)
+            }
             WebsocketAPI::MultiStream => {
-                format!("wss://stream.binance.com/stream?streams={}", subscription)
+                WebsocketAPI::CustomMultiStream(Config::default().ws_endpoint).params(subscription)
             }
             WebsocketAPI::Custom(url) => format!("{}/{}", url, subscription),
+            WebsocketAPI::CustomMultiStream(url) => {
+                format!("{}?streams={}", combined_endpoint(&url), subscription)
+            }
         }
     }
 }
 
+// Combined streams of the host of a raw stream endpoint, "wss://<host>/ws" to "wss://<host>/stream"
+pub(crate) fn combined_endpoint(ws_endpoint: &str) -> String {
+    let base = ws_endpoint.trim_end_matches('/');
+    format!("{}/stream", base.strip_suffix("/ws").unwrap_or(base))
+}
+
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum WebsocketEvent {
-    AccountUpdate(AccountUpdateEvent),
+    AccountPosition(AccountPositionEvent),
     BalanceUpdate(BalanceUpdateEvent),
     OrderTrade(OrderTradeEvent),
+    ListStatus(ListStatusEvent),
+    ExternalLockUpdate(ExternalLockUpdateEvent),
+    EventStreamTerminated(EventStreamTerminatedEvent),
     AggrTrades(AggrTradesEvent),
     Trade(TradeEvent),
     OrderBook(OrderBook),
@@ -47,127 +63,212 @@ pub enum WebsocketEvent {
     DayTickerAll(Vec<DayTickerEvent>),
     WindowTicker(WindowTickerEvent),
     WindowTickerAll(Vec<WindowTickerEvent>),
+    MiniTicker(MiniTickerEvent),
+    MiniTickerAll(Vec<MiniTickerEvent>),
     Kline(KlineEvent),
     DepthOrderBook(DepthOrderBookEvent),
     BookTicker(BookTickerEvent),
+    ListenKeyExpired(UserDataStreamExpiredEvent),
+    Connection(ConnectionEvent),
+    Response(StreamResponse),
+    // Message which is not a known event, as received
+    Unknown(String),
 }
 
-pub struct WebSockets<'a> {
-    pub socket: Option<(WebSocket<MaybeTlsStream<TcpStream>>, Response)>,
-    handler: Box<dyn FnMut(WebsocketEvent) -> Result<()> + 'a>,
+impl WebsocketEvent {
+    /// Time in milliseconds the event was sent by Binance, None for events without it.
+    pub fn event_time(&self) -> Option<u64> {
+        match self {
+            WebsocketEvent::AccountPosition(event) => Some(event.event_time),
+            WebsocketEvent::BalanceUpdate(event) => Some(event.event_time),
+            WebsocketEvent::OrderTrade(event) => Some(event.event_time),
+            WebsocketEvent::ListStatus(event) => Some(event.event_time),
+            WebsocketEvent::ExternalLockUpdate(event) => Some(event.event_time),
+            WebsocketEvent::EventStreamTerminated(event) => Some(event.event_time),
+            WebsocketEvent::AggrTrades(event) => Some(event.event_time),
+            WebsocketEvent::Trade(event) => Some(event.event_time),
+            WebsocketEvent::DayTicker(event) => Some(event.event_time),
+            WebsocketEvent::DayTickerAll(events) => events.first().map(|event| event.event_time),
+            WebsocketEvent::WindowTicker(event) => Some(event.event_time),
+            WebsocketEvent::WindowTickerAll(events) => events.first().map(|event| event.event_time),
+            WebsocketEvent::MiniTicker(event) => Some(event.event_time),
+            WebsocketEvent::MiniTickerAll(events) => events.first().map(|event| event.event_time),
+            WebsocketEvent::Kline(event) => Some(event.event_time),
+            WebsocketEvent::DepthOrderBook(event) => Some(event.event_time),
+            WebsocketEvent::ListenKeyExpired(event) => Some(event.event_time),
+            WebsocketEvent::OrderBook(_)
+            | WebsocketEvent::BookTicker(_)
+            | WebsocketEvent::Connection(_)
+            | WebsocketEvent::Response(_)
+            | WebsocketEvent::Unknown(_) => None,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(untagged)]
-enum Events {
-    DayTickerEventAll(Vec<DayTickerEvent>),
-    WindowTickerEventAll(Vec<WindowTickerEvent>),
-    BalanceUpdateEvent(BalanceUpdateEvent),
-    DayTickerEvent(DayTickerEvent),
-    WindowTickerEvent(WindowTickerEvent),
-    BookTickerEvent(BookTickerEvent),
-    AccountUpdateEvent(AccountUpdateEvent),
-    OrderTradeEvent(OrderTradeEvent),
-    AggrTradesEvent(AggrTradesEvent),
-    TradeEvent(TradeEvent),
-    KlineEvent(KlineEvent),
-    OrderBook(OrderBook),
-    DepthOrderBookEvent(DepthOrderBookEvent),
+/// An event with where and when it was received.
+#[derive(Debug, Clone)]
+pub struct EventEnvelope<E> {
+    // Stream of combined stream connections, None for raw streams and connection events
+    pub stream: Option<String>,
+    // Local time in milliseconds
+    pub received_at: u64,
+    // Changes when the connection is reopened, 0 before the first connection
+    pub connection_id: u64,
+    pub event: E,
 }
 
-impl<'a> WebSockets<'a> {
-    pub fn new<Callback>(handler: Callback) -> WebSockets<'a>
-    where
-        Callback: FnMut(WebsocketEvent) -> Result<()> + 'a,
-    {
-        WebSockets {
-            socket: None,
-            handler: Box::new(handler),
-        }
-    }
+static CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+pub(crate) fn next_connection_id() -> u64 {
+    CONNECTION_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+impl StreamEvent for WebsocketEvent {
+    // Spot connections accept 5 messages per second
+    const MESSAGES_PER_SECOND: usize = 5;
 
-    pub fn connect(&mut self, subscription: &str) -> Result<()> {
-        self.connect_wss(&WebsocketAPI::Default.params(subscription))
+    fn parse(msg: &str) -> Result<(Option<String>, Self)> {
+        parse_stream_event(msg)
     }
 
-    pub fn connect_with_config(&mut self, subscription: &str, config: &Config) -> Result<()> {
-        self.connect_wss(&WebsocketAPI::Custom(config.ws_endpoint.clone()).params(subscription))
+    fn parse_binary(frame: &[u8]) -> Result<(Option<String>, Vec<Self>)> {
+        sbe::decode_stream(frame)
     }
 
-    pub fn connect_multiple_streams(&mut self, endpoints: &[String]) -> Result<()> {
-        self.connect_wss(&WebsocketAPI::MultiStream.params(&endpoints.join("/")))
+    fn from_connection(event: ConnectionEvent) -> Self {
+        WebsocketEvent::Connection(event)
     }
 
-    fn connect_wss(&mut self, wss: &str) -> Result<()> {
-        let url = Url::parse(wss)?;
-        match connect(url) {
-            Ok(answer) => {
-                self.socket = Some(answer);
-                Ok(())
-            }
-            Err(e) => bail!(format!("Error during handshake {}", e)),
+    fn response_mut(&mut self) -> Option<&mut StreamResponse> {
+        match self {
+            WebsocketEvent::Response(response) => Some(response),
+            _ => None,
         }
     }
 
-    pub fn disconnect(&mut self) -> Result<()> {
-        if let Some(ref mut socket) = self.socket {
-            socket.0.close(None)?;
-            return Ok(());
-        }
-        bail!("Not able to close the connection");
+    fn event_time(&self) -> Option<u64> {
+        WebsocketEvent::event_time(self)
     }
+}
+
+pub type WebSockets<'a> = WebSocketConnection<'a, WebsocketEvent>;
 
-    pub fn test_handle_msg(&mut self, msg: &str) -> Result<()> {
-        self.handle_msg(msg)
+impl<'a> WebSocketConnection<'a, WebsocketEvent> {
+    pub fn connect<S>(&mut self, subscription: &S) -> Result<()>
+    where
+        S: ToStreamName + ?Sized,
+    {
+        let subscription = subscription.to_stream_name(StreamMarket::Spot)?;
+        self.connect_json(&WebsocketAPI::Default.params(&subscription))
     }
 
-    pub fn handle_msg(&mut self, msg: &str) -> Result<()> {
-        let value: serde_json::Value = serde_json::from_str(msg)?;
+    pub fn connect_with_config<S>(&mut self, subscription: &S, config: &Config) -> Result<()>
+    where
+        S: ToStreamName + ?Sized,
+    {
+        let subscription = subscription.to_stream_name(StreamMarket::Spot)?;
+        self.connect_json(&WebsocketAPI::Custom(config.ws_endpoint.clone()).params(&subscription))
+    }
 
-        if let Some(data) = value.get("data") {
-            self.handle_msg(&data.to_string())?;
-            return Ok(());
-        }
+    pub fn connect_multiple_streams<S>(&mut self, endpoints: &[S]) -> Result<()>
+    where
+        S: ToStreamName,
+    {
+        let endpoints = join_stream_names(endpoints, StreamMarket::Spot)?;
+        self.connect_json(&WebsocketAPI::MultiStream.params(&endpoints))
+    }
 
-        if let Ok(events) = serde_json::from_value::<Events>(value) {
-            let action = match events {
-                Events::DayTickerEventAll(v) => WebsocketEvent::DayTickerAll(v),
-                Events::WindowTickerEventAll(v) => WebsocketEvent::WindowTickerAll(v),
-                Events::BookTickerEvent(v) => WebsocketEvent::BookTicker(v),
-                Events::BalanceUpdateEvent(v) => WebsocketEvent::BalanceUpdate(v),
-                Events::AccountUpdateEvent(v) => WebsocketEvent::AccountUpdate(v),
-                Events::OrderTradeEvent(v) => WebsocketEvent::OrderTrade(v),
-                Events::AggrTradesEvent(v) => WebsocketEvent::AggrTrades(v),
-                Events::TradeEvent(v) => WebsocketEvent::Trade(v),
-                Events::DayTickerEvent(v) => WebsocketEvent::DayTicker(v),
-                Events::WindowTickerEvent(v) => WebsocketEvent::WindowTicker(v),
-                Events::KlineEvent(v) => WebsocketEvent::Kline(v),
-                Events::OrderBook(v) => WebsocketEvent::OrderBook(v),
-                Events::DepthOrderBookEvent(v) => WebsocketEvent::DepthOrderBook(v),
-            };
-            (self.handler)(action)?;
-        }
-        Ok(())
+    pub fn connect_multiple_streams_with_config<S>(
+        &mut self, endpoints: &[S], config: &Config,
+    ) -> Result<()>
+    where
+        S: ToStreamName,
+    {
+        let endpoints = join_stream_names(endpoints, StreamMarket::Spot)?;
+        self.connect_json(
+            &WebsocketAPI::CustomMultiStream(config.ws_endpoint.clone()).params(&endpoints),
+        )
+    }
+
+    /// Connects to the Simple Binary Encoding streams (`<symbol>@trade`, `<symbol>@bestBidAsk`,
+    /// `<symbol>@depth`, `<symbol>@depth20`), which require an Ed25519 API key. Their events
+    /// are decoded into the events of the JSON streams.
+    pub fn connect_sbe<S>(&mut self, subscription: &S, api_key: &str, config: &Config) -> Result<()>
+    where
+        S: ToStreamName + ?Sized,
+    {
+        let subscription = subscription.to_stream_name(StreamMarket::Spot)?;
+        self.api_key = Some(api_key.to_string());
+        self.connect_wss(
+            &WebsocketAPI::Custom(config.sbe_ws_endpoint.clone()).params(&subscription),
+        )
     }
 
     pub fn event_loop(&mut self, running: &AtomicBool) -> Result<()> {
-        while running.load(Ordering::Relaxed) {
-            if let Some(ref mut socket) = self.socket {
-                let message = socket.0.read()?;
-                match message {
-                    Message::Text(msg) => {
-                        if let Err(e) = self.handle_msg(&msg) {
-                            bail!(format!("Error on handling stream message: {}", e));
-                        }
-                    }
-                    Message::Ping(payload) => {
-                        socket.0.write(Message::Pong(payload)).unwrap();
-                    }
-                    Message::Pong(_) | Message::Binary(_) | Message::Frame(_) => (),
-                    Message::Close(e) => bail!(format!("Disconnected {:?}", e)),
-                }
-            }
-        }
-        Ok(())
+        self.read_while_running(running)
     }
+
+    // The JSON streams are opened without the API key of a previous `connect_sbe`
+    fn connect_json(&mut self, url: &str) -> Result<()> {
+        self.api_key = None;
+        self.connect_wss(url)
+    }
+}
+
+// Decodes a stream message into the type given by its event type or stream name
+pub(crate) fn parse_event(msg: &str) -> Result<WebsocketEvent> {
+    parse_stream_event(msg).map(|(_, event)| event)
+}
+
+// Decodes a stream message, with the name of the stream it was received on. Events which
+// do not decode into the type of their event type are returned as `Unknown`, so that a
+// change of their fields doesn't stop the connection.
+pub(crate) fn parse_stream_event(msg: &str) -> Result<(Option<String>, WebsocketEvent)> {
+    let message = decoder::peek(msg)?;
+    let event = decode_event(message.kind, message.payload, msg)
+        .unwrap_or_else(|_| WebsocketEvent::Unknown(msg.to_string()));
+    Ok((message.stream.map(String::from), event))
+}
+
+fn decode_event(kind: Kind<'_>, payload: &str, msg: &str) -> serde_json::Result<WebsocketEvent> {
+    let event = match kind {
+        Kind::Event(event_type) => match event_type.as_ref() {
+            "24hrTicker" => WebsocketEvent::DayTicker(serde_json::from_str(payload)?),
+            "1hTicker" | "4hTicker" | "1dTicker" => {
+                WebsocketEvent::WindowTicker(serde_json::from_str(payload)?)
+            }
+            "24hrMiniTicker" => WebsocketEvent::MiniTicker(serde_json::from_str(payload)?),
+            "executionReport" => WebsocketEvent::OrderTrade(serde_json::from_str(payload)?),
+            "outboundAccountPosition" => {
+                WebsocketEvent::AccountPosition(serde_json::from_str(payload)?)
+            }
+            "balanceUpdate" => WebsocketEvent::BalanceUpdate(serde_json::from_str(payload)?),
+            "listStatus" => WebsocketEvent::ListStatus(serde_json::from_str(payload)?),
+            "externalLockUpdate" => {
+                WebsocketEvent::ExternalLockUpdate(serde_json::from_str(payload)?)
+            }
+            "eventStreamTerminated" => {
+                WebsocketEvent::EventStreamTerminated(serde_json::from_str(payload)?)
+            }
+            "aggTrade" => WebsocketEvent::AggrTrades(serde_json::from_str(payload)?),
+            "trade" => WebsocketEvent::Trade(serde_json::from_str(payload)?),
+            "kline" => WebsocketEvent::Kline(serde_json::from_str(payload)?),
+            "depthUpdate" => WebsocketEvent::DepthOrderBook(serde_json::from_str(payload)?),
+            "listenKeyExpired" => WebsocketEvent::ListenKeyExpired(serde_json::from_str(payload)?),
+            _ => WebsocketEvent::Unknown(msg.to_string()),
+        },
+        Kind::Events(event_type) => match event_type.as_ref() {
+            "24hrTicker" => WebsocketEvent::DayTickerAll(serde_json::from_str(payload)?),
+            "1hTicker" | "4hTicker" | "1dTicker" => {
+                WebsocketEvent::WindowTickerAll(serde_json::from_str(payload)?)
+            }
+            "24hrMiniTicker" => WebsocketEvent::MiniTickerAll(serde_json::from_str(payload)?),
+            _ => WebsocketEvent::Unknown(msg.to_string()),
+        },
+        Kind::Response => WebsocketEvent::Response(serde_json::from_str(payload)?),
+        Kind::OrderBook => WebsocketEvent::OrderBook(serde_json::from_str(payload)?),
+        Kind::BookTicker => WebsocketEvent::BookTicker(serde_json::from_str(payload)?),
+        Kind::Unknown => WebsocketEvent::Unknown(msg.to_string()),
+    };
+    Ok(event)
 }
@@ -0,0 +1,466 @@
+use crate::account::{Account, OrderRequest, OrderSide, OrderType, TimeInForce};
+use crate::client::Client;
+use crate::errors::{BinanceContentError, ErrorKind, Result};
+use crate::heartbeat::{is_timeout, tcp_stream};
+use crate::model::{
+    AccountInformation, CancelReplaceResult, Empty, Order, OrderCanceled, RateLimit, SessionStatus,
+    Transaction,
+};
+use crate::util::{add_timestamp, build_request};
+use crate::websockets::{self, WebsocketEvent};
+use error_chain::bail;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+use tungstenite::handshake::client::Response;
+use tungstenite::protocol::WebSocket;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{connect, Message};
+use url::Url;
+
+// Parameters sent as numbers, all others are sent as strings
+const INTEGER_PARAMETERS: [&str; 4] = ["cancelOrderId", "orderId", "recvWindow", "timestamp"];
+
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+// Responses kept for `wait`, the oldest are dropped beyond this
+const MAX_RESPONSES: usize = 1000;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WsApiRequest {
+    pub id: u64,
+    pub method: String,
+    pub params: Map<String, Value>,
+}
+
+/// Answer to a WebSocket API request, with the rate limits usage after it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WsApiResponse {
+    // None when the request could not be parsed
+    pub id: Option<u64>,
+    pub status: u16,
+    #[serde(default)]
+    pub result: Option<Value>,
+    #[serde(default)]
+    pub error: Option<BinanceContentError>,
+    #[serde(default)]
+    pub rate_limits: Vec<RateLimit>,
+}
+
+impl WsApiResponse {
+    pub fn into_result<T: DeserializeOwned>(self) -> Result<T> {
+        if let Some(error) = self.error {
+            return Err(ErrorKind::BinanceError(error).into());
+        }
+        match self.result {
+            Some(result) => Ok(serde_json::from_value(result)?),
+            None => bail!(format!("Response to request {:?} has no result", self.id)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Peek<'a> {
+    // User data events are pushed as {"subscriptionId":0,"event":{...}}
+    #[serde(borrow, default)]
+    event: Option<&'a RawValue>,
+}
+
+type Callback<'a> = Box<dyn FnOnce(WsApiResponse) -> Result<()> + 'a>;
+type EventHandler<'a> = Box<dyn FnMut(&str) -> Result<()> + 'a>;
+
+/// Connection to the WebSocket API, matching responses with their requests.
+///
+/// Requests can be sent with a callback, called by `read_message` or `event_loop` when
+/// the response is received, or waited for with `wait`.
+pub struct WsApiConnection<'a> {
+    pub socket: Option<(WebSocket<MaybeTlsStream<TcpStream>>, Response)>,
+    endpoint: String,
+    next_id: u64,
+    callbacks: HashMap<u64, Callback<'a>>,
+    // Responses without callback which were not waited for yet, by request id
+    responses: BTreeMap<u64, WsApiResponse>,
+    response_timeout: Duration,
+    rate_limits: Vec<RateLimit>,
+    event_handler: Option<EventHandler<'a>>,
+}
+
+impl<'a> WsApiConnection<'a> {
+    pub(crate) fn new(endpoint: String) -> Self {
+        WsApiConnection {
+            socket: None,
+            endpoint,
+            next_id: 1,
+            callbacks: HashMap::new(),
+            responses: BTreeMap::new(),
+            response_timeout: RESPONSE_TIMEOUT,
+            rate_limits: Vec::new(),
+            event_handler: None,
+        }
+    }
+
+    pub fn connect(&mut self) -> Result<()> {
+        let url = Url::parse(&self.endpoint)?;
+        match connect(url) {
+            Ok(answer) => {
+                self.socket = Some(answer);
+                self.callbacks.clear();
+                self.responses.clear();
+                Ok(())
+            }
+            Err(e) => bail!(format!("Error during handshake {}", e)),
+        }
+    }
+
+    pub fn disconnect(&mut self) -> Result<()> {
+        if let Some(ref mut socket) = self.socket {
+            socket.0.close(None)?;
+            return Ok(());
+        }
+        bail!("Not able to close the connection");
+    }
+
+    /// How long `wait` and `request` wait for a response, 10 seconds by default.
+    pub fn set_response_timeout(&mut self, response_timeout: Duration) {
+        self.response_timeout = response_timeout;
+    }
+
+    /// Rate limits usage returned with the last response.
+    pub fn rate_limits(&self) -> &[RateLimit] {
+        &self.rate_limits
+    }
+
+    // Sends a request, returns its id
+    pub fn send(&mut self, method: &str, params: Map<String, Value>) -> Result<u64> {
+        let request = WsApiRequest {
+            id: self.next_id,
+            method: method.into(),
+            params,
+        };
+        match self.socket {
+            Some(ref mut socket) => {
+                socket
+                    .0
+                    .send(Message::Text(serde_json::to_string(&request)?))?;
+            }
+            None => bail!("Not connected"),
+        }
+        self.next_id += 1;
+        Ok(request.id)
+    }
+
+    pub fn send_with_callback<F>(
+        &mut self, method: &str, params: Map<String, Value>, callback: F,
+    ) -> Result<u64>
+    where
+        F: FnOnce(WsApiResponse) -> Result<()> + 'a,
+    {
+        let id = self.send(method, params)?;
+        self.callbacks.insert(id, Box::new(callback));
+        Ok(id)
+    }
+
+    // Reads until the response to the given request is received, fails after the
+    // response timeout. Responses to requests sent with a callback go to the callback.
+    pub fn wait(&mut self, id: u64) -> Result<WsApiResponse> {
+        if self.callbacks.contains_key(&id) {
+            bail!(format!(
+                "Response to request {} is passed to its callback",
+                id
+            ));
+        }
+        if id == 0 || id >= self.next_id {
+            bail!(format!("Request {} was not sent", id));
+        }
+        let deadline = Instant::now() + self.response_timeout;
+        loop {
+            if let Some(response) = self.responses.remove(&id) {
+                return Ok(response);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                bail!(format!(
+                    "No response to request {} within {:?}",
+                    id, self.response_timeout
+                ));
+            }
+            let read = match self.socket {
+                Some(ref mut socket) => {
+                    if let Some(stream) = tcp_stream(socket.0.get_ref()) {
+                        stream.set_read_timeout(Some(deadline - now))?;
+                    }
+                    let read = socket.0.read();
+                    if let Some(stream) = tcp_stream(socket.0.get_ref()) {
+                        stream.set_read_timeout(None)?;
+                    }
+                    read
+                }
+                None => bail!("Not connected"),
+            };
+            match read {
+                Ok(message) => self.handle_message(message)?,
+                Err(ref e) if is_timeout(e) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    pub fn request(&mut self, method: &str, params: Map<String, Value>) -> Result<WsApiResponse> {
+        let id = self.send(method, params)?;
+        self.wait(id)
+    }
+
+    pub fn event_loop(&mut self, running: &AtomicBool) -> Result<()> {
+        while running.load(Ordering::Relaxed) {
+            self.read_message()?;
+        }
+        Ok(())
+    }
+
+    // Reads one message, passing responses to their callbacks and events to the handler
+    pub fn read_message(&mut self) -> Result<()> {
+        let message = match self.socket {
+            Some(ref mut socket) => socket.0.read()?,
+            None => bail!("Not connected"),
+        };
+        self.handle_message(message)
+    }
+
+    fn handle_message(&mut self, message: Message) -> Result<()> {
+        match message {
+            Message::Text(msg) => self.handle_msg(&msg),
+            Message::Ping(payload) => {
+                if let Some(ref mut socket) = self.socket {
+                    socket.0.write(Message::Pong(payload))?;
+                }
+                Ok(())
+            }
+            Message::Pong(_) | Message::Binary(_) | Message::Frame(_) => Ok(()),
+            Message::Close(e) => bail!(format!("Disconnected {:?}", e)),
+        }
+    }
+
+    fn handle_msg(&mut self, msg: &str) -> Result<()> {
+        let peek: Peek<'_> = serde_json::from_str(msg)?;
+        if let Some(event) = peek.event {
+            return match self.event_handler {
+                Some(ref mut handler) => handler(event.get()),
+                None => Ok(()),
+            };
+        }
+
+        let response: WsApiResponse = serde_json::from_str(msg)?;
+        if !response.rate_limits.is_empty() {
+            self.rate_limits = response.rate_limits.clone();
+        }
+        let id = match response.id {
+            Some(id) => id,
+            None => return response.into_result::<Value>().map(|_| ()),
+        };
+        if let Some(callback) = self.callbacks.remove(&id) {
+            return callback(response);
+        }
+        self.responses.insert(id, response);
+        if self.responses.len() > MAX_RESPONSES {
+            let oldest = self.responses.keys().next().copied();
+            if let Some(oldest) = oldest {
+                self.responses.remove(&oldest);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Parameters of a request authenticated with the api key and a signature,
+// or only with a timestamp on connections which are logged on
+pub(crate) fn signed_params(
+    client: &Client, recv_window: u64, mut parameters: BTreeMap<String, String>, logged_on: bool,
+) -> Result<Map<String, Value>> {
+    add_timestamp(&mut parameters, recv_window, SystemTime::now())?;
+    if !logged_on {
+        parameters.insert("apiKey".into(), client.api_key().into());
+        let signature = client.sign(&build_request(parameters.clone()));
+        parameters.insert("signature".into(), signature);
+    }
+    Ok(to_params(parameters))
+}
+
+// Parameters of session.logon, signed by 'sign' with an Ed25519 key
+pub(crate) fn logon_params<F>(
+    client: &Client, recv_window: u64, sign: F,
+) -> Result<Map<String, Value>>
+where
+    F: FnOnce(&str) -> String,
+{
+    let mut parameters = BTreeMap::new();
+    parameters.insert("apiKey".into(), client.api_key().to_string());
+    add_timestamp(&mut parameters, recv_window, SystemTime::now())?;
+    let signature = sign(&build_request(parameters.clone()));
+    parameters.insert("signature".into(), signature);
+    Ok(to_params(parameters))
+}
+
+fn to_params(parameters: BTreeMap<String, String>) -> Map<String, Value> {
+    parameters
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match value.parse::<u64>() {
+                Ok(number) if INTEGER_PARAMETERS.contains(&key.as_str()) => Value::from(number),
+                _ => Value::String(value),
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+/// Spot trading through the WebSocket API.
+pub struct WsApi<'a> {
+    pub(crate) account: Account,
+    pub connection: WsApiConnection<'a>,
+    logged_on: bool,
+}
+
+impl<'a> WsApi<'a> {
+    pub(crate) fn new_with_account(account: Account, endpoint: String) -> Self {
+        WsApi {
+            account,
+            connection: WsApiConnection::new(endpoint),
+            logged_on: false,
+        }
+    }
+
+    pub fn connect(&mut self) -> Result<()> {
+        self.logged_on = false;
+        self.connection.connect()
+    }
+
+    pub fn disconnect(&mut self) -> Result<()> {
+        self.connection.disconnect()
+    }
+
+    /// Handler of the user data events received after `user_data_stream_subscribe`.
+    pub fn set_event_handler<Callback>(&mut self, mut handler: Callback)
+    where
+        Callback: FnMut(WebsocketEvent) -> Result<()> + 'a,
+    {
+        self.connection.event_handler = Some(Box::new(move |event: &str| {
+            handler(websockets::parse_event(event)?)
+        }));
+    }
+
+    /// Place an order, see `Account::custom_order`
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_order<S, F>(
+        &mut self, symbol: S, qty: F, price: f64, stop_price: Option<f64>, order_side: OrderSide,
+        order_type: OrderType, time_in_force: TimeInForce, new_client_order_id: Option<String>,
+    ) -> Result<Transaction>
+    where
+        S: Into<String>,
+        F: Into<f64>,
+    {
+        let order = self.account.build_order(OrderRequest {
+            symbol: symbol.into(),
+            qty: qty.into(),
+            price,
+            stop_price,
+            order_side,
+            order_type,
+            time_in_force,
+            new_client_order_id,
+        });
+        self.signed_request("order.place", order)
+    }
+
+    pub fn cancel_order<S>(&mut self, symbol: S, order_id: u64) -> Result<OrderCanceled>
+    where
+        S: Into<String>,
+    {
+        let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+        parameters.insert("symbol".into(), symbol.into());
+        parameters.insert("orderId".into(), order_id.to_string());
+        self.signed_request("order.cancel", parameters)
+    }
+
+    /// Cancel an order and place a new one, the new order is not placed if the
+    /// cancellation fails
+    #[allow(clippy::too_many_arguments)]
+    pub fn cancel_replace_order<S, F>(
+        &mut self, cancel_order_id: u64, symbol: S, qty: F, price: f64, stop_price: Option<f64>,
+        order_side: OrderSide, order_type: OrderType, time_in_force: TimeInForce,
+        new_client_order_id: Option<String>,
+    ) -> Result<CancelReplaceResult>
+    where
+        S: Into<String>,
+        F: Into<f64>,
+    {
+        let mut parameters = self.account.build_order(OrderRequest {
+            symbol: symbol.into(),
+            qty: qty.into(),
+            price,
+            stop_price,
+            order_side,
+            order_type,
+            time_in_force,
+            new_client_order_id,
+        });
+        parameters.insert("cancelOrderId".into(), cancel_order_id.to_string());
+        parameters.insert("cancelReplaceMode".into(), "STOP_ON_FAILURE".into());
+        self.signed_request("order.cancelReplace", parameters)
+    }
+
+    pub fn order_status<S>(&mut self, symbol: S, order_id: u64) -> Result<Order>
+    where
+        S: Into<String>,
+    {
+        let mut parameters: BTreeMap<String, String> = BTreeMap::new();
+        parameters.insert("symbol".into(), symbol.into());
+        parameters.insert("orderId".into(), order_id.to_string());
+        self.signed_request("order.status", parameters)
+    }
+
+    pub fn account_status(&mut self) -> Result<AccountInformation> {
+        self.signed_request("account.status", BTreeMap::new())
+    }
+
+    /// Authenticate the connection, later requests are not signed.
+    ///
+    /// Binance only accepts Ed25519 keys for this, 'sign' returns the base64 encoded
+    /// Ed25519 signature of the given payload.
+    pub fn session_logon<F>(&mut self, sign: F) -> Result<SessionStatus>
+    where
+        F: FnOnce(&str) -> String,
+    {
+        let params = logon_params(&self.account.client, self.account.recv_window, sign)?;
+        let status = self
+            .connection
+            .request("session.logon", params)?
+            .into_result()?;
+        self.logged_on = true;
+        Ok(status)
+    }
+
+    /// Receive the user data events on this connection, the connection must be logged on.
+    pub fn user_data_stream_subscribe(&mut self) -> Result<()> {
+        self.connection
+            .request("userDataStream.subscribe", Map::new())?
+            .into_result::<Empty>()
+            .map(|_| ())
+    }
+
+    fn signed_request<T: DeserializeOwned>(
+        &mut self, method: &str, parameters: BTreeMap<String, String>,
+    ) -> Result<T> {
+        let params = signed_params(
+            &self.account.client,
+            self.account.recv_window,
+            parameters,
+            self.logged_on,
+        )?;
+        self.connection.request(method, params)?.into_result()
+    }
+}
@@ -0,0 +1,109 @@
+use binance::archive::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use float_cmp::*;
+    use std::fs;
+
+    #[test]
+    fn read_spot_klines() {
+        let klines = read_klines("tests/mocks/archive/BTCUSDT-1m-2025-01-01.zip").unwrap();
+
+        assert_eq!(klines.len(), 2);
+        let kline = &klines[0];
+        assert_eq!(kline.open_time, 1735689600000);
+        assert_eq!(kline.close_time, 1735689659999);
+        assert_eq!(kline.open, "93576.00000000");
+        assert_eq!(kline.close, "93610.93000000");
+        assert_eq!(kline.number_of_trades, 1736);
+        assert_eq!(kline.taker_buy_quote_asset_volume, "335341.91223640");
+    }
+
+    #[test]
+    fn read_futures_agg_trades() {
+        let trades =
+            read_agg_trades("tests/mocks/archive/BTCUSDT-aggTrades-2024-01-01.csv").unwrap();
+
+        assert_eq!(trades.len(), 2);
+        let trade = &trades[0];
+        assert_eq!(trade.agg_id, 1919536766);
+        assert_eq!(trade.first_id, 4424813393);
+        assert_eq!(trade.last_id, 4424813394);
+        assert_eq!(trade.time, 1704067200042);
+        assert!(approx_eq!(f64, trade.price, 42314.0, ulps = 2));
+        assert!(approx_eq!(f64, trade.qty, 0.03, ulps = 2));
+        assert!(trade.maker);
+        assert!(trade.best_match);
+        assert!(!trades[1].maker);
+    }
+
+    #[test]
+    fn read_spot_trades() {
+        let trades = read_trades("tests/mocks/archive/BTCUSDT-trades-2025-01-01.csv").unwrap();
+
+        assert_eq!(trades.len(), 2);
+        let trade = &trades[1];
+        assert_eq!(trade.id, 4359220453);
+        assert_eq!(trade.time, 1735689600101);
+        assert!(approx_eq!(f64, trade.price, 93576.01, ulps = 2));
+        assert!(approx_eq!(f64, trade.quote_qty, 9.357601, ulps = 2));
+        assert!(!trade.is_buyer_maker);
+    }
+
+    #[test]
+    fn read_coin_futures_trades() {
+        let path = "tests/mocks/archive/BTCUSD_PERP-trades-2025-01-01.csv";
+        let trades = read_coin_trades(path).unwrap();
+
+        assert_eq!(trades.len(), 2);
+        let trade = &trades[0];
+        assert_eq!(trade.id, 1004560123);
+        assert!(approx_eq!(f64, trade.qty, 2.0, ulps = 2));
+        assert!(approx_eq!(f64, trade.base_qty, 0.00213789, ulps = 2));
+        assert!(trade.is_buyer_maker);
+
+        assert!(read_trades(path).is_err());
+        assert!(read_coin_trades("tests/mocks/archive/BTCUSDT-trades-2025-01-01.csv").is_err());
+    }
+
+    #[test]
+    fn short_header() {
+        let path = std::env::temp_dir().join("binance_archive_short_header.csv");
+        fs::write(&path, "id,price\n1,93550.1,2,187.1002,1735689600123,true\n").unwrap();
+
+        let trades = read_trades(&path).unwrap();
+        assert!(read_coin_trades(&path).is_err());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(trades.len(), 1);
+    }
+
+    #[test]
+    fn iter_klines_rows() {
+        let mut klines = iter_klines("tests/mocks/archive/BTCUSDT-1m-2025-01-01.zip").unwrap();
+
+        assert_eq!(klines.next().unwrap().unwrap().open_time, 1735689600000);
+        assert_eq!(klines.next().unwrap().unwrap().open_time, 1735689660000);
+        assert!(klines.next().is_none());
+    }
+
+    #[test]
+    fn checksum() {
+        assert!(verify_checksum("tests/mocks/archive/BTCUSDT-1m-2025-01-01.zip").is_ok());
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let dir = std::env::temp_dir().join("binance_archive_checksum_mismatch");
+        fs::create_dir_all(&dir).unwrap();
+        let archive = dir.join("BTCUSDT-1m-2025-01-01.zip");
+        fs::copy("tests/mocks/archive/BTCUSDT-1m-2025-01-01.zip", &archive).unwrap();
+        fs::write(
+            dir.join("BTCUSDT-1m-2025-01-01.zip.CHECKSUM"),
+            "0000000000000000000000000000000000000000000000000000000000000000  BTCUSDT-1m-2025-01-01.zip\n",
+        )
+        .unwrap();
+
+        assert!(verify_checksum(&archive).is_err());
+    }
+}
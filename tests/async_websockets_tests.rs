@@ -0,0 +1,100 @@
+#![cfg(feature = "async")]
+
+use binance::async_websockets::*;
+use binance::config::*;
+use binance::futures::websockets::*;
+use binance::websockets::*;
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use std::sync::{Arc, Mutex};
+    use tokio_tungstenite::tungstenite::Message;
+
+    const TRADE: &str = r#"{"e":"trade","E":1672515782136,"s":"BNBBTC","t":12345,"p":"0.001","q":"100","b":88,"a":50,"T":1672515782136,"m":true,"M":true}"#;
+
+    // Serves the given frames to the first client, then closes the connection
+    fn serve(frames: Vec<Message>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let session: common::Session = Box::new(move |socket| {
+            for frame in frames {
+                socket.send(frame).unwrap();
+            }
+            common::close(socket);
+        });
+        common::serve("/ws", vec![session])
+    }
+
+    #[tokio::test]
+    async fn stream_events() {
+        let (endpoint, _) = serve(vec![
+            Message::Ping(vec![1]),
+            Message::Text(r#"{"result":null,"id":1}"#.into()),
+            Message::Text(TRADE.into()),
+            Message::Text(format!(r#"{{"stream":"bnbbtc@trade","data":{}}}"#, TRADE)),
+        ]);
+
+        let config = Config::default().set_ws_endpoint(endpoint);
+        let mut stream = WebsocketStream::connect_with_config("bnbbtc@trade", &config)
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            match stream.next().await {
+                Some(Ok(WebsocketEvent::Trade(trade))) => {
+                    assert_eq!(trade.symbol, "BNBBTC");
+                    assert_eq!(trade.trade_id, 12345);
+                }
+                other => panic!("Unexpected {:?}", other),
+            }
+        }
+
+        // The close frame is reported once, then the stream ends
+        assert!(matches!(stream.next().await, Some(Err(_))));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn stream_in_select() {
+        let (endpoint, _) = serve(vec![Message::Text(TRADE.into())]);
+
+        let config = Config::default().set_ws_endpoint(endpoint);
+        let mut stream = WebsocketStream::connect_with_config("bnbbtc@trade", &config)
+            .await
+            .unwrap();
+
+        let received = tokio::select! {
+            event = stream.next() => matches!(event, Some(Ok(WebsocketEvent::Trade(_)))),
+            _ = tokio::time::sleep(std::time::Duration::from_secs(5)) => false,
+        };
+        assert!(received);
+
+        // Cancelled by drop
+        drop(stream);
+    }
+
+    #[tokio::test]
+    async fn futures_combined_stream_of_config() {
+        let frame = format!(r#"{{"stream":"bnbbtc@trade","data":{}}}"#, TRADE);
+        let (endpoint, uris) = serve(vec![Message::Text(frame)]);
+
+        let config = Config::default().set_futures_ws_endpoint(endpoint);
+        let mut stream = FuturesWebsocketStream::connect_multiple_streams_with_config(
+            &FuturesMarket::USDM,
+            &["bnbbtc@trade", "ethbtc@trade"],
+            &config,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            stream.next().await,
+            Some(Ok(FuturesWebsocketEvent::Trade(_)))
+        ));
+        assert_eq!(
+            uris.lock().unwrap()[0],
+            "/stream?streams=bnbbtc@trade/ethbtc@trade"
+        );
+    }
+}
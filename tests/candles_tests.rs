@@ -0,0 +1,236 @@
+use binance::candles::*;
+use binance::model::{AggrTradesEvent, KlineSummary, TradeEvent};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn trade(price: &str, qty: &str, time: u64, is_buyer_maker: bool) -> TradeEvent {
+        TradeEvent {
+            event_type: "trade".into(),
+            event_time: time,
+            symbol: "BTCUSDT".into(),
+            trade_id: time,
+            price: price.into(),
+            qty: qty.into(),
+            buyer_order_id: 1,
+            seller_order_id: 2,
+            trade_order_time: time,
+            is_buyer_maker,
+            m_ignore: false,
+        }
+    }
+
+    #[test]
+    fn bar_type_from_interval() {
+        assert_eq!(
+            BarType::from_interval("10s").unwrap(),
+            BarType::Time(10_000)
+        );
+        assert_eq!(
+            BarType::from_interval("2m").unwrap(),
+            BarType::Time(120_000)
+        );
+        assert_eq!(
+            BarType::from_interval("1d").unwrap(),
+            BarType::Time(86_400_000)
+        );
+        assert!(BarType::from_interval("0m").is_err());
+        assert!(BarType::from_interval("5x").is_err());
+        assert!(BarType::from_interval("99999999999999999d").is_err());
+    }
+
+    #[test]
+    fn time_bars() {
+        let closed = RefCell::new(Vec::new());
+        let mut aggregator = CandleAggregator::new(BarType::Time(10_000), |symbol, bar| {
+            closed.borrow_mut().push((symbol.to_string(), bar));
+            Ok(())
+        })
+        .unwrap();
+
+        aggregator
+            .handle_trade(&trade("100", "1", 1_000, false))
+            .unwrap();
+        aggregator
+            .handle_trade(&trade("105", "2", 4_000, true))
+            .unwrap();
+        aggregator
+            .handle_trade(&trade("95", "1", 9_999, false))
+            .unwrap();
+        aggregator
+            .handle_trade(&trade("101", "1", 12_000, false))
+            .unwrap();
+
+        let current = aggregator.current("BTCUSDT").unwrap();
+        assert_eq!(current.open_time, 10_000);
+        assert_eq!(current.close_time, 19_999);
+        drop(aggregator);
+
+        let closed = closed.into_inner();
+        assert_eq!(closed.len(), 1);
+        let (symbol, bar) = &closed[0];
+        assert_eq!(symbol, "BTCUSDT");
+        assert_eq!(bar.open_time, 0);
+        assert_eq!(bar.close_time, 9_999);
+        assert_eq!(bar.open, "100");
+        assert_eq!(bar.high, "105");
+        assert_eq!(bar.low, "95");
+        assert_eq!(bar.close, "95");
+        assert_eq!(bar.volume, "4");
+        assert_eq!(bar.quote_asset_volume, "405");
+        assert_eq!(bar.number_of_trades, 3);
+        assert_eq!(bar.taker_buy_base_asset_volume, "2");
+    }
+
+    #[test]
+    fn late_trades() {
+        let closed = RefCell::new(Vec::new());
+        let mut aggregator = CandleAggregator::new(BarType::Time(10_000), |_, bar| {
+            closed.borrow_mut().push(bar);
+            Ok(())
+        })
+        .unwrap();
+        aggregator.set_allowed_lateness(2_000);
+
+        aggregator
+            .handle_trade(&trade("100", "1", 9_000, false))
+            .unwrap();
+        aggregator
+            .handle_trade(&trade("101", "1", 10_500, false))
+            .unwrap();
+        // Still within the allowed lateness
+        aggregator
+            .handle_trade(&trade("99", "1", 9_500, false))
+            .unwrap();
+        aggregator
+            .handle_trade(&trade("102", "1", 12_500, false))
+            .unwrap();
+        // The first bar is closed now
+        aggregator
+            .handle_trade(&trade("98", "1", 9_800, false))
+            .unwrap();
+        assert_eq!(aggregator.late_trades(), 1);
+
+        aggregator.close_expired(30_000).unwrap();
+        drop(aggregator);
+
+        let closed = closed.into_inner();
+        assert_eq!(closed.len(), 2);
+        assert_eq!(closed[0].number_of_trades, 2);
+        assert_eq!(closed[0].close, "99");
+        assert_eq!(closed[1].open_time, 10_000);
+        assert_eq!(closed[1].number_of_trades, 2);
+    }
+
+    #[test]
+    fn volume_bars_from_aggr_trades() {
+        let closed = RefCell::new(Vec::new());
+        let mut aggregator = CandleAggregator::new(BarType::Volume(3.0), |_, bar| {
+            closed.borrow_mut().push(bar);
+            Ok(())
+        })
+        .unwrap();
+
+        for (i, qty) in ["1", "1.5", "1", "2"].iter().enumerate() {
+            let event = AggrTradesEvent {
+                event_type: "aggTrade".into(),
+                event_time: 1_000 + i as u64,
+                symbol: "BTCUSDT".into(),
+                aggregated_trade_id: i as u64,
+                price: "10".into(),
+                qty: qty.to_string(),
+                first_break_trade_id: 10 * i as u64,
+                last_break_trade_id: 10 * i as u64 + 1,
+                trade_order_time: 1_000 + i as u64,
+                is_buyer_maker: false,
+                m_ignore: false,
+            };
+            aggregator.handle_aggr_trade(&event).unwrap();
+        }
+        drop(aggregator);
+
+        let closed = closed.into_inner();
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].volume, "3.5");
+        assert_eq!(closed[0].number_of_trades, 6);
+        assert_eq!(closed[0].open_time, 1_000);
+        assert_eq!(closed[0].close_time, 1_002);
+    }
+
+    #[test]
+    fn seed_from_klines() {
+        let closed = RefCell::new(Vec::new());
+        let mut aggregator = CandleAggregator::new(BarType::Time(60_000), |_, bar| {
+            closed.borrow_mut().push(bar);
+            Ok(())
+        })
+        .unwrap();
+
+        let kline = KlineSummary {
+            open_time: 60_000,
+            open: "100".into(),
+            high: "110".into(),
+            low: "90".into(),
+            close: "105".into(),
+            volume: "10".into(),
+            close_time: 119_999,
+            quote_asset_volume: "1000".into(),
+            number_of_trades: 5,
+            taker_buy_base_asset_volume: "4".into(),
+            taker_buy_quote_asset_volume: "400".into(),
+        };
+        aggregator.seed("BTCUSDT", &[kline]).unwrap();
+
+        aggregator
+            .handle_trade(&trade("120", "1", 100_000, true))
+            .unwrap();
+        aggregator
+            .handle_trade(&trade("106", "1", 120_000, true))
+            .unwrap();
+        drop(aggregator);
+
+        let closed = closed.into_inner();
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].open, "100");
+        assert_eq!(closed[0].high, "120");
+        assert_eq!(closed[0].close, "120");
+        assert_eq!(closed[0].volume, "11");
+        assert_eq!(closed[0].number_of_trades, 6);
+    }
+
+    #[test]
+    fn invalid_time_bars() {
+        assert!(CandleAggregator::new(BarType::Time(0), |_, _| Ok(())).is_err());
+        assert!(CandleAggregator::new(BarType::Time(u64::MAX), |_, _| Ok(())).is_err());
+    }
+
+    #[test]
+    fn closed_bars_kept_when_handler_fails() {
+        let closed = RefCell::new(Vec::new());
+        let fail = RefCell::new(true);
+        let mut aggregator = CandleAggregator::new(BarType::Time(10_000), |_, bar| {
+            if *fail.borrow() {
+                return Err("handler failed".into());
+            }
+            closed.borrow_mut().push(bar.open_time);
+            Ok(())
+        })
+        .unwrap();
+
+        aggregator
+            .handle_trade(&trade("100", "1", 1_000, false))
+            .unwrap();
+        // Closes the first bar
+        assert!(aggregator
+            .handle_trade(&trade("101", "1", 11_000, false))
+            .is_err());
+        assert!(aggregator.close_expired(30_000).is_err());
+
+        *fail.borrow_mut() = false;
+        aggregator.close_expired(30_000).unwrap();
+        drop(aggregator);
+        assert_eq!(closed.into_inner(), vec![0, 10_000]);
+    }
+}
@@ -0,0 +1,73 @@
+// Websocket servers shared by the test targets, each of them uses a part
+#![allow(dead_code)]
+
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::handshake::server::{Request, Response};
+use tungstenite::WebSocket;
+
+pub type Session = Box<dyn FnOnce(&mut WebSocket<TcpStream>) + Send>;
+
+// Runs one session per connection on "ws://<address><path>", returns the endpoint and the
+// requested uris. A connection is dropped when its session returns.
+pub fn serve(path: &str, sessions: Vec<Session>) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("ws://{}{}", listener.local_addr().unwrap(), path);
+    let uris = Arc::new(Mutex::new(Vec::new()));
+    let server_uris = uris.clone();
+
+    thread::spawn(move || {
+        for session in sessions {
+            let (stream, _) = listener.accept().unwrap();
+            let uris = server_uris.clone();
+            // Sessions can overlap when a connection is rotated
+            thread::spawn(move || {
+                let mut socket =
+                    tungstenite::accept_hdr(stream, |request: &Request, response: Response| {
+                        uris.lock().unwrap().push(request.uri().to_string());
+                        Ok(response)
+                    })
+                    .unwrap();
+                session(&mut socket);
+            });
+        }
+    });
+    (endpoint, uris)
+}
+
+// Keeps the connections open after their session, until the client closes them
+pub fn kept_open(sessions: Vec<Session>) -> Vec<Session> {
+    sessions
+        .into_iter()
+        .map(|session| -> Session {
+            Box::new(move |socket| {
+                session(socket);
+                while socket.read().is_ok() {}
+            })
+        })
+        .collect()
+}
+
+// Closes the connection and waits for the client to close it too
+pub fn close(socket: &mut WebSocket<TcpStream>) {
+    socket.close(None).unwrap();
+    while socket.read().is_ok() {}
+}
+
+// Reads for the given time, so that the pings of the client are answered
+pub fn answer_pings(socket: &mut WebSocket<TcpStream>, duration: Duration) {
+    let deadline = Instant::now() + duration;
+    socket
+        .get_ref()
+        .set_read_timeout(Some(Duration::from_millis(10)))
+        .unwrap();
+    while Instant::now() < deadline {
+        match socket.read() {
+            Ok(_) | Err(tungstenite::Error::Io(_)) => (),
+            Err(_) => return,
+        }
+    }
+    socket.get_ref().set_read_timeout(None).unwrap();
+}
@@ -0,0 +1,188 @@
+use binance::api::*;
+use binance::config::*;
+use binance::exchange_info::*;
+use binance::general::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Server;
+    use float_cmp::*;
+    use std::cell::RefCell;
+    use std::time::Duration;
+
+    #[test]
+    fn symbol_lookups() {
+        let mut server = Server::new();
+        let mock_exchange_info = server
+            .mock("GET", "/api/v3/exchangeInfo")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .with_body_from_file("tests/mocks/general/exchange_info.json")
+            .expect(1)
+            .create();
+
+        let config = Config::default().set_rest_api_endpoint(server.url());
+        let general: General = Binance::new_with_config(None, None, &config);
+        let mut cache = ExchangeInfoCache::new(general, Duration::from_secs(3600));
+
+        let symbol = cache.symbol("ltcbtc").unwrap().unwrap();
+        assert_eq!(symbol.base_asset, "LTC");
+        assert!(approx_eq!(
+            f64,
+            symbol.tick_size().unwrap(),
+            0.000001,
+            ulps = 2
+        ));
+        assert!(approx_eq!(f64, symbol.step_size().unwrap(), 0.01, ulps = 2));
+        assert!(approx_eq!(
+            f64,
+            symbol.min_notional().unwrap(),
+            0.0001,
+            ulps = 2
+        ));
+        assert_eq!(symbol.max_num_orders(), Some(200));
+
+        assert!(cache.symbol("XRPBTC").unwrap().is_none());
+        assert_eq!(cache.symbols_by_quote_asset("BTC").unwrap().len(), 3);
+        let by_base: Vec<String> = cache
+            .symbols_by_base_asset("BNB")
+            .unwrap()
+            .iter()
+            .map(|s| s.symbol.clone())
+            .collect();
+        assert_eq!(by_base, vec!["BNBBTC"]);
+
+        // Served from the cache
+        mock_exchange_info.assert();
+    }
+
+    #[test]
+    fn symbol_changes() {
+        let mut server = Server::new();
+        let mock_exchange_info = server
+            .mock("GET", "/api/v3/exchangeInfo")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .with_body_from_file("tests/mocks/general/exchange_info.json")
+            .expect(1)
+            .create();
+        let mock_updated_exchange_info = server
+            .mock("GET", "/api/v3/exchangeInfo")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .with_body_from_file("tests/mocks/general/exchange_info_updated.json")
+            .expect(1)
+            .create();
+
+        let config = Config::default().set_rest_api_endpoint(server.url());
+        let general: General = Binance::new_with_config(None, None, &config);
+
+        let changes = RefCell::new(Vec::new());
+        let mut cache = ExchangeInfoCache::new(general, Duration::from_secs(0));
+        cache.on_change(|change| {
+            let change = match change {
+                SymbolChange::Listed(symbol) => format!("listed {}", symbol.symbol),
+                SymbolChange::Delisted(symbol) => format!("delisted {}", symbol.symbol),
+                SymbolChange::StatusChanged {
+                    symbol,
+                    previous_status,
+                } => format!("{} {} -> {}", symbol.symbol, previous_status, symbol.status),
+            };
+            changes.borrow_mut().push(change);
+            Ok(())
+        });
+
+        cache.refresh().unwrap();
+        assert!(cache.symbol("XRPBTC").unwrap().is_some());
+        drop(cache);
+
+        mock_exchange_info.assert();
+        mock_updated_exchange_info.assert();
+        assert_eq!(
+            changes.into_inner(),
+            vec![
+                "BNBBTC TRADING -> BREAK",
+                "listed XRPBTC",
+                "delisted LTCBTC"
+            ]
+        );
+    }
+
+    #[test]
+    fn symbol_changes_delivered_when_handler_fails() {
+        let mut server = Server::new();
+        server
+            .mock("GET", "/api/v3/exchangeInfo")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .with_body_from_file("tests/mocks/general/exchange_info.json")
+            .expect(1)
+            .create();
+        server
+            .mock("GET", "/api/v3/exchangeInfo")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .with_body_from_file("tests/mocks/general/exchange_info_updated.json")
+            .expect(1)
+            .create();
+
+        let config = Config::default().set_rest_api_endpoint(server.url());
+        let general: General = Binance::new_with_config(None, None, &config);
+
+        let delivered = RefCell::new(0);
+        let mut cache = ExchangeInfoCache::new(general, Duration::from_secs(3600));
+        cache.on_change(|_| {
+            *delivered.borrow_mut() += 1;
+            Err("handler failed".into())
+        });
+
+        cache.refresh().unwrap();
+        assert!(cache.refresh().is_err());
+        // The updated symbols are kept, without another download
+        assert!(cache.symbol("XRPBTC").unwrap().is_some());
+        assert!(cache.symbol("LTCBTC").unwrap().is_none());
+        drop(cache);
+
+        assert_eq!(delivered.into_inner(), 3);
+    }
+
+    #[test]
+    fn futures_symbol_filters() {
+        let symbol: binance::futures::model::Symbol = serde_json::from_str(
+            r#"{
+                "symbol": "BTCUSDT",
+                "status": "TRADING",
+                "maintMarginPercent": "2.5000",
+                "requiredMarginPercent": "5.0000",
+                "baseAsset": "BTC",
+                "quoteAsset": "USDT",
+                "onboardDate": 1569398400000,
+                "pricePrecision": 2,
+                "quantityPrecision": 3,
+                "baseAssetPrecision": 8,
+                "quotePrecision": 8,
+                "filters": [
+                    {"filterType": "PRICE_FILTER", "minPrice": "556.80", "maxPrice": "4529764", "tickSize": "0.10"},
+                    {"filterType": "LOT_SIZE", "stepSize": "0.001", "maxQty": "1000", "minQty": "0.001"},
+                    {"filterType": "MAX_NUM_ORDERS", "limit": 200},
+                    {"filterType": "MAX_NUM_ALGO_ORDERS", "limit": 10},
+                    {"filterType": "MIN_NOTIONAL", "notional": "100"}
+                ],
+                "orderTypes": ["LIMIT", "MARKET"],
+                "timeInForce": ["GTC"]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(approx_eq!(f64, symbol.tick_size().unwrap(), 0.1, ulps = 2));
+        assert!(approx_eq!(
+            f64,
+            symbol.step_size().unwrap(),
+            0.001,
+            ulps = 2
+        ));
+        assert!(approx_eq!(
+            f64,
+            symbol.min_notional().unwrap(),
+            100.0,
+            ulps = 2
+        ));
+        assert_eq!(symbol.max_num_orders(), Some(200));
+    }
+}
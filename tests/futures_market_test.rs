@@ -1,7 +1,9 @@
 use binance::api::*;
 use binance::config::*;
 use binance::futures::market::FuturesMarket;
-use binance::futures::model::OpenInterestHist;
+use binance::futures::account::ContractType;
+use binance::futures::market::FUNDING_RATE_LIMIT;
+use binance::futures::model::{FundingInfo, FundingRate, OpenInterestHist};
 
 #[cfg(test)]
 mod tests {
@@ -43,4 +45,239 @@ mod tests {
 
         assert_eq!(open_interest_hists, expectation);
     }
+
+    #[test]
+    fn get_premium_index() {
+        let mut server = Server::new();
+        let mock_premium_index = server
+            .mock("GET", "/fapi/v1/premiumIndex")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .match_query(Matcher::Regex("symbol=BTCUSDT".into()))
+            .with_body_from_file("tests/mocks/futures/market/get_premium_index.json")
+            .create();
+
+        let config = Config::default().set_futures_rest_api_endpoint(server.url());
+        let market: FuturesMarket = Binance::new_with_config(None, None, &config);
+
+        let premium_index = market.get_premium_index("BTCUSDT").unwrap();
+        mock_premium_index.assert();
+
+        assert_eq!(premium_index.symbol, "BTCUSDT");
+        assert!((premium_index.mark_price - 11793.63104562).abs() < f64::EPSILON);
+        assert!((premium_index.index_price - 11781.80495970).abs() < f64::EPSILON);
+        assert!((premium_index.last_funding_rate.unwrap() - 0.00038246).abs() < f64::EPSILON);
+        assert!((premium_index.interest_rate.unwrap() - 0.0001).abs() < f64::EPSILON);
+        assert_eq!(premium_index.next_funding_time, 1597392000000);
+    }
+
+    #[test]
+    fn get_premium_index_of_delivery_contract() {
+        let mut server = Server::new();
+        let mock_premium_index = server
+            .mock("GET", "/fapi/v1/premiumIndex")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .match_query(Matcher::Regex("symbol=BTCUSDT_250328".into()))
+            .with_body(
+                r#"{"symbol":"BTCUSDT_250328","markPrice":"94321.10000000","indexPrice":"93512.42000000","estimatedSettlePrice":"93498.13000000","lastFundingRate":"","interestRate":"","nextFundingTime":0,"time":1737072000000}"#,
+            )
+            .create();
+
+        let config = Config::default().set_futures_rest_api_endpoint(server.url());
+        let market: FuturesMarket = Binance::new_with_config(None, None, &config);
+
+        let premium_index = market.get_premium_index("BTCUSDT_250328").unwrap();
+        mock_premium_index.assert();
+
+        assert_eq!(premium_index.symbol, "BTCUSDT_250328");
+        assert_eq!(premium_index.last_funding_rate, None);
+        assert_eq!(premium_index.interest_rate, None);
+    }
+
+    #[test]
+    fn get_funding_rate_history() {
+        let mut server = Server::new();
+        let mock_funding_rate = server
+            .mock("GET", "/fapi/v1/fundingRate")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .match_query(Matcher::Regex(
+                "endTime=1570636800000&limit=2&startTime=1570608000000&symbol=BTCUSDT".into(),
+            ))
+            .with_body_from_file("tests/mocks/futures/market/get_funding_rate_history.json")
+            .create();
+
+        let config = Config::default().set_futures_rest_api_endpoint(server.url());
+        let market: FuturesMarket = Binance::new_with_config(None, None, &config);
+
+        let fundings = market
+            .get_funding_rate_history("BTCUSDT", 1570608000000, 1570636800000, 2)
+            .unwrap();
+        mock_funding_rate.assert();
+
+        let expectation = vec![
+            FundingRate {
+                symbol: "BTCUSDT".into(),
+                funding_rate: -0.0375,
+                funding_time: 1570608000000,
+                mark_price: None,
+            },
+            FundingRate {
+                symbol: "BTCUSDT".into(),
+                funding_rate: 0.0001,
+                funding_time: 1570636800000,
+                mark_price: Some(34287.54619963),
+            },
+        ];
+        assert_eq!(fundings, expectation);
+    }
+
+    #[test]
+    fn get_all_funding_rate_history() {
+        let fundings = |first: u64, count: u64| {
+            let fundings: Vec<String> = (first..first + count)
+                .map(|i| {
+                    format!(
+                        r#"{{"symbol":"BTCUSDT","fundingRate":"0.00010000","fundingTime":{},"markPrice":"34287.54619963"}}"#,
+                        i * 8 * 3600 * 1000
+                    )
+                })
+                .collect();
+            format!("[{}]", fundings.join(","))
+        };
+        let limit = u64::from(FUNDING_RATE_LIMIT);
+        let mut server = Server::new();
+        let mock_first_page = server
+            .mock("GET", "/fapi/v1/fundingRate")
+            .match_query(Matcher::Regex(
+                "limit=1000&startTime=0&symbol=BTCUSDT$".into(),
+            ))
+            .with_body(fundings(0, limit))
+            .create();
+        let mock_second_page = server
+            .mock("GET", "/fapi/v1/fundingRate")
+            .match_query(Matcher::Regex(format!(
+                "limit=1000&startTime={}&symbol=BTCUSDT$",
+                (limit - 1) * 8 * 3600 * 1000 + 1
+            )))
+            .with_body(fundings(limit, 3))
+            .create();
+
+        let config = Config::default().set_futures_rest_api_endpoint(server.url());
+        let market: FuturesMarket = Binance::new_with_config(None, None, &config);
+
+        let fundings = market
+            .get_all_funding_rate_history("BTCUSDT", 0, None)
+            .unwrap();
+        mock_first_page.assert();
+        mock_second_page.assert();
+
+        assert_eq!(fundings.len() as u64, limit + 3);
+        assert!(fundings
+            .windows(2)
+            .all(|pair| pair[0].funding_time < pair[1].funding_time));
+    }
+
+    #[test]
+    fn get_funding_info() {
+        let mut server = Server::new();
+        let mock_funding_info = server
+            .mock("GET", "/fapi/v1/fundingInfo")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .with_body_from_file("tests/mocks/futures/market/get_funding_info.json")
+            .create();
+
+        let config = Config::default().set_futures_rest_api_endpoint(server.url());
+        let market: FuturesMarket = Binance::new_with_config(None, None, &config);
+
+        let funding_info = market.get_funding_info().unwrap();
+        mock_funding_info.assert();
+
+        let expectation = vec![FundingInfo {
+            symbol: "BLZUSDT".into(),
+            adjusted_funding_rate_cap: 0.025,
+            adjusted_funding_rate_floor: -0.025,
+            funding_interval_hours: 8,
+            disclaimer: false,
+        }];
+        assert_eq!(funding_info, expectation);
+    }
+
+    #[test]
+    fn get_continuous_klines() {
+        let mut server = Server::new();
+        let mock_continuous_klines = server
+            .mock("GET", "/fapi/v1/continuousKlines")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .match_query(Matcher::Regex(
+                "contractType=CURRENT_QUARTER&interval=1m&limit=1&pair=BTCUSDT".into(),
+            ))
+            .with_body_from_file("tests/mocks/futures/market/get_continuous_klines.json")
+            .create();
+
+        let config = Config::default().set_futures_rest_api_endpoint(server.url());
+        let market: FuturesMarket = Binance::new_with_config(None, None, &config);
+
+        let klines = market
+            .get_continuous_klines("BTCUSDT", ContractType::CurrentQuarter, "1m", 1, None, None)
+            .unwrap();
+        mock_continuous_klines.assert();
+
+        assert_eq!(klines.len(), 1);
+        assert_eq!(klines[0].open_time, 1607444700000);
+        assert_eq!(klines[0].close, "18896.13");
+        assert_eq!(klines[0].volume, "492.363");
+        assert_eq!(klines[0].number_of_trades, 1874);
+    }
+
+    #[test]
+    fn get_price_klines() {
+        let mut server = Server::new();
+        let mut mocks = Vec::new();
+        for (path, key) in [
+            ("/fapi/v1/indexPriceKlines", "pair"),
+            ("/fapi/v1/markPriceKlines", "symbol"),
+            ("/fapi/v1/premiumIndexKlines", "symbol"),
+        ]
+        .iter()
+        {
+            mocks.push(
+                server
+                    .mock("GET", *path)
+                    .with_header("content-type", "application/json;charset=UTF-8")
+                    .match_query(Matcher::AllOf(vec![
+                        Matcher::UrlEncoded(key.to_string(), "BTCUSDT".into()),
+                        Matcher::UrlEncoded("interval".into(), "1m".into()),
+                        Matcher::UrlEncoded("startTime".into(), "1591256400000".into()),
+                        Matcher::UrlEncoded("endTime".into(), "1591256459999".into()),
+                    ]))
+                    .with_body_from_file("tests/mocks/futures/market/get_price_klines.json")
+                    .create(),
+            );
+        }
+
+        let config = Config::default().set_futures_rest_api_endpoint(server.url());
+        let market: FuturesMarket = Binance::new_with_config(None, None, &config);
+
+        let (start_time, end_time) = (1591256400000, 1591256459999);
+        let klines = vec![
+            market
+                .get_index_price_klines("BTCUSDT", "1m", None, start_time, end_time)
+                .unwrap(),
+            market
+                .get_mark_price_klines("BTCUSDT", "1m", None, start_time, end_time)
+                .unwrap(),
+            market
+                .get_premium_index_klines("BTCUSDT", "1m", None, start_time, end_time)
+                .unwrap(),
+        ];
+        for mock in mocks {
+            mock.assert();
+        }
+
+        for klines in klines {
+            assert_eq!(klines.len(), 1);
+            assert_eq!(klines[0].open, "9653.69440000");
+            assert_eq!(klines[0].close_time, 1591256459999);
+            assert_eq!(klines[0].volume, "0");
+        }
+    }
 }
@@ -6,7 +6,7 @@ use binance::model::*;
 #[cfg(test)]
 mod tests {
     use super::*;
-    use mockito::Server;
+    use mockito::{Server, Matcher};
     use float_cmp::*;
 
     #[test]
@@ -63,6 +63,32 @@ mod tests {
         assert!(exchange_info.symbols.len() > 1);
     }
 
+    #[test]
+    fn exchange_info_for_permissions() {
+        let mut server = Server::new();
+        let mock_exchange_info = server
+            .mock("GET", "/api/v3/exchangeInfo")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .match_query(Matcher::Exact(
+                "permissions=%5B%22SPOT%22%2C%22MARGIN%22%5D".into(),
+            ))
+            .with_body_from_file("tests/mocks/general/exchange_info.json")
+            .create();
+
+        let config = Config::default().set_rest_api_endpoint(server.url());
+        let general: General = Binance::new_with_config(None, None, &config);
+
+        let exchange_info = general
+            .exchange_info_for_permissions(&["SPOT", "MARGIN"])
+            .unwrap();
+        mock_exchange_info.assert();
+
+        assert!(exchange_info.symbols.len() > 1);
+        assert!(exchange_info.symbols[0]
+            .permissions
+            .contains(&"SPOT".to_string()));
+    }
+
     #[test]
     fn get_symbol_info() {
         let mut server = Server::new();
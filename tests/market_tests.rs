@@ -97,6 +97,31 @@ mod tests {
         assert!(approx_eq!(f64, symbol.price, 4.00000200, ulps = 2));
     }
 
+    #[test]
+    fn get_symbols_prices() {
+        let mut server = Server::new();
+        let mock_get_symbols_prices = server
+            .mock("GET", "/api/v3/ticker/price")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .match_query(Matcher::Exact(
+                "symbols=%5B%22LTCBTC%22%2C%22ETHBTC%22%5D".into(),
+            ))
+            .with_body_from_file("tests/mocks/market/get_all_prices.json")
+            .create();
+
+        let config = Config::default().set_rest_api_endpoint(server.url());
+        let market: Market = Binance::new_with_config(None, None, &config);
+
+        let prices = market.get_symbols_prices(&["LTCBTC", "ETHBTC"]).unwrap();
+        mock_get_symbols_prices.assert();
+
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices[0].symbol, "LTCBTC");
+        assert!(approx_eq!(f64, prices[0].price, 4.00000200, ulps = 2));
+        assert_eq!(prices[1].symbol, "ETHBTC");
+        assert!(approx_eq!(f64, prices[1].price, 0.07946600, ulps = 2));
+    }
+
     #[test]
     fn get_average_price() {
         let mut server = Server::new();
@@ -352,4 +377,121 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn get_rolling_window_price_stats() {
+        let mut server = Server::new();
+        let mock_get_rolling_window_price_stats = server
+            .mock("GET", "/api/v3/ticker")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .match_query(Matcher::Regex(
+                "symbol=BNBBTC&type=FULL&windowSize=4h".into(),
+            ))
+            .with_body_from_file("tests/mocks/market/get_rolling_window_price_stats.json")
+            .create();
+
+        let config = Config::default().set_rest_api_endpoint(server.url());
+        let market: Market = Binance::new_with_config(None, None, &config);
+
+        let price_stats = market
+            .get_rolling_window_price_stats("BNBBTC", "4h")
+            .unwrap();
+        mock_get_rolling_window_price_stats.assert();
+
+        assert_eq!(price_stats.symbol, "BNBBTC");
+        assert_eq!(price_stats.price_change, "-8.00000000");
+        assert_eq!(price_stats.price_change_percent, "-88.889");
+        assert_eq!(price_stats.weighted_avg_price, "2.60427807");
+        assert!(approx_eq!(
+            f64,
+            price_stats.open_price,
+            9.00000000,
+            ulps = 2
+        ));
+        assert!(approx_eq!(
+            f64,
+            price_stats.last_price,
+            1.00000000,
+            ulps = 2
+        ));
+        assert!(approx_eq!(f64, price_stats.volume, 187.00000000, ulps = 2));
+        assert!(approx_eq!(
+            f64,
+            price_stats.quote_volume,
+            487.00000000,
+            ulps = 2
+        ));
+        assert_eq!(price_stats.open_time, 1641859200000);
+        assert_eq!(price_stats.close_time, 1642031999999);
+        assert_eq!(price_stats.first_id, 0);
+        assert_eq!(price_stats.last_id, 60);
+        assert_eq!(price_stats.count, 61);
+    }
+
+    #[test]
+    fn get_mini_trading_day_price_stats() {
+        let mut server = Server::new();
+        let mock_get_mini_trading_day_price_stats = server
+            .mock("GET", "/api/v3/ticker/tradingDay")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .match_query(Matcher::Regex(
+                "symbol=BTCUSDT&timeZone=%2B08%3A00&type=MINI".into(),
+            ))
+            .with_body_from_file("tests/mocks/market/get_mini_trading_day_price_stats.json")
+            .create();
+
+        let config = Config::default().set_rest_api_endpoint(server.url());
+        let market: Market = Binance::new_with_config(None, None, &config);
+
+        let price_stats = market
+            .get_mini_trading_day_price_stats("BTCUSDT", Some("+08:00".into()))
+            .unwrap();
+        mock_get_mini_trading_day_price_stats.assert();
+
+        assert_eq!(price_stats.symbol, "BTCUSDT");
+        assert!(approx_eq!(
+            f64,
+            price_stats.high_price,
+            26397.46000000,
+            ulps = 2
+        ));
+        assert!(approx_eq!(
+            f64,
+            price_stats.low_price,
+            26088.34000000,
+            ulps = 2
+        ));
+        assert_eq!(price_stats.first_id, 3220151555);
+        assert_eq!(price_stats.last_id, 3220849281);
+        assert_eq!(price_stats.count, 697727);
+    }
+
+    #[test]
+    fn get_ui_klines() {
+        let mut server = Server::new();
+        let mock_get_ui_klines = server
+            .mock("GET", "/api/v3/uiKlines")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .match_query(Matcher::Regex(
+                "interval=1h&limit=10&symbol=LTCBTC&timeZone=-1%3A00".into(),
+            ))
+            .with_body_from_file("tests/mocks/market/get_ui_klines.json")
+            .create();
+
+        let config = Config::default().set_rest_api_endpoint(server.url());
+        let market: Market = Binance::new_with_config(None, None, &config);
+
+        let klines = market
+            .get_ui_klines("LTCBTC", "1h", 10, None, None, Some("-1:00".into()))
+            .unwrap();
+        mock_get_ui_klines.assert();
+
+        match klines {
+            binance::model::KlineSummaries::AllKlineSummaries(klines) => {
+                assert_eq!(klines.len(), 1);
+                assert_eq!(klines[0].open_time, 1499040000000);
+                assert_eq!(klines[0].close, "0.01577100");
+            }
+        }
+    }
 }
@@ -0,0 +1,177 @@
+use binance::config::*;
+use binance::futures::websockets::*;
+use binance::multiplexer::*;
+use binance::reconnect::*;
+use binance::replay_server::*;
+use binance::websockets::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    fn trade_event(stream: &str, symbol: &str) -> ServerAction {
+        ServerAction::event(
+            stream,
+            format!(
+                r#"{{"e":"trade","E":1672515782136,"s":"{}","t":12345,"p":"0.001","q":"100","b":88,"a":50,"T":1672515782136,"m":true,"M":true}}"#,
+                symbol
+            ),
+        )
+    }
+
+    fn config(server: &ReplayServer) -> Config {
+        Config::default()
+            .set_ws_endpoint(server.ws_endpoint())
+            .set_futures_ws_endpoint(server.ws_endpoint())
+    }
+
+    #[test]
+    fn spot_and_futures_on_one_thread() {
+        let server = ReplayServer::start(vec![
+            vec![
+                trade_event("bnbbtc@trade", "BNBBTC"),
+                ServerAction::Ping,
+                ServerAction::Wait(Duration::from_millis(50)),
+                trade_event("bnbbtc@trade", "BNBBTC"),
+            ],
+            vec![
+                trade_event("ethbtc@trade", "ETHBTC"),
+                trade_event("ethbtc@trade", "ETHBTC"),
+            ],
+        ])
+        .unwrap();
+
+        let keep_running = AtomicBool::new(true);
+        let symbols = RefCell::new(Vec::new());
+        let received = |symbol: String| {
+            let mut symbols = symbols.borrow_mut();
+            symbols.push(symbol);
+            if symbols.len() == 4 {
+                keep_running.store(false, Ordering::Relaxed);
+            }
+        };
+        let mut spot = WebSockets::new(|event: WebsocketEvent| {
+            if let WebsocketEvent::Trade(trade) = event {
+                received(trade.symbol);
+            }
+            Ok(())
+        });
+        spot.connect_with_config("bnbbtc@trade", &config(&server))
+            .unwrap();
+        let mut futures = FuturesWebSockets::new(|event: FuturesWebsocketEvent| {
+            if let FuturesWebsocketEvent::Trade(trade) = event {
+                received(trade.symbol);
+            }
+            Ok(())
+        });
+        futures
+            .connect_with_config(&FuturesMarket::USDM, "ethbtc@trade", &config(&server))
+            .unwrap();
+
+        let mut multiplexer = Multiplexer::new();
+        assert_eq!(multiplexer.add(spot).unwrap(), 0);
+        assert_eq!(multiplexer.add(futures).unwrap(), 1);
+        assert_eq!(multiplexer.len(), 2);
+        multiplexer.event_loop(&keep_running).unwrap();
+        drop(multiplexer);
+
+        let mut symbols = symbols.into_inner();
+        symbols.sort();
+        assert_eq!(symbols, vec!["BNBBTC", "BNBBTC", "ETHBTC", "ETHBTC"]);
+        assert_eq!(server.pongs(), 1);
+    }
+
+    #[test]
+    fn lost_connection_stops_loop() {
+        let server = ReplayServer::start(vec![
+            vec![trade_event("bnbbtc@trade", "BNBBTC")],
+            vec![trade_event("ethbtc@trade", "ETHBTC"), ServerAction::Drop],
+        ])
+        .unwrap();
+
+        let keep_running = AtomicBool::new(true);
+        let mut multiplexer = Multiplexer::new();
+        for stream in ["bnbbtc@trade", "ethbtc@trade"].iter() {
+            let mut web_socket = WebSockets::new(|_event: WebsocketEvent| Ok(()));
+            web_socket
+                .connect_with_config(*stream, &config(&server))
+                .unwrap();
+            multiplexer.add(web_socket).unwrap();
+        }
+        let error = multiplexer.event_loop(&keep_running).unwrap_err();
+        assert!(error.to_string().starts_with("Connection 1 lost"));
+    }
+
+    #[test]
+    fn reconnect_without_holding_up_others() {
+        let server = ReplayServer::start(vec![
+            vec![trade_event("bnbbtc@trade", "BNBBTC"), ServerAction::Drop],
+            vec![
+                ServerAction::Wait(Duration::from_millis(100)),
+                trade_event("ethbtc@trade", "ETHBTC"),
+            ],
+            vec![trade_event("bnbbtc@trade", "BNBBTC")],
+        ])
+        .unwrap();
+
+        let keep_running = AtomicBool::new(true);
+        let events = RefCell::new(Vec::new());
+        let mut reconnecting = WebSockets::new(|event: WebsocketEvent| {
+            let mut events = events.borrow_mut();
+            match event {
+                WebsocketEvent::Trade(_) => {
+                    events.push("bnbbtc trade");
+                    if events.len() == 6 {
+                        keep_running.store(false, Ordering::Relaxed);
+                    }
+                }
+                WebsocketEvent::Connection(ConnectionEvent::Disconnected { .. }) => {
+                    events.push("bnbbtc disconnected")
+                }
+                WebsocketEvent::Connection(ConnectionEvent::Reconnecting { .. }) => {
+                    events.push("bnbbtc reconnecting")
+                }
+                WebsocketEvent::Connection(ConnectionEvent::Connected { .. }) => {
+                    events.push("bnbbtc connected")
+                }
+                _ => (),
+            }
+            Ok(())
+        });
+        reconnecting
+            .connect_with_config("bnbbtc@trade", &config(&server))
+            .unwrap();
+        let mut other = WebSockets::new(|event: WebsocketEvent| {
+            if let WebsocketEvent::Trade(_) = event {
+                events.borrow_mut().push("ethbtc trade");
+            }
+            Ok(())
+        });
+        other
+            .connect_with_config("ethbtc@trade", &config(&server))
+            .unwrap();
+
+        let policy = ReconnectPolicy::default().set_initial_backoff(Duration::from_millis(300));
+        let mut multiplexer = Multiplexer::new().set_reconnect_policy(policy);
+        multiplexer.add(reconnecting).unwrap();
+        multiplexer.add(other).unwrap();
+        multiplexer.event_loop(&keep_running).unwrap();
+        drop(multiplexer);
+
+        assert_eq!(
+            events.into_inner(),
+            vec![
+                "bnbbtc trade",
+                "bnbbtc disconnected",
+                "bnbbtc reconnecting",
+                "ethbtc trade",
+                "bnbbtc connected",
+                "bnbbtc trade",
+            ]
+        );
+        assert_eq!(server.requests().len(), 3);
+    }
+}
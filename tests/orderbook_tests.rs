@@ -0,0 +1,284 @@
+use binance::api::*;
+use binance::config::*;
+use binance::futures::market::FuturesMarket;
+use binance::market::*;
+use binance::model::*;
+use binance::orderbook::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Server, Matcher};
+    use float_cmp::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    fn depth_event(
+        first_update_id: u64, final_update_id: u64, previous_final_update_id: Option<u64>,
+        bids: &str, asks: &str,
+    ) -> DepthOrderBookEvent {
+        let pu = previous_final_update_id
+            .map(|pu| format!(r#""pu":{},"#, pu))
+            .unwrap_or_default();
+        let json = format!(
+            r#"{{"e":"depthUpdate","E":1589436922972,"s":"LTCBTC","U":{},"u":{},{}"b":{},"a":{}}}"#,
+            first_update_id, final_update_id, pu, bids, asks
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn spot_order_book_sync() {
+        let mut server = Server::new();
+        let mock_get_depth = server
+            .mock("GET", "/api/v3/depth")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .match_query(Matcher::Regex("limit=1000&symbol=LTCBTC".into()))
+            .with_body_from_file("tests/mocks/market/get_depth.json")
+            .expect(1)
+            .create();
+
+        let config = Config::default().set_rest_api_endpoint(server.url());
+        let market: Market = Binance::new_with_config(None, None, &config);
+
+        let mut changes = 0;
+        {
+            let mut manager = OrderBookManager::spot(market, 1000);
+            manager.on_change(|_book| {
+                changes += 1;
+                Ok(())
+            });
+
+            // Stale event, fully covered by the snapshot
+            manager
+                .handle_event(depth_event(
+                    1027000,
+                    1027010,
+                    None,
+                    r#"[["3.0","1.0"]]"#,
+                    "[]",
+                ))
+                .unwrap();
+            // No event following the snapshot yet
+            assert!(!manager.is_synced("LTCBTC"));
+            assert!(manager.book("LTCBTC").is_none());
+
+            manager
+                .handle_event(depth_event(
+                    1027020,
+                    1027025,
+                    None,
+                    r#"[["3.9","10.0"]]"#,
+                    "[]",
+                ))
+                .unwrap();
+            manager
+                .handle_event(depth_event(
+                    1027026,
+                    1027027,
+                    None,
+                    "[]",
+                    r#"[["4.000002","0.0"],["4.1","2.5"]]"#,
+                ))
+                .unwrap();
+
+            let book = manager.book("ltcbtc").unwrap();
+            assert_eq!(book.last_update_id, 1027027);
+            assert_eq!(book.best_bid().unwrap(), Bids::new(4.0, 431.0));
+            assert!(approx_eq!(
+                f64,
+                book.best_ask().unwrap().price,
+                4.1,
+                ulps = 2
+            ));
+
+            let bids = book.bids(5);
+            assert_eq!(bids, vec![Bids::new(4.0, 431.0), Bids::new(3.9, 10.0)]);
+            assert_eq!(book.asks(5).len(), 1);
+        }
+        mock_get_depth.assert();
+        assert_eq!(changes, 2);
+    }
+
+    #[test]
+    fn spot_order_book_resync_on_gap() {
+        let mut server = Server::new();
+        let mock_get_depth = server
+            .mock("GET", "/api/v3/depth")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .match_query(Matcher::Regex("symbol=LTCBTC".into()))
+            .with_body_from_file("tests/mocks/market/get_depth.json")
+            .expect(2)
+            .create();
+
+        let config = Config::default().set_rest_api_endpoint(server.url());
+        let market: Market = Binance::new_with_config(None, None, &config);
+
+        let mut manager = OrderBookManager::spot(market, 100);
+        manager
+            .handle_event(depth_event(
+                1027024,
+                1027025,
+                None,
+                r#"[["3.9","10.0"]]"#,
+                "[]",
+            ))
+            .unwrap();
+        assert_eq!(manager.book("LTCBTC").unwrap().bids(5).len(), 2);
+
+        // Already applied
+        manager
+            .handle_event(depth_event(1027020, 1027023, None, "[]", "[]"))
+            .unwrap();
+        assert_eq!(manager.book("LTCBTC").unwrap().last_update_id, 1027025);
+
+        // 1027026 is missing: a new snapshot is fetched, which is older than the event
+        manager
+            .handle_event(depth_event(1027027, 1027030, None, "[]", "[]"))
+            .unwrap();
+        mock_get_depth.assert();
+        assert!(!manager.is_synced("LTCBTC"));
+    }
+
+    #[test]
+    fn futures_order_book_sync() {
+        let mut server = Server::new();
+        let mock_get_depth = server
+            .mock("GET", "/fapi/v1/depth")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .match_query(Matcher::Regex("limit=500&symbol=LTCBTC".into()))
+            .with_body_from_file("tests/mocks/futures/market/get_depth.json")
+            .create();
+
+        let config = Config::default().set_futures_rest_api_endpoint(server.url());
+        let market: FuturesMarket = Binance::new_with_config(None, None, &config);
+
+        let mut manager = OrderBookManager::futures(market, 500);
+        manager
+            .handle_event(depth_event(
+                1027020,
+                1027024,
+                Some(1027019),
+                r#"[["3.9","10.0"]]"#,
+                "[]",
+            ))
+            .unwrap();
+        manager
+            .handle_event(depth_event(
+                1027025,
+                1027030,
+                Some(1027024),
+                r#"[["4.0","0"]]"#,
+                "[]",
+            ))
+            .unwrap();
+        mock_get_depth.assert();
+
+        let book = manager.book("LTCBTC").unwrap();
+        assert_eq!(book.last_update_id, 1027030);
+        assert_eq!(book.best_bid().unwrap(), Bids::new(3.9, 10.0));
+    }
+
+    #[test]
+    fn spot_order_book_waits_for_lagging_snapshot() {
+        let mut server = Server::new();
+        let mock_get_depth = server
+            .mock("GET", "/api/v3/depth")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .match_query(Matcher::Regex("symbol=LTCBTC".into()))
+            .with_body_from_file("tests/mocks/market/get_depth.json")
+            .expect(1)
+            .create();
+
+        let config = Config::default().set_rest_api_endpoint(server.url());
+        let market: Market = Binance::new_with_config(None, None, &config);
+
+        // The snapshot is older than the events, it is not fetched again on every event
+        let mut manager = OrderBookManager::spot(market, 5000)
+            .set_resync_delay(std::time::Duration::from_secs(60));
+        for first_update_id in [1027030, 1027031, 1027032].iter() {
+            manager
+                .handle_event(depth_event(
+                    *first_update_id,
+                    *first_update_id,
+                    None,
+                    "[]",
+                    "[]",
+                ))
+                .unwrap();
+            assert!(!manager.is_synced("LTCBTC"));
+        }
+        mock_get_depth.assert();
+    }
+
+    #[test]
+    fn order_book_kept_when_handler_fails() {
+        let mut server = Server::new();
+        let mock_get_depth = server
+            .mock("GET", "/api/v3/depth")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .match_query(Matcher::Regex("symbol=LTCBTC".into()))
+            .with_body_from_file("tests/mocks/market/get_depth.json")
+            .expect(1)
+            .create();
+
+        let config = Config::default().set_rest_api_endpoint(server.url());
+        let market: Market = Binance::new_with_config(None, None, &config);
+
+        let mut manager = OrderBookManager::spot(market, 100);
+        manager.on_change(|_book| Err("handler failed".into()));
+        assert!(manager
+            .handle_event(depth_event(1027024, 1027025, None, "[]", "[]"))
+            .is_err());
+        assert!(manager
+            .handle_event(depth_event(1027026, 1027026, None, "[]", "[]"))
+            .is_err());
+        mock_get_depth.assert();
+        assert_eq!(manager.book("LTCBTC").unwrap().last_update_id, 1027026);
+    }
+
+    // Fails the first fetch, then returns a snapshot at update 100
+    struct FlakySource(Rc<Cell<u32>>);
+
+    impl DepthSnapshot for FlakySource {
+        fn depth_snapshot(&self, _symbol: &str, _limit: u64) -> binance::errors::Result<OrderBook> {
+            self.0.set(self.0.get() + 1);
+            if self.0.get() == 1 {
+                return Err("snapshot failed".into());
+            }
+            Ok(serde_json::from_str(r#"{"lastUpdateId":100,"bids":[],"asks":[]}"#).unwrap())
+        }
+    }
+
+    #[test]
+    fn events_kept_when_snapshot_fails() {
+        let fetches = Rc::new(Cell::new(0));
+        let mut manager =
+            OrderBookManager::new(FlakySource(fetches.clone()), SequenceRules::Spot, 100)
+                .set_resync_delay(Duration::from_secs(0));
+        assert!(manager
+            .handle_event(depth_event(100, 101, None, "[]", "[]"))
+            .is_err());
+        // Applied after the first event, which overlaps the snapshot
+        manager
+            .handle_event(depth_event(102, 102, None, "[]", "[]"))
+            .unwrap();
+        assert_eq!(fetches.get(), 2);
+        assert_eq!(manager.book("LTCBTC").unwrap().last_update_id, 102);
+
+        // Not fetched again before the delay
+        let fetches = Rc::new(Cell::new(0));
+        let mut manager =
+            OrderBookManager::new(FlakySource(fetches.clone()), SequenceRules::Spot, 100)
+                .set_resync_delay(Duration::from_secs(60));
+        assert!(manager
+            .handle_event(depth_event(100, 101, None, "[]", "[]"))
+            .is_err());
+        manager
+            .handle_event(depth_event(102, 102, None, "[]", "[]"))
+            .unwrap();
+        assert_eq!(fetches.get(), 1);
+        assert!(!manager.is_synced("LTCBTC"));
+    }
+}
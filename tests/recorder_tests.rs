@@ -0,0 +1,125 @@
+use binance::futures::websockets::*;
+use binance::recorder::*;
+use binance::websockets::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Instant;
+
+    const TRADE: &str = r#"{"e":"trade","E":1672515782136,"s":"BNBBTC","t":12345,"p":"0.001","q":"100","b":88,"a":50,"T":1672515782136,"m":true,"M":true}"#;
+    const COMBINED_TRADE: &str = r#"{"stream":"bnbbtc@trade","data":{"e":"trade","E":1672515782136,"s":"BNBBTC","t":12346,"p":"0.001","q":"100","b":88,"a":50,"T":1672515782136,"m":true,"M":true}}"#;
+
+    // Path of an empty log in the temporary directory
+    fn session_log(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("binance_{}_{}.log", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn describe(envelope: &EventEnvelope<WebsocketEvent>) -> String {
+        format!(
+            "{} {} {:?} {:?}",
+            envelope.received_at, envelope.connection_id, envelope.stream, envelope.event
+        )
+    }
+
+    #[test]
+    fn read_recorded_frames() {
+        let path = session_log("read_recorded_frames");
+        let mut recorder = SessionRecorder::create(&path).unwrap();
+        recorder.record(1000, 1, TRADE).unwrap();
+        recorder.record(1005, 2, "line\nbreak ").unwrap();
+        recorder.record(1010, 2, "").unwrap();
+        recorder
+            .record_binary(1015, 3, &[0xff, b'\n', b' ', 0])
+            .unwrap();
+
+        let frames: Vec<RecordedFrame> = SessionReader::open(&path)
+            .unwrap()
+            .collect::<binance::errors::Result<_>>()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0].received_at, 1000);
+        assert_eq!(frames[0].connection_id, 1);
+        assert_eq!(frames[0].frame, Frame::Text(TRADE.into()));
+        assert_eq!(frames[1].frame, Frame::Text("line\nbreak ".into()));
+        assert_eq!(frames[2].received_at, 1010);
+        assert_eq!(frames[2].frame, Frame::Text("".into()));
+        assert_eq!(frames[3].connection_id, 3);
+        assert_eq!(frames[3].frame, Frame::Binary(vec![0xff, b'\n', b' ', 0]));
+    }
+
+    #[test]
+    fn truncated_log() {
+        let mut reader = SessionReader::new("1000 1 20 {\"e\":".as_bytes());
+        assert!(reader.read_frame().is_err());
+    }
+
+    #[test]
+    fn replay_recorded_session() {
+        let path = session_log("replay_recorded_session");
+        let mut recorded = Vec::new();
+        {
+            let mut web_socket = WebSockets::new_with_envelope(|envelope| {
+                recorded.push(describe(&envelope));
+                Ok(())
+            });
+            web_socket.set_recorder(SessionRecorder::create(&path).unwrap());
+            web_socket.handle_msg(TRADE).unwrap();
+            web_socket.handle_msg(COMBINED_TRADE).unwrap();
+            assert!(web_socket.take_recorder().is_some());
+            // Not recorded anymore
+            web_socket.handle_msg(TRADE).unwrap();
+        }
+
+        let mut replayed = Vec::new();
+        let mut web_socket = WebSockets::new_with_envelope(|envelope| {
+            replayed.push(describe(&envelope));
+            Ok(())
+        });
+        let count = web_socket
+            .replay(&path, ReplaySpeed::AsFastAsPossible)
+            .unwrap();
+        drop(web_socket);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(count, 2);
+        assert_eq!(replayed, recorded[..2].to_vec());
+        assert!(replayed[1].contains("Some(\"bnbbtc@trade\")"));
+    }
+
+    #[test]
+    fn replay_at_recorded_speed() {
+        let path = session_log("replay_at_recorded_speed");
+        let mut recorder = SessionRecorder::create(&path).unwrap();
+        recorder.record(1000, 7, TRADE).unwrap();
+        recorder.record(1200, 7, TRADE).unwrap();
+        drop(recorder);
+
+        let mut received_at = Vec::new();
+        let mut web_socket = FuturesWebSockets::new_with_envelope(|envelope| {
+            assert!(matches!(envelope.event, FuturesWebsocketEvent::Trade(_)));
+            assert_eq!(envelope.connection_id, 7);
+            received_at.push(envelope.received_at);
+            Ok(())
+        });
+        let started = Instant::now();
+        web_socket.replay(&path, ReplaySpeed::Recorded).unwrap();
+        assert!(started.elapsed().as_millis() >= 200);
+
+        let started = Instant::now();
+        web_socket.replay(&path, ReplaySpeed::Scaled(10.0)).unwrap();
+        assert!(started.elapsed().as_millis() < 200);
+
+        assert!(web_socket.replay(&path, ReplaySpeed::Scaled(0.0)).is_err());
+        drop(web_socket);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(received_at, vec![1000, 1200, 1000, 1200]);
+    }
+}
@@ -0,0 +1,240 @@
+use binance::config::*;
+use binance::futures::websockets::*;
+use binance::reconnect::*;
+use binance::recorder::*;
+use binance::replay_server::*;
+use binance::websockets::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::{Duration, Instant};
+    use tungstenite::Message;
+
+    fn trade(symbol: &str) -> String {
+        format!(
+            r#"{{"e":"trade","E":1672515782136,"s":"{}","t":12345,"p":"0.001","q":"100","b":88,"a":50,"T":1672515782136,"m":true,"M":true}}"#,
+            symbol
+        )
+    }
+
+    fn trade_event(stream: &str, symbol: &str) -> ServerAction {
+        ServerAction::event(stream, trade(symbol))
+    }
+
+    fn config(server: &ReplayServer) -> Config {
+        Config::default()
+            .set_ws_endpoint(server.ws_endpoint())
+            .set_futures_ws_endpoint(server.ws_endpoint())
+    }
+
+    #[test]
+    fn raw_stream() {
+        let server = ReplayServer::start(vec![vec![
+            trade_event("bnbbtc@trade", "BNBBTC"),
+            ServerAction::Ping,
+            // Not subscribed
+            trade_event("ethbtc@trade", "ETHBTC"),
+            trade_event("bnbbtc@trade", "BNBBTC"),
+            ServerAction::Malformed,
+        ]])
+        .unwrap();
+
+        let keep_running = AtomicBool::new(true);
+        let mut symbols = Vec::new();
+        let mut web_socket = WebSockets::new(|event: WebsocketEvent| {
+            if let WebsocketEvent::Trade(trade) = event {
+                symbols.push(trade.symbol);
+            }
+            Ok(())
+        });
+        web_socket
+            .connect_with_config("bnbbtc@trade", &config(&server))
+            .unwrap();
+        // The malformed frame cannot be decoded
+        assert!(web_socket.event_loop(&keep_running).is_err());
+        drop(web_socket);
+
+        assert_eq!(symbols, vec!["BNBBTC", "BNBBTC"]);
+        assert_eq!(server.requests(), vec!["/ws/bnbbtc@trade"]);
+    }
+
+    #[test]
+    fn reconnect_after_close_and_drop() {
+        let server = ReplayServer::start(vec![
+            vec![trade_event("bnbbtc@trade", "BNBBTC"), ServerAction::Close],
+            vec![trade_event("bnbbtc@trade", "BNBBTC"), ServerAction::Drop],
+            vec![trade_event("bnbbtc@trade", "BNBBTC")],
+        ])
+        .unwrap();
+
+        let keep_running = AtomicBool::new(true);
+        let mut events = Vec::new();
+        let mut web_socket = WebSockets::new(|event: WebsocketEvent| {
+            match event {
+                WebsocketEvent::Trade(_) => {
+                    events.push("trade");
+                    if events.len() == 5 {
+                        keep_running.store(false, Ordering::Relaxed);
+                    }
+                }
+                WebsocketEvent::Connection(ConnectionEvent::Disconnected { .. }) => {
+                    events.push("disconnected")
+                }
+                _ => (),
+            }
+            Ok(())
+        });
+        web_socket
+            .connect_with_config("bnbbtc@trade", &config(&server))
+            .unwrap();
+        let policy = ReconnectPolicy::default().set_initial_backoff(Duration::from_millis(10));
+        web_socket
+            .event_loop_with_reconnect(&keep_running, &policy)
+            .unwrap();
+        drop(web_socket);
+
+        assert_eq!(
+            events,
+            vec!["trade", "disconnected", "trade", "disconnected", "trade"]
+        );
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn subscribe_message() {
+        let server = ReplayServer::start(vec![vec![
+            ServerAction::AwaitSubscription("ethbtc@trade".into()),
+            trade_event("ethbtc@trade", "ETHBTC"),
+        ]])
+        .unwrap();
+
+        let keep_running = AtomicBool::new(true);
+        let mut events = Vec::new();
+        let mut web_socket = WebSockets::new(|event: WebsocketEvent| {
+            match event {
+                WebsocketEvent::Response(response) => {
+                    events.push(format!("response {:?}", response.id))
+                }
+                WebsocketEvent::Trade(trade) => {
+                    events.push(format!("trade {}", trade.symbol));
+                    keep_running.store(false, Ordering::Relaxed);
+                }
+                _ => (),
+            }
+            Ok(())
+        });
+        web_socket
+            .connect_with_config("bnbbtc@trade", &config(&server))
+            .unwrap();
+        let id = web_socket.subscribe(&["ethbtc@trade".into()]).unwrap();
+        web_socket.event_loop(&keep_running).unwrap();
+        drop(web_socket);
+
+        assert_eq!(
+            events,
+            vec![format!("response Some({})", id), "trade ETHBTC".into()]
+        );
+        let received = server.received();
+        assert_eq!(received.len(), 1);
+        assert!(received[0].contains("SUBSCRIBE"));
+    }
+
+    #[test]
+    fn combined_stream() {
+        let server = ReplayServer::start(vec![vec![
+            trade_event("bnbbtc@trade", "BNBBTC"),
+            trade_event("btcusdt@trade", "BTCUSDT"),
+            trade_event("ethbtc@trade", "ETHBTC"),
+        ]])
+        .unwrap();
+        let url = format!(
+            "{}/stream?streams=bnbbtc@trade/ethbtc@trade",
+            server.endpoint()
+        );
+        let (mut socket, _) = tungstenite::connect(url).unwrap();
+
+        let mut streams = Vec::new();
+        let mut web_socket = WebSockets::new_with_envelope(|envelope| {
+            if let WebsocketEvent::Trade(trade) = envelope.event {
+                streams.push((envelope.stream, trade.symbol));
+            }
+            Ok(())
+        });
+        for _ in 0..2 {
+            match socket.read().unwrap() {
+                Message::Text(msg) => web_socket.handle_msg(&msg).unwrap(),
+                message => panic!("Unexpected message {:?}", message),
+            }
+        }
+        drop(web_socket);
+
+        assert_eq!(
+            streams,
+            vec![
+                (Some("bnbbtc@trade".to_string()), "BNBBTC".to_string()),
+                (Some("ethbtc@trade".to_string()), "ETHBTC".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn replay_recorded_frames() {
+        let path =
+            std::env::temp_dir().join(format!("binance_replay_server_{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut recorder = SessionRecorder::create(&path).unwrap();
+        recorder.record(1000, 1, &trade("BNBBTC")).unwrap();
+        recorder.record(1100, 1, &trade("ETHBTC")).unwrap();
+        drop(recorder);
+        let actions = ServerAction::from_session_log(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let server = ReplayServer::start(vec![actions]).unwrap();
+
+        let keep_running = AtomicBool::new(true);
+        let mut symbols = Vec::new();
+        let mut web_socket = FuturesWebSockets::new(|event: FuturesWebsocketEvent| {
+            if let FuturesWebsocketEvent::Trade(trade) = event {
+                symbols.push(trade.symbol);
+                if symbols.len() == 2 {
+                    keep_running.store(false, Ordering::Relaxed);
+                }
+            }
+            Ok(())
+        });
+        web_socket
+            .connect_with_config(&FuturesMarket::USDM, "bnbbtc@trade", &config(&server))
+            .unwrap();
+        let started = Instant::now();
+        // Fails once stopped
+        let _ = web_socket.event_loop(&keep_running);
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        drop(web_socket);
+
+        assert_eq!(symbols, vec!["BNBBTC", "ETHBTC"]);
+    }
+
+    #[test]
+    fn push_on_demand() {
+        let server = ReplayServer::start(Vec::new()).unwrap();
+
+        let keep_running = AtomicBool::new(true);
+        let mut trades = 0;
+        let mut web_socket = WebSockets::new(|event: WebsocketEvent| {
+            if let WebsocketEvent::Trade(_) = event {
+                trades += 1;
+                keep_running.store(false, Ordering::Relaxed);
+            }
+            Ok(())
+        });
+        web_socket
+            .connect_with_config("bnbbtc@trade", &config(&server))
+            .unwrap();
+        server.push(trade_event("bnbbtc@trade", "BNBBTC"));
+        web_socket.event_loop(&keep_running).unwrap();
+        drop(web_socket);
+
+        assert_eq!(trades, 1);
+    }
+}
@@ -0,0 +1,284 @@
+use binance::config::*;
+use binance::recorder::*;
+use binance::sbe::*;
+use binance::websockets::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use tungstenite::handshake::server::{Request, Response};
+    use tungstenite::Message;
+
+    // Message header of the stream_1_0 schema
+    fn header(block_length: u16, template_id: u16) -> Vec<u8> {
+        let mut frame = Vec::new();
+        for value in [block_length, template_id, SCHEMA_ID, 0].iter() {
+            frame.extend_from_slice(&value.to_le_bytes());
+        }
+        frame
+    }
+
+    fn symbol(frame: &mut Vec<u8>, symbol: &str) {
+        frame.push(symbol.len() as u8);
+        frame.extend_from_slice(symbol.as_bytes());
+    }
+
+    fn levels(frame: &mut Vec<u8>, levels: &[(i64, i64)]) {
+        frame.extend_from_slice(&16u16.to_le_bytes());
+        frame.extend_from_slice(&(levels.len() as u16).to_le_bytes());
+        for (price, qty) in levels {
+            frame.extend_from_slice(&price.to_le_bytes());
+            frame.extend_from_slice(&qty.to_le_bytes());
+        }
+    }
+
+    fn trades_frame() -> Vec<u8> {
+        let mut frame = header(18, TRADES_STREAM_EVENT);
+        frame.extend_from_slice(&1672515782136123i64.to_le_bytes());
+        frame.extend_from_slice(&1672515782135999i64.to_le_bytes());
+        frame.push(-8i8 as u8);
+        frame.push(-5i8 as u8);
+        frame.extend_from_slice(&25u16.to_le_bytes());
+        frame.extend_from_slice(&2u32.to_le_bytes());
+        for (id, price, qty, is_buyer_maker) in [
+            (12345i64, 100000i64, 1i64, 1u8),
+            (12346, 4210000000, 120000, 0),
+        ]
+        .iter()
+        {
+            frame.extend_from_slice(&id.to_le_bytes());
+            frame.extend_from_slice(&price.to_le_bytes());
+            frame.extend_from_slice(&qty.to_le_bytes());
+            frame.push(*is_buyer_maker);
+        }
+        symbol(&mut frame, "BNBBTC");
+        frame
+    }
+
+    #[test]
+    fn decimals() {
+        assert_eq!(decimal(100000, -8), "0.00100000");
+        assert_eq!(decimal(4210000000, -8), "42.10000000");
+        assert_eq!(decimal(-15, -1), "-1.5");
+        assert_eq!(decimal(0, -2), "0.00");
+        assert_eq!(decimal(12, 2), "1200");
+    }
+
+    #[test]
+    fn trades() {
+        let events = decode(&trades_frame()).unwrap();
+        assert_eq!(events.len(), 2);
+        match (&events[0], &events[1]) {
+            (WebsocketEvent::Trade(first), WebsocketEvent::Trade(second)) => {
+                assert_eq!(first.event_type, "trade");
+                assert_eq!(first.event_time, 1672515782136);
+                assert_eq!(first.trade_order_time, 1672515782135);
+                assert_eq!(first.symbol, "BNBBTC");
+                assert_eq!(first.trade_id, 12345);
+                assert_eq!(first.price, "0.00100000");
+                assert_eq!(first.qty, "0.00001");
+                assert!(first.is_buyer_maker);
+                assert_eq!(second.trade_id, 12346);
+                assert_eq!(second.price, "42.10000000");
+                assert_eq!(second.qty, "1.20000");
+                assert!(!second.is_buyer_maker);
+            }
+            events => panic!("Unexpected events {:?}", events),
+        }
+    }
+
+    #[test]
+    fn record_and_replay_binary_frames() {
+        let path = std::env::temp_dir().join(format!("binance_sbe_{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut recorded = Vec::new();
+        {
+            let mut web_socket = WebSockets::new_with_envelope(|envelope| {
+                recorded.push(format!(
+                    "{} {:?} {:?}",
+                    envelope.received_at, envelope.stream, envelope.event
+                ));
+                Ok(())
+            });
+            web_socket.set_recorder(SessionRecorder::create(&path).unwrap());
+            web_socket.handle_binary(&trades_frame()).unwrap();
+        }
+
+        let mut replayed = Vec::new();
+        let mut web_socket = WebSockets::new_with_envelope(|envelope| {
+            replayed.push(format!(
+                "{} {:?} {:?}",
+                envelope.received_at, envelope.stream, envelope.event
+            ));
+            Ok(())
+        });
+        let count = web_socket
+            .replay(&path, ReplaySpeed::AsFastAsPossible)
+            .unwrap();
+        drop(web_socket);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(count, 1);
+        assert_eq!(recorded.len(), 2);
+        assert!(recorded[0].contains("Some(\"bnbbtc@trade\")"));
+        assert_eq!(replayed, recorded);
+    }
+
+    #[test]
+    fn best_bid_ask() {
+        // Two bytes of fields unknown to the decoder
+        let mut frame = header(52, BEST_BID_ASK_STREAM_EVENT);
+        frame.extend_from_slice(&1672515782136123i64.to_le_bytes());
+        frame.extend_from_slice(&400900217i64.to_le_bytes());
+        frame.push(-2i8 as u8);
+        frame.push(-3i8 as u8);
+        for value in [2512i64, 31210, 2513, 40066].iter() {
+            frame.extend_from_slice(&value.to_le_bytes());
+        }
+        frame.extend_from_slice(&[0, 0]);
+        symbol(&mut frame, "BNBUSDT");
+
+        let (stream, _) = decode_stream(&frame).unwrap();
+        assert_eq!(stream.as_deref(), Some("bnbusdt@bestBidAsk"));
+        match &decode(&frame).unwrap()[..] {
+            [WebsocketEvent::BookTicker(book_ticker)] => {
+                assert_eq!(book_ticker.update_id, 400900217);
+                assert_eq!(book_ticker.symbol, "BNBUSDT");
+                assert_eq!(book_ticker.best_bid, "25.12");
+                assert_eq!(book_ticker.best_bid_qty, "31.210");
+                assert_eq!(book_ticker.best_ask, "25.13");
+                assert_eq!(book_ticker.best_ask_qty, "40.066");
+            }
+            events => panic!("Unexpected events {:?}", events),
+        }
+    }
+
+    #[test]
+    fn depth() {
+        let mut frame = header(26, DEPTH_DIFF_STREAM_EVENT);
+        frame.extend_from_slice(&1672515782136123i64.to_le_bytes());
+        frame.extend_from_slice(&157i64.to_le_bytes());
+        frame.extend_from_slice(&160i64.to_le_bytes());
+        frame.push(-4i8 as u8);
+        frame.push(-1i8 as u8);
+        levels(&mut frame, &[(2500, 10), (2499, 0)]);
+        levels(&mut frame, &[(2501, 25)]);
+        symbol(&mut frame, "BNBBTC");
+
+        let (stream, _) = decode_stream(&frame).unwrap();
+        assert_eq!(stream.as_deref(), Some("bnbbtc@depth"));
+        match &decode(&frame).unwrap()[..] {
+            [WebsocketEvent::DepthOrderBook(depth)] => {
+                assert_eq!(depth.event_type, "depthUpdate");
+                assert_eq!(depth.event_time, 1672515782136);
+                assert_eq!(depth.symbol, "BNBBTC");
+                assert_eq!(depth.first_update_id, 157);
+                assert_eq!(depth.final_update_id, 160);
+                assert_eq!(depth.bids.len(), 2);
+                assert_eq!(depth.bids[0].price, 0.25);
+                assert_eq!(depth.bids[0].qty, 1.0);
+                assert_eq!(depth.bids[1].qty, 0.0);
+                assert_eq!(depth.asks.len(), 1);
+                assert_eq!(depth.asks[0].price, 0.2501);
+                assert_eq!(depth.asks[0].qty, 2.5);
+            }
+            events => panic!("Unexpected events {:?}", events),
+        }
+
+        let mut frame = header(18, DEPTH_SNAPSHOT_STREAM_EVENT);
+        frame.extend_from_slice(&1672515782136123i64.to_le_bytes());
+        frame.extend_from_slice(&160i64.to_le_bytes());
+        frame.push(-4i8 as u8);
+        frame.push(-1i8 as u8);
+        levels(&mut frame, &[(2500, 10)]);
+        levels(&mut frame, &[(2501, 25), (2502, 5)]);
+        symbol(&mut frame, "BNBBTC");
+
+        let (stream, _) = decode_stream(&frame).unwrap();
+        assert_eq!(stream.as_deref(), Some("bnbbtc@depth20"));
+        match &decode(&frame).unwrap()[..] {
+            [WebsocketEvent::OrderBook(order_book)] => {
+                assert_eq!(order_book.last_update_id, 160);
+                assert_eq!(order_book.bids.len(), 1);
+                assert_eq!(order_book.asks.len(), 2);
+                assert_eq!(order_book.asks[1].price, 0.2502);
+            }
+            events => panic!("Unexpected events {:?}", events),
+        }
+    }
+
+    #[test]
+    fn invalid_frames() {
+        let frame = trades_frame();
+        assert!(decode(&frame[..frame.len() - 1]).is_err());
+        assert!(decode(&header(10, TRADES_STREAM_EVENT)).is_err());
+
+        let mut frame = header(0, 10100);
+        frame.extend_from_slice(&[1, 2, 3]);
+        assert!(decode(&frame).unwrap().is_empty());
+
+        let mut frame = header(18, TRADES_STREAM_EVENT);
+        frame[4] = 2;
+        assert!(decode(&frame).is_err());
+    }
+
+    #[test]
+    fn connect_with_api_key() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("ws://{}/ws", listener.local_addr().unwrap());
+        let api_keys = Arc::new(Mutex::new(Vec::new()));
+        let server_api_keys = api_keys.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut socket = tungstenite::accept_hdr(
+                    stream.unwrap(),
+                    |request: &Request, response: Response| {
+                        let api_key = request.headers().get("X-MBX-APIKEY").cloned();
+                        server_api_keys.lock().unwrap().push(api_key);
+                        Ok(response)
+                    },
+                )
+                .unwrap();
+                socket.send(Message::Binary(trades_frame())).unwrap();
+                while socket.read().is_ok() {}
+            }
+        });
+
+        let keep_running = AtomicBool::new(true);
+        let mut trade_ids = Vec::new();
+        let mut web_socket = WebSockets::new(|event: WebsocketEvent| {
+            if let WebsocketEvent::Trade(trade) = event {
+                trade_ids.push(trade.trade_id);
+                if trade_ids.len() == 2 {
+                    keep_running.store(false, Ordering::Relaxed);
+                }
+            }
+            Ok(())
+        });
+        let config = Config::default()
+            .set_sbe_ws_endpoint(endpoint.clone())
+            .set_ws_endpoint(endpoint);
+        web_socket
+            .connect_sbe("bnbbtc@trade", "ed25519_api_key", &config)
+            .unwrap();
+        web_socket.event_loop(&keep_running).unwrap();
+        web_socket.disconnect().unwrap();
+
+        // The JSON streams are opened without the key
+        web_socket
+            .connect_with_config("bnbbtc@trade", &config)
+            .unwrap();
+        drop(web_socket);
+
+        assert_eq!(trade_ids, vec![12345, 12346]);
+        let api_keys = api_keys.lock().unwrap();
+        assert_eq!(api_keys.len(), 2);
+        assert_eq!(api_keys[0].as_ref().unwrap(), "ed25519_api_key");
+        assert!(api_keys[1].is_none());
+    }
+}
@@ -0,0 +1,157 @@
+use binance::futures::account::ContractType;
+use binance::streams::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spot_stream_names() {
+        let market = StreamMarket::Spot;
+        let names = [
+            (Stream::AggTrade("BTCUSDT".into()), "btcusdt@aggTrade"),
+            (Stream::Trade("BNBBTC".into()), "bnbbtc@trade"),
+            (
+                Stream::Kline("ETHBTC".into(), KlineInterval::Seconds1),
+                "ethbtc@kline_1s",
+            ),
+            (
+                Stream::Kline("ETHBTC".into(), KlineInterval::Months1),
+                "ethbtc@kline_1M",
+            ),
+            (Stream::MiniTicker("BTCUSDT".into()), "btcusdt@miniTicker"),
+            (Stream::AllMiniTickers, "!miniTicker@arr"),
+            (Stream::Ticker("BTCUSDT".into()), "btcusdt@ticker"),
+            (Stream::AllTickers, "!ticker@arr"),
+            (
+                Stream::RollingWindowTicker("BTCUSDT".into(), RollingWindow::Hours4),
+                "btcusdt@ticker_4h",
+            ),
+            (
+                Stream::AllRollingWindowTickers(RollingWindow::Days1),
+                "!ticker_1d@arr",
+            ),
+            (Stream::BookTicker("BTCUSDT".into()), "btcusdt@bookTicker"),
+            (
+                Stream::PartialDepth("BTCUSDT".into(), DepthLevels::Twenty, None),
+                "btcusdt@depth20",
+            ),
+            (
+                Stream::PartialDepth(
+                    "BTCUSDT".into(),
+                    DepthLevels::Five,
+                    Some(UpdateSpeed::Ms100),
+                ),
+                "btcusdt@depth5@100ms",
+            ),
+            (
+                Stream::DiffDepth("BTCUSDT".into(), Some(UpdateSpeed::Ms1000)),
+                "btcusdt@depth@1000ms",
+            ),
+        ];
+
+        for (stream, name) in names.iter() {
+            assert_eq!(stream.name(market).unwrap(), *name);
+        }
+    }
+
+    #[test]
+    fn futures_stream_names() {
+        let names = [
+            (
+                Stream::MarkPrice {
+                    symbol: "BTCUSDT".into(),
+                    every_second: true,
+                },
+                "btcusdt@markPrice@1s",
+            ),
+            (
+                Stream::AllMarkPrices {
+                    every_second: false,
+                },
+                "!markPrice@arr",
+            ),
+            (Stream::ForceOrder("BTCUSDT".into()), "btcusdt@forceOrder"),
+            (Stream::AllForceOrders, "!forceOrder@arr"),
+            (Stream::AllBookTickers, "!bookTicker"),
+            (
+                Stream::ContinuousKline(
+                    "BTCUSDT".into(),
+                    ContractType::CurrentQuarter,
+                    KlineInterval::Minutes5,
+                ),
+                "btcusdt_current_quarter@continuousKline_5m",
+            ),
+            (
+                Stream::DiffDepth("BTCUSDT".into(), Some(UpdateSpeed::Ms500)),
+                "btcusdt@depth@500ms",
+            ),
+            (
+                Stream::PartialDepth("BTCUSDT".into(), DepthLevels::Ten, Some(UpdateSpeed::Ms250)),
+                "btcusdt@depth10@250ms",
+            ),
+        ];
+
+        for (stream, name) in names.iter() {
+            assert_eq!(stream.name(StreamMarket::UsdM).unwrap(), *name);
+            assert_eq!(stream.name(StreamMarket::CoinM).unwrap(), *name);
+        }
+
+        assert_eq!(
+            Stream::CompositeIndex("DEFIUSDT".into())
+                .name(StreamMarket::UsdM)
+                .unwrap(),
+            "defiusdt@compositeIndex"
+        );
+    }
+
+    #[test]
+    fn unsupported_streams() {
+        let spot_only = [
+            Stream::RollingWindowTicker("BTCUSDT".into(), RollingWindow::Hours1),
+            Stream::AllRollingWindowTickers(RollingWindow::Hours1),
+            Stream::Kline("BTCUSDT".into(), KlineInterval::Seconds1),
+            Stream::DiffDepth("BTCUSDT".into(), Some(UpdateSpeed::Ms1000)),
+        ];
+        for stream in spot_only.iter() {
+            assert!(stream.name(StreamMarket::UsdM).is_err());
+            assert!(stream.name(StreamMarket::CoinM).is_err());
+        }
+
+        let futures_only = [
+            Stream::MarkPrice {
+                symbol: "BTCUSDT".into(),
+                every_second: false,
+            },
+            Stream::ForceOrder("BTCUSDT".into()),
+            Stream::AllBookTickers,
+            Stream::DiffDepth("BTCUSDT".into(), Some(UpdateSpeed::Ms250)),
+            Stream::ContinuousKline(
+                "BTCUSDT".into(),
+                ContractType::Perpetual,
+                KlineInterval::Minutes1,
+            ),
+        ];
+        for stream in futures_only.iter() {
+            assert!(stream.name(StreamMarket::Spot).is_err());
+        }
+
+        assert!(Stream::CompositeIndex("DEFIUSDT".into())
+            .name(StreamMarket::CoinM)
+            .is_err());
+    }
+
+    #[test]
+    fn stream_names_and_strings() {
+        assert_eq!(
+            "btcusdt@trade".to_stream_name(StreamMarket::Spot).unwrap(),
+            "btcusdt@trade"
+        );
+        assert_eq!(
+            String::from("!bookTicker")
+                .to_stream_name(StreamMarket::UsdM)
+                .unwrap(),
+            "!bookTicker"
+        );
+    }
+}
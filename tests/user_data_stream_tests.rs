@@ -0,0 +1,355 @@
+use binance::config::*;
+use binance::futures::websockets::*;
+use binance::reconnect::*;
+use binance::user_data_stream::*;
+use binance::websockets::*;
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::Session;
+    use mockito::Server;
+    use std::cell::RefCell;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+    use std::time::Duration;
+    use tungstenite::Message;
+
+    const LISTEN_KEY_EXPIRED: &str = r#"{"e":"listenKeyExpired","E":1576653824250}"#;
+    const AGG_TRADE: &str = r#"{"e":"aggTrade","E":123456789,"s":"BTCUSDT","a":5933014,"p":"0.001","q":"100","f":100,"l":105,"T":123456785,"m":true}"#;
+
+    fn reconnect_policy() -> ReconnectPolicy {
+        ReconnectPolicy::default()
+            .set_initial_backoff(Duration::from_millis(10))
+            .set_max_attempts(3)
+    }
+
+    fn connection_event(event: &ConnectionEvent) -> String {
+        match event {
+            ConnectionEvent::Disconnected { reason } => format!("disconnected: {}", reason),
+            ConnectionEvent::Reconnecting { .. } => "reconnecting".into(),
+            ConnectionEvent::Connected { .. } => "connected".into(),
+            ConnectionEvent::Rotated { .. } => "rotated".into(),
+        }
+    }
+
+    #[test]
+    fn recreate_expired_listen_key() {
+        let mut server = Server::new();
+        let mock_start = server
+            .mock("POST", "/api/v3/userDataStream")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .with_body(r#"{"listenKey":"spot_key"}"#)
+            .expect(2)
+            .create();
+
+        let sessions: Vec<Session> = vec![
+            Box::new(|socket| {
+                socket
+                    .send(Message::Text(LISTEN_KEY_EXPIRED.into()))
+                    .unwrap();
+            }),
+            Box::new(|socket| {
+                let execution_report = include_str!("mocks/websockets/execution_report.json");
+                socket.send(Message::Text(execution_report.into())).unwrap();
+            }),
+        ];
+        let (endpoint, paths) = common::serve("/ws", common::kept_open(sessions));
+        let config = Config::default()
+            .set_rest_api_endpoint(server.url())
+            .set_ws_endpoint(endpoint);
+
+        let keep_running = AtomicBool::new(true);
+        let log = RefCell::new(Vec::new());
+        let listen_keys = ListenKeys::new_with_config(None, UserDataMarket::Spot, &config);
+        let user_stream_config =
+            UserStreamConfig::default().set_reconnect_policy(reconnect_policy());
+        let mut user_stream =
+            UserStreamManager::new(listen_keys, user_stream_config, |event: WebsocketEvent| {
+                match event {
+                    WebsocketEvent::ListenKeyExpired(_) => log.borrow_mut().push("expired".into()),
+                    WebsocketEvent::Connection(ref event) => {
+                        log.borrow_mut().push(connection_event(event))
+                    }
+                    WebsocketEvent::OrderTrade(ref trade) => {
+                        log.borrow_mut().push(format!("order {}", trade.order_id));
+                        keep_running.store(false, Ordering::Relaxed);
+                    }
+                    _ => (),
+                }
+                Ok(())
+            });
+        user_stream.set_resync_handler(|| {
+            log.borrow_mut().push("resync".into());
+            Ok(())
+        });
+
+        user_stream.event_loop(&keep_running).unwrap();
+        assert!(user_stream.listen_key().unwrap().starts_with("spot_key"));
+        drop(user_stream);
+
+        mock_start.assert();
+        assert_eq!(
+            log.into_inner(),
+            vec![
+                "expired",
+                "disconnected: Listen key expired",
+                "reconnecting",
+                "connected",
+                "resync",
+                "order 4293153",
+            ]
+        );
+        let paths = paths.lock().unwrap();
+        assert_eq!(paths.len(), 2);
+        assert!(paths[1].starts_with("/ws/spot_key"));
+    }
+
+    #[test]
+    fn keep_listen_key_alive() {
+        let mut server = Server::new();
+        let mock_start = server
+            .mock("POST", "/fapi/v1/listenKey")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .with_body(r#"{"listenKey":"futures_key"}"#)
+            .expect(1)
+            .create();
+        let mock_keep_alive = server
+            .mock("PUT", "/fapi/v1/listenKey")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .with_body("{}")
+            .expect(2)
+            .create();
+
+        let sessions: Vec<Session> = vec![Box::new(|socket| {
+            for _ in 0..2 {
+                socket.send(Message::Text(AGG_TRADE.into())).unwrap();
+            }
+        })];
+        let (endpoint, paths) = common::serve("/ws", common::kept_open(sessions));
+        let config = Config::default()
+            .set_futures_rest_api_endpoint(server.url())
+            .set_futures_ws_endpoint(endpoint);
+
+        let keep_running = AtomicBool::new(true);
+        let mut trades = 0;
+        let listen_keys = ListenKeys::new_with_config(None, UserDataMarket::UsdM, &config);
+        let user_stream_config = UserStreamConfig::default()
+            .set_keep_alive_interval(Duration::from_secs(0))
+            .set_reconnect_policy(reconnect_policy());
+        let mut user_stream = FuturesUserStreamManager::new(
+            listen_keys,
+            user_stream_config,
+            |event: FuturesWebsocketEvent| {
+                if let FuturesWebsocketEvent::AggrTrades(_) = event {
+                    trades += 1;
+                    if trades == 2 {
+                        keep_running.store(false, Ordering::Relaxed);
+                    }
+                }
+                Ok(())
+            },
+        );
+
+        user_stream.event_loop(&keep_running).unwrap();
+        drop(user_stream);
+
+        mock_start.assert();
+        mock_keep_alive.assert();
+        assert_eq!(*paths.lock().unwrap(), vec!["/ws/futures_key"]);
+    }
+
+    #[test]
+    fn reconnect_after_keep_alive_failure() {
+        let mut server = Server::new();
+        let mock_start = server
+            .mock("POST", "/dapi/v1/listenKey")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .with_body(r#"{"listenKey":"coin_key"}"#)
+            .expect(2)
+            .create();
+        let mock_keep_alive = server
+            .mock("PUT", "/dapi/v1/listenKey")
+            .with_status(400)
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .with_body(r#"{"code":-1125,"msg":"This listenKey does not exist."}"#)
+            .expect(1)
+            .create();
+
+        let sessions: Vec<Session> = vec![
+            Box::new(|socket| {
+                socket.send(Message::Text(AGG_TRADE.into())).unwrap();
+                // The keepalive is due when the ping is received
+                thread::sleep(Duration::from_millis(300));
+                socket.send(Message::Ping(Vec::new())).unwrap();
+            }),
+            Box::new(|socket| {
+                socket.send(Message::Text(AGG_TRADE.into())).unwrap();
+            }),
+        ];
+        let (endpoint, _) = common::serve("/ws", common::kept_open(sessions));
+        let config = Config::default()
+            .set_futures_cm_rest_api_endpoint(server.url())
+            .set_futures_cm_ws_endpoint(endpoint);
+
+        let keep_running = AtomicBool::new(true);
+        let mut log = Vec::new();
+        let listen_keys = ListenKeys::new_with_config(None, UserDataMarket::CoinM, &config);
+        let user_stream_config = UserStreamConfig::default()
+            .set_keep_alive_interval(Duration::from_millis(200))
+            .set_reconnect_policy(reconnect_policy());
+        let mut user_stream = FuturesUserStreamManager::new(
+            listen_keys,
+            user_stream_config,
+            |event: FuturesWebsocketEvent| {
+                match event {
+                    FuturesWebsocketEvent::AggrTrades(_) => {
+                        log.push("trade".to_string());
+                        if log.len() > 1 {
+                            keep_running.store(false, Ordering::Relaxed);
+                        }
+                    }
+                    FuturesWebsocketEvent::Connection(ConnectionEvent::Disconnected { reason }) => {
+                        log.push(reason)
+                    }
+                    _ => (),
+                }
+                Ok(())
+            },
+        );
+
+        user_stream.event_loop(&keep_running).unwrap();
+        drop(user_stream);
+
+        mock_start.assert();
+        mock_keep_alive.assert();
+        assert_eq!(log.len(), 3);
+        assert_eq!(log[0], "trade");
+        assert!(log[1].starts_with("Keepalive failed"));
+        assert_eq!(log[2], "trade");
+    }
+
+    #[test]
+    fn rotate_connection_without_gap() {
+        let mut server = Server::new();
+        let mock_start = server
+            .mock("POST", "/fapi/v1/listenKey")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .with_body(r#"{"listenKey":"futures_key"}"#)
+            .expect(1)
+            .create();
+
+        // The first connection sends one more event once the rotation has started
+        let (rotating, rotation_started) = mpsc::channel();
+        let sessions: Vec<Session> = vec![
+            Box::new(move |socket| {
+                socket.send(Message::Text(AGG_TRADE.into())).unwrap();
+                rotation_started.recv().unwrap();
+                let late_trade = AGG_TRADE.replace("BTCUSDT", "ETHUSDT");
+                socket.send(Message::Text(late_trade)).unwrap();
+            }),
+            Box::new(move |socket| {
+                rotating.send(()).unwrap();
+                socket.send(Message::Text(AGG_TRADE.into())).unwrap();
+            }),
+        ];
+        let (endpoint, paths) = common::serve("/ws", common::kept_open(sessions));
+        let config = Config::default()
+            .set_futures_rest_api_endpoint(server.url())
+            .set_futures_ws_endpoint(endpoint);
+
+        let keep_running = AtomicBool::new(true);
+        let log = RefCell::new(Vec::new());
+        let listen_keys = ListenKeys::new_with_config(None, UserDataMarket::UsdM, &config);
+        let user_stream_config = UserStreamConfig::default()
+            .set_reconnect_policy(reconnect_policy().set_rotate_after(Duration::from_millis(200)));
+        let mut user_stream = FuturesUserStreamManager::new(
+            listen_keys,
+            user_stream_config,
+            |event: FuturesWebsocketEvent| {
+                match event {
+                    FuturesWebsocketEvent::AggrTrades(trade) => {
+                        log.borrow_mut().push(trade.symbol);
+                        if log.borrow().len() > 3 {
+                            keep_running.store(false, Ordering::Relaxed);
+                        }
+                    }
+                    FuturesWebsocketEvent::Connection(ref event) => {
+                        log.borrow_mut().push(connection_event(event))
+                    }
+                    _ => (),
+                }
+                Ok(())
+            },
+        );
+
+        user_stream.event_loop(&keep_running).unwrap();
+        drop(user_stream);
+
+        mock_start.assert();
+        assert_eq!(
+            log.into_inner(),
+            vec!["BTCUSDT", "ETHUSDT", "rotated", "BTCUSDT"]
+        );
+        assert_eq!(
+            *paths.lock().unwrap(),
+            vec!["/ws/futures_key", "/ws/futures_key"]
+        );
+    }
+
+    #[test]
+    fn keep_listen_key_alive_on_quiet_connection() {
+        let mut server = Server::new();
+        let mock_start = server
+            .mock("POST", "/api/v3/userDataStream")
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .with_body(r#"{"listenKey":"spot_key"}"#)
+            .expect(1)
+            .create();
+        let mock_keep_alive = server
+            .mock("PUT", "/api/v3/userDataStream")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json;charset=UTF-8")
+            .with_body("{}")
+            .expect_at_least(1)
+            .create();
+
+        let sessions: Vec<Session> = vec![Box::new(|_socket| ())];
+        let (endpoint, _) = common::serve("/ws", common::kept_open(sessions));
+        let config = Config::default()
+            .set_rest_api_endpoint(server.url())
+            .set_ws_endpoint(endpoint);
+
+        let keep_running = Arc::new(AtomicBool::new(true));
+        let stop = keep_running.clone();
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(1500));
+            stop.store(false, Ordering::Relaxed);
+        });
+
+        let listen_keys = ListenKeys::new_with_config(None, UserDataMarket::Spot, &config);
+        let user_stream_config = UserStreamConfig::default()
+            .set_keep_alive_interval(Duration::from_millis(100))
+            .set_reconnect_policy(reconnect_policy());
+        let mut user_stream =
+            UserStreamManager::new(listen_keys, user_stream_config, |_event| Ok(()));
+
+        user_stream.event_loop(&keep_running).unwrap();
+        drop(user_stream);
+        stopper.join().unwrap();
+
+        mock_start.assert();
+        mock_keep_alive.assert();
+    }
+
+    #[test]
+    fn market_of_manager() {
+        let listen_keys = ListenKeys::new(None, UserDataMarket::PortfolioMargin);
+        let mut user_stream =
+            UserStreamManager::new(listen_keys, UserStreamConfig::default(), |_event| Ok(()));
+        assert!(user_stream.event_loop(&AtomicBool::new(true)).is_err());
+    }
+}
@@ -41,6 +41,12 @@ mod tests {
         );
     }
 
+    #[test]
+    fn build_list_parameter() {
+        let result = binance::util::build_list_parameter(&["BTCUSDT", "BNBBTC"]).unwrap();
+        assert_eq!(result, "%5B%22BTCUSDT%22%2C%22BNBBTC%22%5D");
+    }
+
     #[test]
     fn to_i64() {
         let value_max = serde_json::json!(i64::MAX);
@@ -0,0 +1,1124 @@
+use binance::config::*;
+use binance::event_channel::*;
+use binance::futures::model::AccountUpdateReason;
+use binance::futures::websockets::*;
+use binance::heartbeat::*;
+use binance::model::{ExecutionType, OrderRejectReason, OrderStatus};
+use binance::reconnect::*;
+use binance::streams::*;
+use binance::subscription::*;
+use binance::websockets::*;
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{close, Session};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+    use tungstenite::Message;
+
+    const TRADE: &str = r#"{"e":"trade","E":1672515782136,"s":"BNBBTC","t":12345,"p":"0.001","q":"100","b":88,"a":50,"T":1672515782136,"m":true,"M":true}"#;
+
+    #[test]
+    fn reconnect_after_close() {
+        let sessions: Vec<Session> = vec![
+            Box::new(|socket| {
+                socket.send(Message::Text(TRADE.into())).unwrap();
+                close(socket);
+            }),
+            Box::new(|socket| {
+                socket.send(Message::Text(TRADE.into())).unwrap();
+                let _ = socket.read();
+            }),
+        ];
+        let (endpoint, paths) = common::serve("/ws", sessions);
+
+        let keep_running = AtomicBool::new(true);
+        let mut events = Vec::new();
+        let mut web_socket = WebSockets::new(|event: WebsocketEvent| {
+            if let WebsocketEvent::Trade(_) = event {
+                events.push("trade".to_string());
+                if events.len() > 1 {
+                    keep_running.store(false, Ordering::Relaxed);
+                }
+            } else if let WebsocketEvent::Connection(connection) = event {
+                events.push(
+                    match connection {
+                        ConnectionEvent::Disconnected { .. } => "disconnected",
+                        ConnectionEvent::Reconnecting { .. } => "reconnecting",
+                        ConnectionEvent::Connected { .. } => "connected",
+                        ConnectionEvent::Rotated { .. } => "rotated",
+                    }
+                    .to_string(),
+                );
+            }
+            Ok(())
+        });
+
+        let config = Config::default().set_ws_endpoint(endpoint);
+        web_socket
+            .connect_with_config("bnbbtc@trade", &config)
+            .unwrap();
+        let policy = ReconnectPolicy::default().set_initial_backoff(Duration::from_millis(10));
+        web_socket
+            .event_loop_with_reconnect(&keep_running, &policy)
+            .unwrap();
+        drop(web_socket);
+
+        assert_eq!(
+            events,
+            vec![
+                "trade",
+                "disconnected",
+                "reconnecting",
+                "connected",
+                "trade"
+            ]
+        );
+        assert_eq!(
+            *paths.lock().unwrap(),
+            vec!["/ws/bnbbtc@trade", "/ws/bnbbtc@trade"]
+        );
+    }
+
+    #[test]
+    fn give_up_reconnecting() {
+        let sessions: Vec<Session> = vec![Box::new(close)];
+        let (endpoint, _) = common::serve("/ws", sessions);
+
+        let keep_running = AtomicBool::new(true);
+        let mut web_socket = WebSockets::new(|_| Ok(()));
+        let config = Config::default().set_ws_endpoint(endpoint);
+        web_socket
+            .connect_with_config("bnbbtc@trade", &config)
+            .unwrap();
+
+        let policy = ReconnectPolicy::default()
+            .set_initial_backoff(Duration::from_millis(1))
+            .set_max_attempts(2);
+        assert!(web_socket
+            .event_loop_with_reconnect(&keep_running, &policy)
+            .is_err());
+    }
+
+    #[test]
+    fn dispatch_events() {
+        let mut events = Vec::new();
+        let mut web_socket = WebSockets::new(|event: WebsocketEvent| {
+            events.push(match event {
+                WebsocketEvent::DayTickerAll(tickers) => format!("tickers {}", tickers.len()),
+                WebsocketEvent::OrderBook(order_book) => {
+                    format!("order book {}", order_book.last_update_id)
+                }
+                WebsocketEvent::BookTicker(book_ticker) => {
+                    format!("book ticker {}", book_ticker.symbol)
+                }
+                WebsocketEvent::Trade(trade) => format!("trade {}", trade.trade_id),
+                WebsocketEvent::Response(response) => format!("response {:?}", response.id),
+                WebsocketEvent::Unknown(msg) => format!("unknown {}", msg),
+                other => panic!("Unexpected {:?}", other),
+            });
+            Ok(())
+        });
+
+        for fixture in [
+            include_str!("mocks/websockets/all_tickers.json"),
+            include_str!("mocks/websockets/partial_depth.json"),
+            include_str!("mocks/websockets/book_ticker.json"),
+            include_str!("mocks/websockets/combined_trade.json"),
+            r#"{"result":null,"id":1}"#,
+            r#"{"e":"newEvent","E":1672515782136}"#,
+            // Known events which can't be decoded are passed on as unknown
+            r#"{"e":"trade","E":1}"#,
+        ]
+        .iter()
+        {
+            web_socket.handle_msg(fixture).unwrap();
+        }
+        // Messages which are not JSON are errors
+        assert!(web_socket.handle_msg("not json").is_err());
+        drop(web_socket);
+
+        assert_eq!(
+            events,
+            vec![
+                "tickers 50",
+                "order book 160",
+                "book ticker BNBUSDT",
+                "trade 12345",
+                "response Some(1)",
+                r#"unknown {"e":"newEvent","E":1672515782136}"#,
+                r#"unknown {"e":"trade","E":1}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn spot_user_data_events() {
+        let mut events = Vec::new();
+        let mut web_socket = WebSockets::new(|event: WebsocketEvent| {
+            events.push(event);
+            Ok(())
+        });
+
+        for fixture in [
+            include_str!("mocks/websockets/outbound_account_position.json"),
+            include_str!("mocks/websockets/balance_update.json"),
+            include_str!("mocks/websockets/list_status.json"),
+            include_str!("mocks/websockets/external_lock_update.json"),
+            include_str!("mocks/websockets/execution_report.json"),
+            include_str!("mocks/websockets/execution_report_prevented.json"),
+            r#"{"e":"eventStreamTerminated","E":1728973001334}"#,
+        ]
+        .iter()
+        {
+            web_socket.handle_msg(fixture).unwrap();
+        }
+        drop(web_socket);
+
+        assert_eq!(events.len(), 7);
+        match &events[0] {
+            WebsocketEvent::AccountPosition(position) => {
+                assert_eq!(position.last_account_update_time, 1564034571073);
+                assert_eq!(position.balances[0].asset, "ETH");
+                assert_eq!(position.balances[0].free, "10000.000000");
+                assert_eq!(position.balances[0].locked, "0.000000");
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+        match &events[1] {
+            WebsocketEvent::BalanceUpdate(update) => {
+                assert_eq!(update.asset, "BTC");
+                assert_eq!(update.balance_delta, "100.00000000");
+                assert_eq!(update.clear_time, 1573200697068);
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+        match &events[2] {
+            WebsocketEvent::ListStatus(list) => {
+                assert_eq!(list.contingency_type, "OCO");
+                assert_eq!(list.list_order_status, "EXECUTING");
+                assert_eq!(list.orders.len(), 2);
+                assert_eq!(list.orders[1].order_id, 18);
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+        match &events[3] {
+            WebsocketEvent::ExternalLockUpdate(update) => {
+                assert_eq!(update.asset, "NEO");
+                assert_eq!(update.delta, "10.00000000");
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+        match &events[4] {
+            WebsocketEvent::OrderTrade(trade) => {
+                assert_eq!(trade.execution_type, ExecutionType::New);
+                assert_eq!(trade.order_status, OrderStatus::New);
+                assert_eq!(trade.order_reject_reason, OrderRejectReason::None);
+                assert_eq!(trade.working_time, Some(1499405658657));
+                assert_eq!(trade.self_trade_prevention_mode, Some("NONE".into()));
+                assert_eq!(trade.prevented_match_id, None);
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+        match &events[5] {
+            WebsocketEvent::OrderTrade(trade) => {
+                assert_eq!(trade.execution_type, ExecutionType::TradePrevention);
+                assert_eq!(trade.order_status, OrderStatus::Expired);
+                assert_eq!(trade.order_reject_reason, OrderRejectReason::Unknown);
+                assert_eq!(trade.prevented_match_id, Some(3));
+                assert_eq!(trade.prevented_quantity, Some("1.00000000".into()));
+                assert_eq!(trade.counter_order_id, Some(37));
+                assert_eq!(trade.working_time, None);
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+        match &events[6] {
+            WebsocketEvent::EventStreamTerminated(event) => {
+                assert_eq!(event.event_time, 1728973001334)
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn futures_dispatch_events() {
+        let mut events = Vec::new();
+        let mut web_socket = FuturesWebSockets::new(|event: FuturesWebsocketEvent| {
+            events.push(match event {
+                FuturesWebsocketEvent::DayTickerAll(tickers) => {
+                    format!("tickers {}", tickers.len())
+                }
+                FuturesWebsocketEvent::Trade(trade) => format!("trade {}", trade.trade_id),
+                FuturesWebsocketEvent::UserDataStreamExpiredEvent(event) => {
+                    format!("expired {}", event.event_time)
+                }
+                FuturesWebsocketEvent::Unknown(_) => "unknown".to_string(),
+                other => panic!("Unexpected {:?}", other),
+            });
+            Ok(())
+        });
+
+        for fixture in [
+            include_str!("mocks/websockets/all_tickers.json"),
+            include_str!("mocks/websockets/combined_trade.json"),
+            r#"{"e":"listenKeyExpired","E":1576653824250}"#,
+            "[]",
+        ]
+        .iter()
+        {
+            web_socket.handle_msg(fixture).unwrap();
+        }
+        drop(web_socket);
+
+        assert_eq!(
+            events,
+            vec![
+                "tickers 50",
+                "trade 12345",
+                "expired 1576653824250",
+                "unknown"
+            ]
+        );
+    }
+
+    #[test]
+    fn futures_user_data_events() {
+        let mut events = Vec::new();
+        let mut web_socket = FuturesWebSockets::new(|event: FuturesWebsocketEvent| {
+            events.push(match event {
+                FuturesWebsocketEvent::AccountUpdate(update) => {
+                    assert_eq!(update.data.reason, AccountUpdateReason::FundingFee);
+                    format!("account {}", update.data.positions[0].position_side)
+                }
+                FuturesWebsocketEvent::MarginCall(margin_call) => format!(
+                    "margin call {} {}",
+                    margin_call.positions[0].symbol,
+                    margin_call.positions[0].maintenance_margin_required
+                ),
+                FuturesWebsocketEvent::AccountConfigUpdate(update) => {
+                    match (update.leverage, update.multi_assets) {
+                        (Some(leverage), None) => {
+                            format!("leverage {} {}", leverage.symbol, leverage.leverage)
+                        }
+                        (None, Some(multi_assets)) => {
+                            format!("multi-assets {}", multi_assets.multi_assets_margin)
+                        }
+                        other => panic!("Unexpected {:?}", other),
+                    }
+                }
+                FuturesWebsocketEvent::StrategyUpdate(update) => format!(
+                    "strategy {} {}",
+                    update.strategy.strategy_id, update.strategy.op_code
+                ),
+                FuturesWebsocketEvent::GridUpdate(update) => {
+                    format!("grid {}", update.grid.realized_pnl)
+                }
+                FuturesWebsocketEvent::ConditionalOrderTriggerReject(reject) => {
+                    format!("rejected {}", reject.order.order_id)
+                }
+                FuturesWebsocketEvent::TradeLite(trade) => {
+                    format!("trade {} {}", trade.trade_id, trade.last_filled_qty)
+                }
+                other => panic!("Unexpected {:?}", other),
+            });
+            Ok(())
+        });
+
+        for fixture in [
+            include_str!("mocks/futures/websockets/account_update.json"),
+            include_str!("mocks/futures/websockets/margin_call.json"),
+            include_str!("mocks/futures/websockets/account_config_update.json"),
+            include_str!("mocks/futures/websockets/strategy_update.json"),
+            include_str!("mocks/futures/websockets/grid_update.json"),
+            include_str!("mocks/futures/websockets/conditional_order_trigger_reject.json"),
+            include_str!("mocks/futures/websockets/trade_lite.json"),
+            r#"{"e":"ACCOUNT_CONFIG_UPDATE","E":1611646737479,"T":1611646737476,"ai":{"j":true}}"#,
+        ]
+        .iter()
+        {
+            web_socket.handle_msg(fixture).unwrap();
+        }
+        drop(web_socket);
+
+        assert_eq!(
+            events,
+            vec![
+                "account LONG",
+                "margin call ETHUSDT 1.614445",
+                "leverage BTCUSDT 25",
+                "strategy 176054594 8007",
+                "grid -0.00300716",
+                "rejected 155618472834",
+                "trade 109100866 0.040",
+                "multi-assets true",
+            ]
+        );
+    }
+
+    #[test]
+    fn portfolio_margin_margin_events() {
+        let mut events = Vec::new();
+        let mut web_socket = FuturesWebSockets::new(|event: FuturesWebsocketEvent| {
+            events.push(match event {
+                FuturesWebsocketEvent::MarginOrderTrade(trade) => {
+                    format!("order {}", trade.order_id)
+                }
+                FuturesWebsocketEvent::MarginAccountPosition(position) => {
+                    format!("position {}", position.balances[0].asset)
+                }
+                FuturesWebsocketEvent::MarginBalanceUpdate(update) => {
+                    format!("balance {} {}", update.asset, update.balance_delta)
+                }
+                other => panic!("Unexpected {:?}", other),
+            });
+            Ok(())
+        });
+
+        for fixture in [
+            include_str!("mocks/websockets/execution_report.json"),
+            include_str!("mocks/websockets/outbound_account_position.json"),
+            include_str!("mocks/websockets/balance_update.json"),
+        ]
+        .iter()
+        {
+            web_socket.handle_msg(fixture).unwrap();
+        }
+        drop(web_socket);
+
+        assert_eq!(
+            events,
+            vec!["order 4293153", "position ETH", "balance BTC 100.00000000"]
+        );
+    }
+
+    #[test]
+    fn event_envelopes() {
+        let sessions: Vec<Session> = vec![Box::new(|socket| {
+            socket
+                .send(Message::Text(
+                    include_str!("mocks/websockets/partial_depth.json").into(),
+                ))
+                .unwrap();
+            socket.send(Message::Text(TRADE.into())).unwrap();
+            close(socket);
+        })];
+        let (endpoint, _) = common::serve("/ws", sessions);
+
+        let keep_running = AtomicBool::new(true);
+        let mut envelopes = Vec::new();
+        let mut web_socket = WebSockets::new_with_envelope(|envelope| {
+            envelopes.push(envelope);
+            if envelopes.len() == 2 {
+                keep_running.store(false, Ordering::Relaxed);
+            }
+            Ok(())
+        });
+        let config = Config::default().set_ws_endpoint(endpoint);
+        web_socket
+            .connect_with_config("bnbbtc@depth10@100ms/bnbbtc@trade", &config)
+            .unwrap();
+        web_socket.event_loop(&keep_running).unwrap();
+        drop(web_socket);
+
+        assert_eq!(envelopes[0].stream.as_deref(), Some("bnbbtc@depth10@100ms"));
+        assert!(matches!(envelopes[0].event, WebsocketEvent::OrderBook(_)));
+        // Raw stream messages don't have a stream name
+        assert_eq!(envelopes[1].stream, None);
+        assert!(matches!(envelopes[1].event, WebsocketEvent::Trade(_)));
+
+        assert_ne!(envelopes[0].connection_id, 0);
+        assert_eq!(envelopes[0].connection_id, envelopes[1].connection_id);
+        assert!(envelopes[0].received_at > 0);
+        assert!(envelopes[0].received_at <= envelopes[1].received_at);
+    }
+
+    fn trade(id: u64) -> String {
+        TRADE.replace("12345", &id.to_string())
+    }
+
+    // Sends trades 1 to 'count' then closes the connection
+    fn send_trades(count: u64) -> String {
+        let sessions: Vec<Session> = vec![Box::new(move |socket| {
+            for id in 1..=count {
+                socket.send(Message::Text(trade(id))).unwrap();
+            }
+            close(socket);
+        })];
+        common::serve("/ws", sessions).0
+    }
+
+    fn trade_ids(events: Vec<EventEnvelope<WebsocketEvent>>) -> Vec<u64> {
+        events
+            .into_iter()
+            .filter_map(|envelope| match envelope.event {
+                WebsocketEvent::Trade(trade) => Some(trade.trade_id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reader_thread_blocks_when_full() {
+        let config = Config::default().set_ws_endpoint(send_trades(5));
+        let (events, reader) = WebSockets::spawn_reader(
+            ChannelConfig::default().set_capacity(2),
+            move |web_socket| web_socket.connect_with_config("bnbbtc@trade", &config),
+        );
+
+        let mut received = Vec::new();
+        while let Some(envelope) = events.recv() {
+            thread::sleep(Duration::from_millis(5));
+            received.push(envelope);
+        }
+        // Stopped by the close frame
+        assert!(reader.join().is_err());
+
+        assert_eq!(trade_ids(received), vec![1, 2, 3, 4, 5]);
+        let metrics = events.metrics();
+        assert_eq!(metrics.sent, 5);
+        assert_eq!(metrics.dropped, 0);
+        assert_eq!(metrics.depth, 0);
+        assert!(metrics.max_depth <= 2);
+    }
+
+    #[test]
+    fn reader_thread_drops_oldest() {
+        let config = Config::default().set_ws_endpoint(send_trades(5));
+        let (events, reader) = WebSockets::spawn_reader(
+            ChannelConfig::default()
+                .set_capacity(2)
+                .set_overflow_policy(OverflowPolicy::DropOldest),
+            move |web_socket| web_socket.connect_with_config("bnbbtc@trade", &config),
+        );
+        assert!(reader.join().is_err());
+
+        let metrics = events.metrics();
+        assert_eq!(metrics.depth, 2);
+        assert_eq!(metrics.max_depth, 2);
+        assert_eq!(metrics.dropped, 3);
+        assert!(events.is_closed());
+        assert_eq!(trade_ids(events.collect()), vec![4, 5]);
+    }
+
+    #[test]
+    fn reader_thread_disconnects_when_full() {
+        let config = Config::default().set_ws_endpoint(send_trades(3));
+        let (events, reader) = WebSockets::spawn_reader(
+            ChannelConfig::default()
+                .set_capacity(1)
+                .set_overflow_policy(OverflowPolicy::Disconnect),
+            move |web_socket| web_socket.connect_with_config("bnbbtc@trade", &config),
+        );
+
+        let error = reader.join().unwrap_err();
+        assert!(error.to_string().contains("Event queue is full"));
+        assert_eq!(trade_ids(events.collect()), vec![1]);
+    }
+
+    #[test]
+    fn reader_thread_with_zero_capacity() {
+        let config = Config::default().set_ws_endpoint(send_trades(3));
+        let channel_config = ChannelConfig {
+            capacity: 0,
+            overflow_policy: OverflowPolicy::DropOldest,
+            reconnect_policy: None,
+        };
+        let (events, reader) = WebSockets::spawn_reader(channel_config, move |web_socket| {
+            web_socket.connect_with_config("bnbbtc@trade", &config)
+        });
+        assert!(reader.join().is_err());
+
+        // Queues hold at least one event
+        assert_eq!(events.metrics().capacity, 1);
+        assert_eq!(trade_ids(events.collect()), vec![3]);
+    }
+
+    #[test]
+    fn reader_thread_stops_on_quiet_connection() {
+        let sessions: Vec<Session> = vec![Box::new(|socket| {
+            let _ = socket.read();
+        })];
+        let config = Config::default().set_ws_endpoint(common::serve("/ws", sessions).0);
+        let (events, reader) =
+            WebSockets::spawn_reader(ChannelConfig::default(), move |web_socket| {
+                web_socket.connect_with_config("bnbbtc@trade", &config)
+            });
+        assert!(events.recv_timeout(Duration::from_millis(50)).is_none());
+
+        let started = std::time::Instant::now();
+        reader.stop().unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn reader_thread_shared_by_workers() {
+        let config = Config::default().set_ws_endpoint(send_trades(20));
+        let (events, reader) = WebSockets::spawn_reader(
+            ChannelConfig::default().set_capacity(4),
+            move |web_socket| web_socket.connect_with_config("bnbbtc@trade", &config),
+        );
+
+        let workers: Vec<_> = (0..3)
+            .map(|_| {
+                let events = events.clone();
+                thread::spawn(move || trade_ids(events.collect()))
+            })
+            .collect();
+        drop(events);
+
+        let mut received: Vec<u64> = workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect();
+        received.sort_unstable();
+        assert_eq!(received, (1..=20).collect::<Vec<u64>>());
+        assert!(reader.join().is_err());
+    }
+
+    #[test]
+    fn connect_typed_stream() {
+        let sessions: Vec<Session> = vec![Box::new(close)];
+        let (endpoint, paths) = common::serve("/ws", sessions);
+
+        let mut web_socket = WebSockets::new(|_| Ok(()));
+        let config = Config::default().set_ws_endpoint(endpoint);
+        web_socket
+            .connect_with_config(
+                &Stream::Kline("BTCUSDT".into(), KlineInterval::Minutes1),
+                &config,
+            )
+            .unwrap();
+        web_socket.disconnect().unwrap();
+
+        assert_eq!(*paths.lock().unwrap(), vec!["/ws/btcusdt@kline_1m"]);
+        assert!(web_socket
+            .connect(&Stream::MarkPrice {
+                symbol: "BTCUSDT".into(),
+                every_second: true
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn futures_rotate_connection() {
+        let sessions: Vec<Session> = vec![
+            Box::new(|socket| {
+                socket.send(Message::Text(TRADE.into())).unwrap();
+                thread::sleep(Duration::from_millis(100));
+                socket.send(Message::Text(TRADE.into())).unwrap();
+                let _ = socket.read();
+            }),
+            Box::new(|socket| {
+                socket.send(Message::Text(TRADE.into())).unwrap();
+                let _ = socket.read();
+            }),
+        ];
+        let (endpoint, paths) = common::serve("/ws", sessions);
+        let config = Config::default().set_futures_ws_endpoint(endpoint);
+
+        let keep_running = AtomicBool::new(true);
+        let mut events = Vec::new();
+        let mut web_socket = FuturesWebSockets::new(|event: FuturesWebsocketEvent| {
+            match event {
+                FuturesWebsocketEvent::Trade(_) => events.push("trade"),
+                FuturesWebsocketEvent::Connection(ConnectionEvent::Rotated { .. }) => {
+                    events.push("rotated")
+                }
+                _ => events.push("other"),
+            }
+            if events.len() == 4 {
+                keep_running.store(false, Ordering::Relaxed);
+            }
+            Ok(())
+        });
+
+        web_socket
+            .connect_with_config(&FuturesMarket::USDM, "bnbbtc@trade", &config)
+            .unwrap();
+        let policy = ReconnectPolicy::default().set_rotate_after(Duration::from_millis(50));
+        web_socket
+            .event_loop_with_reconnect(&keep_running, &policy)
+            .unwrap();
+        drop(web_socket);
+
+        assert_eq!(events, vec!["trade", "trade", "rotated", "trade"]);
+        assert_eq!(
+            *paths.lock().unwrap(),
+            vec!["/ws/bnbbtc@trade", "/ws/bnbbtc@trade"]
+        );
+    }
+
+    #[test]
+    fn endpoints_of_config() {
+        let sessions: Vec<Session> = vec![Box::new(close), Box::new(close), Box::new(close)];
+        let (endpoint, paths) = common::serve("/ws", sessions);
+        let config = Config::default()
+            .set_ws_endpoint(endpoint.clone())
+            .set_futures_cm_ws_endpoint(endpoint.clone())
+            .set_options_ws_endpoint(format!("{}/", endpoint));
+
+        let mut web_socket = WebSockets::new(|_| Ok(()));
+        web_socket
+            .connect_multiple_streams_with_config(&["bnbbtc@trade", "ethbtc@trade"], &config)
+            .unwrap();
+        web_socket.disconnect().unwrap();
+        let mut web_socket = FuturesWebSockets::new(|_| Ok(()));
+        web_socket
+            .connect_with_config(&FuturesMarket::COINM, "btcusd_perp@aggTrade", &config)
+            .unwrap();
+        web_socket.disconnect().unwrap();
+        web_socket
+            .connect_multiple_streams_with_config(
+                &FuturesMarket::Vanilla,
+                &["btc-240628-60000-c@trade"],
+                &config,
+            )
+            .unwrap();
+        web_socket.disconnect().unwrap();
+
+        assert_eq!(
+            *paths.lock().unwrap(),
+            vec![
+                "/stream?streams=bnbbtc@trade/ethbtc@trade",
+                "/ws/btcusd_perp@aggTrade",
+                "/stream?streams=btc-240628-60000-c@trade",
+            ]
+        );
+    }
+
+    #[test]
+    fn testnet_and_market_data_endpoints() {
+        let config = Config::testnet();
+        for endpoint in [
+            &config.ws_endpoint,
+            &config.ws_api_endpoint,
+            &config.futures_ws_endpoint,
+            &config.futures_cm_ws_endpoint,
+            &config.futures_ws_api_endpoint,
+        ]
+        .iter()
+        {
+            assert!(endpoint.starts_with("wss://"), "{}", endpoint);
+        }
+
+        let config = Config::market_data_only();
+        assert_eq!(config.rest_api_endpoint, "https://data-api.binance.vision");
+        assert_eq!(config.ws_endpoint, "wss://data-stream.binance.vision/ws");
+    }
+
+    // Answers the next request with the given result or error
+    fn answer(
+        socket: &mut tungstenite::WebSocket<std::net::TcpStream>, answer: &str,
+    ) -> serde_json::Value {
+        let request: serde_json::Value = match socket.read().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("Unexpected {:?}", other),
+        };
+        let response = format!(r#"{{{},"id":{}}}"#, answer, request["id"]);
+        socket.send(Message::Text(response)).unwrap();
+        request
+    }
+
+    #[test]
+    fn subscribe_on_open_connection() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let server_requests = requests.clone();
+        let sessions: Vec<Session> = vec![Box::new(move |socket| {
+            let mut requests = server_requests.lock().unwrap();
+            requests.push(answer(socket, r#""result":null"#));
+            requests.push(answer(
+                socket,
+                r#""result":["bnbbtc@trade","ethbtc@trade"]"#,
+            ));
+            requests.push(answer(
+                socket,
+                r#""error":{"code":2,"msg":"Invalid request: unknown stream"}"#,
+            ));
+            drop(requests);
+            let _ = socket.read();
+        })];
+        let (endpoint, _) = common::serve("/ws", sessions);
+
+        let keep_running = AtomicBool::new(true);
+        let mut responses = Vec::new();
+        let mut web_socket = WebSockets::new(|event: WebsocketEvent| {
+            if let WebsocketEvent::Response(response) = event {
+                responses.push(response);
+                if responses.len() == 3 {
+                    keep_running.store(false, Ordering::Relaxed);
+                }
+            }
+            Ok(())
+        });
+
+        let config = Config::default().set_ws_endpoint(endpoint);
+        web_socket
+            .connect_with_config("bnbbtc@trade", &config)
+            .unwrap();
+        assert_eq!(web_socket.subscribe(&["ethbtc@trade".into()]).unwrap(), 1);
+        assert_eq!(web_socket.list_subscriptions().unwrap(), 2);
+        // Already subscribed to ethbtc@trade
+        assert_eq!(
+            web_socket
+                .subscribe(&["ethbtc@trade".into(), "unknown".into()])
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            web_socket.subscriptions(),
+            vec!["bnbbtc@trade", "ethbtc@trade", "unknown"]
+        );
+
+        web_socket.event_loop(&keep_running).unwrap();
+        // The failed subscription is rolled back, without the stream subscribed before it
+        assert_eq!(
+            web_socket.subscriptions(),
+            vec!["bnbbtc@trade", "ethbtc@trade"]
+        );
+        drop(web_socket);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0]["method"], "SUBSCRIBE");
+        assert_eq!(requests[0]["params"], serde_json::json!(["ethbtc@trade"]));
+        assert_eq!(requests[1]["method"], "LIST_SUBSCRIPTIONS");
+
+        assert!(!responses[0].is_error());
+        assert_eq!(responses[0].request.as_ref().unwrap().method, "SUBSCRIBE");
+        assert_eq!(responses[1].streams(), vec!["bnbbtc@trade", "ethbtc@trade"]);
+        assert!(responses[2].is_error());
+        assert_eq!(responses[2].id, Some(3));
+    }
+
+    #[test]
+    fn subscription_limit() {
+        let sessions: Vec<Session> = vec![Box::new(|socket| {
+            let _ = socket.read();
+        })];
+        let (endpoint, _) = common::serve("/ws", sessions);
+
+        let mut web_socket = WebSockets::new(|_| Ok(()));
+        let config = Config::default().set_ws_endpoint(endpoint);
+        web_socket
+            .connect_with_config("bnbbtc@trade", &config)
+            .unwrap();
+
+        let streams: Vec<String> = (0..MAX_STREAMS)
+            .map(|i| format!("symbol{}@trade", i))
+            .collect();
+        assert!(web_socket.subscribe(&streams).is_err());
+        assert_eq!(web_socket.subscriptions(), vec!["bnbbtc@trade"]);
+    }
+
+    #[test]
+    fn subscribe_without_connection() {
+        let mut web_socket = WebSockets::new(|_| Ok(()));
+
+        assert!(web_socket.subscribe(&["ethbtc@trade".into()]).is_err());
+        assert!(web_socket.subscriptions().is_empty());
+    }
+
+    #[test]
+    fn requests_over_rate_limit_are_queued() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let server_requests = requests.clone();
+        let sessions: Vec<Session> = vec![Box::new(move |socket| {
+            // Held until the answers are recorded, the client stops once it has them all
+            let mut requests = server_requests.lock().unwrap();
+            for _ in 0..7 {
+                requests.push(answer(socket, r#""result":null"#));
+            }
+            drop(requests);
+            let _ = socket.read();
+        })];
+        let (endpoint, _) = common::serve("/ws", sessions);
+
+        let keep_running = AtomicBool::new(true);
+        let mut responses = 0;
+        let mut web_socket = WebSockets::new(|event: WebsocketEvent| {
+            if let WebsocketEvent::Response(_) = event {
+                responses += 1;
+                if responses == 7 {
+                    keep_running.store(false, Ordering::Relaxed);
+                }
+            }
+            Ok(())
+        });
+
+        let config = Config::default().set_ws_endpoint(endpoint);
+        web_socket
+            .connect_with_config("bnbbtc@trade", &config)
+            .unwrap();
+        web_socket
+            .set_heartbeat(HeartbeatConfig::default().set_read_timeout(Duration::from_millis(50)))
+            .unwrap();
+
+        // Spot connections accept 5 messages per second, the others wait without blocking
+        let started = std::time::Instant::now();
+        for i in 0..7 {
+            web_socket
+                .subscribe(&[format!("symbol{}@trade", i)])
+                .unwrap();
+        }
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert_eq!(web_socket.subscriptions().len(), 8);
+
+        web_socket.event_loop(&keep_running).unwrap();
+        drop(web_socket);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 7);
+        assert_eq!(requests[6]["params"], serde_json::json!(["symbol6@trade"]));
+    }
+
+    #[test]
+    fn restore_subscriptions_after_reconnect() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let first_requests = requests.clone();
+        let second_requests = requests.clone();
+        let sessions: Vec<Session> = vec![
+            Box::new(move |socket| {
+                let mut requests = first_requests.lock().unwrap();
+                requests.push(answer(socket, r#""result":null"#));
+                drop(requests);
+                close(socket);
+            }),
+            Box::new(move |socket| {
+                // Held until the answer is recorded, the client stops once it is received
+                let mut requests = second_requests.lock().unwrap();
+                requests.push(answer(socket, r#""result":null"#));
+                drop(requests);
+                let _ = socket.read();
+            }),
+        ];
+        let (endpoint, _) = common::serve("/ws", sessions);
+
+        let keep_running = AtomicBool::new(true);
+        let mut responses = 0;
+        let mut web_socket = WebSockets::new(|event: WebsocketEvent| {
+            if let WebsocketEvent::Response(_) = event {
+                responses += 1;
+                if responses == 2 {
+                    keep_running.store(false, Ordering::Relaxed);
+                }
+            }
+            Ok(())
+        });
+
+        let config = Config::default().set_ws_endpoint(endpoint);
+        web_socket
+            .connect_with_config("bnbbtc@trade", &config)
+            .unwrap();
+        web_socket.subscribe(&["ethbtc@trade".into()]).unwrap();
+
+        let policy = ReconnectPolicy::default().set_initial_backoff(Duration::from_millis(10));
+        web_socket
+            .event_loop_with_reconnect(&keep_running, &policy)
+            .unwrap();
+        drop(web_socket);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1]["method"], "SUBSCRIBE");
+        assert_eq!(requests[1]["params"], serde_json::json!(["ethbtc@trade"]));
+    }
+
+    // A trade on a connection of combined streams
+    fn combined_trade(stream: &str, symbol: &str) -> String {
+        format!(
+            r#"{{"stream":"{}","data":{}}}"#,
+            stream,
+            TRADE.replace("BNBBTC", symbol)
+        )
+    }
+
+    #[test]
+    fn pong_to_server_ping() {
+        let pongs = Arc::new(AtomicUsize::new(0));
+        let server_pongs = pongs.clone();
+        let sessions: Vec<Session> = vec![Box::new(move |socket| {
+            socket.send(Message::Ping(Vec::new())).unwrap();
+            while let Ok(message) = socket.read() {
+                if let Message::Pong(_) = message {
+                    server_pongs.fetch_add(1, Ordering::Relaxed);
+                    break;
+                }
+            }
+            socket.send(Message::Text(TRADE.into())).unwrap();
+        })];
+        let (endpoint, _) = common::serve("/ws", common::kept_open(sessions));
+
+        let keep_running = AtomicBool::new(true);
+        let mut web_socket = WebSockets::new(|_event: WebsocketEvent| {
+            keep_running.store(false, Ordering::Relaxed);
+            Ok(())
+        });
+        web_socket
+            .connect_with_config("bnbbtc@trade", &Config::default().set_ws_endpoint(endpoint))
+            .unwrap();
+        web_socket.event_loop(&keep_running).unwrap();
+        drop(web_socket);
+
+        assert_eq!(pongs.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn idle_timeout() {
+        let sessions: Vec<Session> = vec![
+            Box::new(|socket| socket.send(Message::Text(TRADE.into())).unwrap()),
+            Box::new(|socket| socket.send(Message::Text(TRADE.into())).unwrap()),
+            Box::new(|_| ()),
+        ];
+        let (endpoint, _) = common::serve("/ws", common::kept_open(sessions));
+        let config = Config::default()
+            .set_ws_endpoint(endpoint.clone())
+            .set_futures_ws_endpoint(endpoint);
+        let heartbeat = HeartbeatConfig::default().set_idle_timeout(Duration::from_millis(100));
+
+        let keep_running = AtomicBool::new(true);
+        let mut web_socket = WebSockets::new(|_event: WebsocketEvent| Ok(()));
+        web_socket
+            .connect_with_config("bnbbtc@trade", &config)
+            .unwrap();
+        web_socket.set_heartbeat(heartbeat.clone()).unwrap();
+        let error = web_socket.event_loop(&keep_running).unwrap_err();
+        assert!(error.to_string().contains("No message received"));
+        drop(web_socket);
+
+        let mut events = Vec::new();
+        let mut web_socket = FuturesWebSockets::new(|event: FuturesWebsocketEvent| {
+            match event {
+                FuturesWebsocketEvent::Trade(_) => events.push("trade"),
+                FuturesWebsocketEvent::Connection(ConnectionEvent::Disconnected { .. }) => {
+                    events.push("disconnected")
+                }
+                FuturesWebsocketEvent::Connection(ConnectionEvent::Connected { .. }) => {
+                    keep_running.store(false, Ordering::Relaxed)
+                }
+                _ => (),
+            }
+            Ok(())
+        });
+        web_socket.set_heartbeat(heartbeat).unwrap();
+        web_socket
+            .connect_with_config(&FuturesMarket::USDM, "bnbbtc@trade", &config)
+            .unwrap();
+        let policy = ReconnectPolicy::default().set_initial_backoff(Duration::from_millis(10));
+        web_socket
+            .event_loop_with_reconnect(&keep_running, &policy)
+            .unwrap();
+        drop(web_socket);
+
+        assert_eq!(events, vec!["trade", "disconnected"]);
+    }
+
+    #[test]
+    fn zero_read_timeout() {
+        let sessions: Vec<Session> = vec![Box::new(|socket| {
+            socket.send(Message::Text(TRADE.into())).unwrap()
+        })];
+        let (endpoint, _) = common::serve("/ws", common::kept_open(sessions));
+
+        let keep_running = AtomicBool::new(true);
+        let mut web_socket = WebSockets::new(|_event: WebsocketEvent| {
+            keep_running.store(false, Ordering::Relaxed);
+            Ok(())
+        });
+        web_socket
+            .connect_with_config("bnbbtc@trade", &Config::default().set_ws_endpoint(endpoint))
+            .unwrap();
+        web_socket
+            .set_heartbeat(HeartbeatConfig::default().set_read_timeout(Duration::from_secs(0)))
+            .unwrap();
+        web_socket.event_loop(&keep_running).unwrap();
+    }
+
+    #[test]
+    fn ping_round_trip() {
+        let sessions: Vec<Session> = vec![Box::new(|socket| {
+            socket.send(Message::Text(TRADE.into())).unwrap();
+            common::answer_pings(socket, Duration::from_millis(200));
+            socket.send(Message::Text(TRADE.into())).unwrap();
+        })];
+        let (endpoint, _) = common::serve("/ws", common::kept_open(sessions));
+
+        let keep_running = AtomicBool::new(true);
+        let mut trades = 0;
+        let mut web_socket = WebSockets::new(|event: WebsocketEvent| {
+            if let WebsocketEvent::Trade(_) = event {
+                trades += 1;
+                if trades == 2 {
+                    keep_running.store(false, Ordering::Relaxed);
+                }
+            }
+            Ok(())
+        });
+        web_socket
+            .set_heartbeat(HeartbeatConfig::default().set_ping_interval(Duration::from_millis(20)))
+            .unwrap();
+        web_socket
+            .connect_with_config("bnbbtc@trade", &Config::default().set_ws_endpoint(endpoint))
+            .unwrap();
+        web_socket.event_loop(&keep_running).unwrap();
+        let stats = web_socket.monitor().stats();
+        drop(web_socket);
+
+        assert!(stats.pings_sent >= 1);
+        assert!(stats.pongs_received >= 1);
+        assert!(stats.ping_round_trip.unwrap() < Duration::from_millis(200));
+        assert!(stats.last_received_at.is_some());
+        // The stream of a raw stream connection
+        assert_eq!(stats.latency["bnbbtc@trade"].count, 2);
+    }
+
+    #[test]
+    fn stream_latency() {
+        let sessions: Vec<Session> = vec![Box::new(|socket| {
+            for (stream, symbol) in [
+                ("bnbbtc@trade", "BNBBTC"),
+                ("ethbtc@trade", "ETHBTC"),
+                ("bnbbtc@trade", "BNBBTC"),
+            ]
+            .iter()
+            {
+                let trade = combined_trade(stream, symbol);
+                socket.send(Message::Text(trade)).unwrap();
+            }
+        })];
+        let (endpoint, _) = common::serve("/ws", common::kept_open(sessions));
+
+        let keep_running = AtomicBool::new(true);
+        let mut trades = 0;
+        let mut web_socket = WebSockets::new(|event: WebsocketEvent| {
+            if let WebsocketEvent::Trade(_) = event {
+                trades += 1;
+                if trades == 3 {
+                    keep_running.store(false, Ordering::Relaxed);
+                }
+            }
+            Ok(())
+        });
+        web_socket
+            .connect_multiple_streams_with_config(
+                &["bnbbtc@trade", "ethbtc@trade"],
+                &Config::default().set_ws_endpoint(endpoint),
+            )
+            .unwrap();
+        let monitor = web_socket.monitor();
+        web_socket.event_loop(&keep_running).unwrap();
+        drop(web_socket);
+
+        let latency = monitor.latency("bnbbtc@trade").unwrap();
+        assert_eq!(latency.count, 2);
+        // Events were sent on 2023-01-01
+        assert!(latency.min > 0);
+        assert!(latency.min <= latency.max);
+        assert_eq!(monitor.latency("ethbtc@trade").unwrap().count, 1);
+        assert!(!monitor.is_stale("bnbbtc@trade", Duration::from_secs(60)));
+        assert!(monitor.is_stale("btcusdt@trade", Duration::from_secs(60)));
+    }
+}
@@ -0,0 +1,334 @@
+use binance::account::*;
+use binance::api::*;
+use binance::config::*;
+use binance::errors::ErrorKind as BinanceLibErrorKind;
+use binance::websockets::*;
+use binance::ws_api::*;
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hmac::{Hmac, Mac};
+    use serde_json::{json, Value};
+    use sha2::Sha256;
+    use std::net::TcpStream;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use tungstenite::{Message, WebSocket};
+
+    const SECRET_KEY: &str = "secret";
+
+    // Runs the session on the first connection, returns the endpoint
+    fn serve<F>(session: F) -> String
+    where
+        F: FnOnce(&mut WebSocket<TcpStream>) + Send + 'static,
+    {
+        common::serve("/ws-api/v3", common::kept_open(vec![Box::new(session)])).0
+    }
+
+    fn read_request(socket: &mut WebSocket<TcpStream>) -> Value {
+        match socket.read().unwrap() {
+            Message::Text(msg) => serde_json::from_str(&msg).unwrap(),
+            message => panic!("Unexpected message {:?}", message),
+        }
+    }
+
+    fn answer(socket: &mut WebSocket<TcpStream>, response: Value) {
+        socket.send(Message::Text(response.to_string())).unwrap();
+    }
+
+    // Payload signed by the client, parameters sorted by name without the signature
+    fn payload(params: &Value) -> String {
+        params
+            .as_object()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| *key != "signature")
+            .map(|(key, value)| match value {
+                Value::String(value) => format!("{}={}", key, value),
+                value => format!("{}={}", key, value),
+            })
+            .collect::<Vec<String>>()
+            .join("&")
+    }
+
+    fn logon_result() -> Value {
+        json!({
+            "apiKey": "api_key",
+            "authorizedSince": 1649729878532u64,
+            "connectedSince": 1649729873021u64,
+            "returnRateLimits": false,
+            "serverTime": 1649729878630u64
+        })
+    }
+
+    fn new_ws_api<'a>(endpoint: String) -> WsApi<'a> {
+        let config = Config::default().set_ws_api_endpoint(endpoint);
+        WsApi::new_with_config(Some("api_key".into()), Some(SECRET_KEY.into()), &config)
+    }
+
+    #[test]
+    fn place_order() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let server_requests = requests.clone();
+        let endpoint = serve(move |socket| {
+            let request = read_request(socket);
+            server_requests.lock().unwrap().push(request.clone());
+            let order: Value =
+                serde_json::from_str(include_str!("mocks/account/limit_buy.json")).unwrap();
+            answer(
+                socket,
+                json!({
+                    "id": request["id"],
+                    "status": 200,
+                    "result": order,
+                    "rateLimits": [{
+                        "rateLimitType": "ORDERS",
+                        "interval": "SECOND",
+                        "intervalNum": 10,
+                        "limit": 50,
+                        "count": 1
+                    }]
+                }),
+            );
+        });
+
+        let mut ws_api = new_ws_api(endpoint);
+        ws_api.connect().unwrap();
+        let transaction = ws_api
+            .place_order(
+                "LTCBTC",
+                1,
+                0.1,
+                None,
+                OrderSide::Buy,
+                OrderType::Limit,
+                TimeInForce::GTC,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(transaction.symbol, "LTCBTC");
+        assert_eq!(transaction.order_id, 1);
+        let rate_limits = ws_api.connection.rate_limits();
+        assert_eq!(rate_limits.len(), 1);
+        assert_eq!(rate_limits[0].rate_limit_type, "ORDERS");
+        assert_eq!(rate_limits[0].count, Some(1));
+
+        let request = requests.lock().unwrap()[0].clone();
+        assert_eq!(request["method"], "order.place");
+        let params = &request["params"];
+        assert_eq!(params["apiKey"], "api_key");
+        assert_eq!(params["symbol"], "LTCBTC");
+        assert_eq!(params["side"], "BUY");
+        assert_eq!(params["type"], "LIMIT");
+        assert_eq!(params["timeInForce"], "GTC");
+        assert!(params["timestamp"].is_u64());
+        assert!(params["recvWindow"].is_u64());
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(SECRET_KEY.as_bytes()).unwrap();
+        mac.update(payload(params).as_bytes());
+        let signature = hex::encode(mac.finalize().into_bytes());
+        assert_eq!(params["signature"], signature);
+    }
+
+    #[test]
+    fn error_response() {
+        let endpoint = serve(|socket| {
+            let request = read_request(socket);
+            answer(
+                socket,
+                json!({
+                    "id": request["id"],
+                    "status": 400,
+                    "error": {"code": -2011, "msg": "Unknown order sent."},
+                    "rateLimits": []
+                }),
+            );
+        });
+
+        let mut ws_api = new_ws_api(endpoint);
+        ws_api.connect().unwrap();
+        match ws_api.cancel_order("LTCBTC", 1) {
+            Err(e) => match e.0 {
+                BinanceLibErrorKind::BinanceError(response) => {
+                    assert_eq!(response.code, -2011);
+                    assert_eq!(response.msg, "Unknown order sent.");
+                }
+                kind => panic!("Unexpected error {:?}", kind),
+            },
+            Ok(order) => panic!("Unexpected answer {:?}", order),
+        }
+    }
+
+    #[test]
+    fn callbacks_out_of_order() {
+        let endpoint = serve(|socket| {
+            let first = read_request(socket);
+            let second = read_request(socket);
+            for request in [second, first].iter() {
+                answer(
+                    socket,
+                    json!({
+                        "id": request["id"],
+                        "status": 200,
+                        "result": {"method": request["method"]}
+                    }),
+                );
+            }
+        });
+
+        let answers = Mutex::new(Vec::new());
+        let mut ws_api = new_ws_api(endpoint);
+        ws_api.connect().unwrap();
+        let first = ws_api
+            .connection
+            .send_with_callback("ping", Default::default(), |response| {
+                answers.lock().unwrap().push(response.id.unwrap());
+                Ok(())
+            })
+            .unwrap();
+        let second = ws_api.connection.send("time", Default::default()).unwrap();
+
+        let response = ws_api.connection.wait(second).unwrap();
+        assert_eq!(response.into_result::<Value>().unwrap()["method"], "time");
+        assert!(answers.lock().unwrap().is_empty());
+        ws_api.connection.read_message().unwrap();
+        drop(ws_api);
+        assert_eq!(answers.into_inner().unwrap(), vec![first]);
+    }
+
+    #[test]
+    fn unanswered_request_times_out() {
+        let endpoint = serve(|socket| {
+            read_request(socket);
+            read_request(socket);
+        });
+
+        let mut ws_api = new_ws_api(endpoint);
+        ws_api.connect().unwrap();
+        ws_api
+            .connection
+            .set_response_timeout(Duration::from_millis(100));
+        let with_callback = ws_api
+            .connection
+            .send_with_callback("ping", Default::default(), |_| Ok(()))
+            .unwrap();
+        assert!(ws_api.connection.wait(with_callback).is_err());
+        assert!(ws_api.connection.wait(with_callback + 1).is_err());
+
+        let started = Instant::now();
+        assert!(ws_api
+            .connection
+            .request("time", Default::default())
+            .is_err());
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn responses_not_waited_for_are_dropped() {
+        let endpoint = serve(|socket| {
+            for _ in 0..1001 {
+                let request = read_request(socket);
+                answer(
+                    socket,
+                    json!({"id": request["id"], "status": 200, "result": {}}),
+                );
+            }
+        });
+
+        let mut ws_api = new_ws_api(endpoint);
+        ws_api.connect().unwrap();
+        ws_api
+            .connection
+            .set_response_timeout(Duration::from_millis(100));
+        let ids: Vec<u64> = (0..1001)
+            .map(|_| ws_api.connection.send("ping", Default::default()).unwrap())
+            .collect();
+        assert!(ws_api.connection.wait(ids[1000]).is_ok());
+        assert!(ws_api.connection.wait(ids[0]).is_err());
+        assert!(ws_api.connection.wait(ids[1]).is_ok());
+    }
+
+    #[test]
+    fn user_data_stream() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let server_requests = requests.clone();
+        let endpoint = serve(move |socket| {
+            for result in [logon_result(), json!({})].iter() {
+                let request = read_request(socket);
+                server_requests.lock().unwrap().push(request.clone());
+                answer(
+                    socket,
+                    json!({"id": request["id"], "status": 200, "result": result}),
+                );
+            }
+            let event: Value =
+                serde_json::from_str(include_str!("mocks/websockets/execution_report.json"))
+                    .unwrap();
+            answer(socket, json!({"subscriptionId": 0, "event": event}));
+        });
+
+        let mut events = Vec::new();
+        let mut ws_api = new_ws_api(endpoint);
+        ws_api.set_event_handler(|event: WebsocketEvent| {
+            events.push(event);
+            Ok(())
+        });
+        ws_api.connect().unwrap();
+        let status = ws_api
+            .session_logon(|payload| format!("signed {}", payload))
+            .unwrap();
+        assert_eq!(status.api_key, Some("api_key".into()));
+        ws_api.user_data_stream_subscribe().unwrap();
+        ws_api.connection.read_message().unwrap();
+        drop(ws_api);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0]["method"], "session.logon");
+        let params = &requests[0]["params"];
+        assert_eq!(params["signature"], format!("signed {}", payload(params)));
+        assert_eq!(requests[1]["method"], "userDataStream.subscribe");
+
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            WebsocketEvent::OrderTrade(trade) => {
+                assert_eq!(trade.symbol, "ETHBTC");
+                assert_eq!(trade.order_id, 4293153);
+            }
+            event => panic!("Unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn logged_on_requests_are_not_signed() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let server_requests = requests.clone();
+        let endpoint = serve(move |socket| {
+            let account: Value =
+                serde_json::from_str(include_str!("mocks/account/get_account.json")).unwrap();
+            for result in [logon_result(), account].iter() {
+                let request = read_request(socket);
+                server_requests.lock().unwrap().push(request.clone());
+                answer(
+                    socket,
+                    json!({"id": request["id"], "status": 200, "result": result}),
+                );
+            }
+        });
+
+        let mut ws_api = new_ws_api(endpoint);
+        ws_api.connect().unwrap();
+        ws_api.session_logon(|_| "signature".into()).unwrap();
+        ws_api.account_status().unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[1]["method"], "account.status");
+        let params = requests[1]["params"].as_object().unwrap();
+        assert!(params.contains_key("timestamp"));
+        assert!(!params.contains_key("apiKey"));
+        assert!(!params.contains_key("signature"));
+    }
+}